@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MIT
+
+pragma solidity 0.8.23;
+
+import {Address} from "solidity-utils/contracts/libraries/AddressLib.sol";
+
+import {IBaseEscrow} from "../lib/cross-chain-swap/contracts/interfaces/IBaseEscrow.sol";
+import {ImmutablesLib} from "../lib/cross-chain-swap/contracts/libraries/ImmutablesLib.sol";
+import {Timelocks} from "../lib/cross-chain-swap/contracts/libraries/TimelocksLib.sol";
+import {TimelocksSettersLib} from "../lib/cross-chain-swap/test/utils/libraries/TimelocksSettersLib.sol";
+
+/**
+ * @title Expected hashes of the Stellar conformance vectors.
+ * @notice Prints `ImmutablesLib.hashMem` of each vector in
+ * stellar-resolver/contracts/shared/src/conformance.rs, in the same order:
+ *   forge script contracts/script/ImmutablesVectors.s.sol
+ * The vectors here and there have to be kept in sync by hand.
+ */
+contract ImmutablesVectors {
+    using ImmutablesLib for IBaseEscrow.Immutables;
+
+    function run() external pure returns (bytes32[3] memory hashes) {
+        // Small round numbers
+        hashes[0] = _immutables(
+            0x1111111111111111111111111111111111111111111111111111111111111111,
+            0x2222222222222222222222222222222222222222222222222222222222222222,
+            0x3333333333333333333333333333333333333333,
+            0x4444444444444444444444444444444444444444,
+            0x5555555555555555555555555555555555555555,
+            1000,
+            100,
+            TimelocksSettersLib.init(100, 200, 300, 400, 150, 250, 350, 1000)
+        ).hashMem();
+        // Mainnet-like order: USDC-sized addresses, 18 decimal amounts, 2025 deployment
+        hashes[1] = _immutables(
+            0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f,
+            0x202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f,
+            0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48,
+            0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045,
+            0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2,
+            1e24,
+            1e17,
+            TimelocksSettersLib.init(300, 600, 1800, 3600, 240, 540, 1500, 1_735_689_600)
+        ).hashMem();
+        // Every field at its widest
+        uint32 max = type(uint32).max;
+        hashes[2] = _immutables(
+            bytes32(type(uint256).max),
+            0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee,
+            address(type(uint160).max),
+            address(type(uint160).max),
+            address(type(uint160).max),
+            uint256(uint128(type(int128).max)),
+            1,
+            TimelocksSettersLib.init(max, max, max, max, max, max, max, max)
+        ).hashMem();
+    }
+
+    function _immutables(
+        bytes32 orderHash,
+        bytes32 hashlock,
+        address maker,
+        address taker,
+        address token,
+        uint256 amount,
+        uint256 safetyDeposit,
+        Timelocks timelocks
+    ) private pure returns (IBaseEscrow.Immutables memory) {
+        return IBaseEscrow.Immutables({
+            orderHash: orderHash,
+            hashlock: hashlock,
+            maker: Address.wrap(uint160(maker)),
+            taker: Address.wrap(uint160(taker)),
+            token: Address.wrap(uint160(token)),
+            amount: amount,
+            safetyDeposit: safetyDeposit,
+            timelocks: timelocks
+        });
+    }
+}
@@ -290,16 +290,16 @@ describe('Resolving example', () => {
         }
         console.log('📊 Timelock values:', timelockValues)
         
-        // Pack values exactly like Rust Timelocks::new() does
+        // Pack values like TimelocksLib (and Rust Timelocks::new()): stage i at bits i*32
         const packedValue = 
             (BigInt(timelockValues.deployed_at) << 224n) |
-            (BigInt(timelockValues.src_withdrawal) << 192n) |
-            (BigInt(timelockValues.src_public_withdrawal) << 160n) |
-            (BigInt(timelockValues.src_cancellation) << 128n) |
+            (BigInt(timelockValues.dst_cancellation) << 192n) |
+            (BigInt(timelockValues.dst_public_withdrawal) << 160n) |
+            (BigInt(timelockValues.dst_withdrawal) << 128n) |
             (BigInt(timelockValues.src_public_cancellation) << 96n) |
-            (BigInt(timelockValues.dst_withdrawal) << 64n) |
-            (BigInt(timelockValues.dst_public_withdrawal) << 32n) |
-            BigInt(timelockValues.dst_cancellation)
+            (BigInt(timelockValues.src_cancellation) << 64n) |
+            (BigInt(timelockValues.src_public_withdrawal) << 32n) |
+            BigInt(timelockValues.src_withdrawal)
         
         console.log('📦 Packed timelock value:', packedValue.toString())
         
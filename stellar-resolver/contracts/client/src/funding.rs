@@ -0,0 +1,90 @@
+//! Auth entries for resolver funding flows.
+//!
+//! Soroban has no approve / transferFrom step: `create_dst_escrow` pulls amount and safety
+//! deposit with token transfers made under the relayer's own authorization. The relayer
+//! signs a single tree, `create_dst_escrow` at the root and the factory's transfers as its
+//! sub-invocations. Leaving a transfer out, or getting an argument wrong, fails the whole
+//! call, so these helpers build the tree the same way the factory pulls the funds.
+
+use soroban_sdk::xdr::{
+    self, InvokeContractArgs, ScAddress, ScVal, SorobanAddressCredentials, SorobanAuthorizationEntry,
+    SorobanAuthorizedFunction, SorobanAuthorizedInvocation, SorobanCredentials,
+};
+use soroban_sdk::{Address, Env, IntoVal, TryFromVal, Val, Vec};
+
+use shared::Immutables;
+
+/// Transfers create_dst_escrow makes from the relayer to the factory, as (token, amount).
+/// The safety deposit rides along with the amount unless it's held in XLM
+pub fn dst_funding_transfers(env: &Env, immutables: &Immutables) -> std::vec::Vec<(Address, i128)> {
+    let deposit_token = immutables.deposit_token(env);
+    if deposit_token == immutables.token.stellar {
+        std::vec![(deposit_token, immutables.amount + immutables.safety_deposit)]
+    } else {
+        std::vec![
+            (deposit_token, immutables.safety_deposit),
+            (immutables.token.stellar.clone(), immutables.amount),
+        ]
+    }
+}
+
+/// Invocation tree the relayer authorizes for create_dst_escrow: the call itself, with each
+/// of dst_funding_transfers as a token transfer to the factory under it
+pub fn approve_and_create_dst(
+    env: &Env,
+    factory: &Address,
+    relayer: &Address,
+    immutables: &Immutables,
+    src_cancellation_timestamp: u64,
+    not_after: Option<u64>,
+) -> Result<SorobanAuthorizedInvocation, xdr::Error> {
+    let transfers = dst_funding_transfers(env, immutables)
+        .into_iter()
+        .map(|(token, amount)| {
+            let args = (relayer.clone(), factory.clone(), amount).into_val(env);
+            invocation(env, &token, "transfer", args, std::vec::Vec::new())
+        })
+        .collect::<Result<std::vec::Vec<_>, _>>()?;
+    let args = (relayer.clone(), immutables.clone(), src_cancellation_timestamp, not_after).into_val(env);
+    invocation(env, factory, "create_dst_escrow", args, transfers)
+}
+
+/// Authorization entry carrying the tree for the relayer to sign: the credentials have the
+/// given nonce and expiration ledger and an empty signature, filled in by the relayer's wallet
+pub fn relayer_auth_entry(
+    relayer: &Address,
+    invocation: SorobanAuthorizedInvocation,
+    nonce: i64,
+    signature_expiration_ledger: u32,
+) -> SorobanAuthorizationEntry {
+    SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: ScAddress::from(relayer),
+            nonce,
+            signature_expiration_ledger,
+            signature: ScVal::Void,
+        }),
+        root_invocation: invocation,
+    }
+}
+
+fn invocation(
+    env: &Env,
+    contract: &Address,
+    function: &str,
+    args: Vec<Val>,
+    sub_invocations: std::vec::Vec<SorobanAuthorizedInvocation>,
+) -> Result<SorobanAuthorizedInvocation, xdr::Error> {
+    let args = args
+        .iter()
+        .map(|arg| ScVal::try_from_val(env, &arg).map_err(|_| xdr::Error::Invalid))
+        .collect::<Result<std::vec::Vec<_>, _>>()?;
+    Ok(SorobanAuthorizedInvocation {
+        function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+            contract_address: ScAddress::from(contract),
+            function_name: function.try_into().map_err(|_| xdr::Error::Invalid)?,
+            args: args.try_into()?,
+        }),
+        sub_invocations: sub_invocations.try_into()?,
+    })
+}
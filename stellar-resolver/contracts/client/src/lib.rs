@@ -0,0 +1,145 @@
+//! Client helpers for off-chain Rust resolvers.
+//!
+//! Re-exports the generated contract clients and adds the byte-level helpers a
+//! resolver bot otherwise has to reimplement: building packed timelocks from
+//! durations, computing the hashes both chains agree on, decoding the factory's
+//! events and escrow exports, and building the auth tree of funding calls (`funding`).
+
+use core::time::Duration;
+
+use soroban_sdk::xdr::FromXdr;
+use soroban_sdk::{Address, Bytes, BytesN, Env, Symbol, TryFromVal, Val, Vec};
+
+pub use dst_escrow::DstEscrowClient;
+pub use factory::{EscrowCreated, EscrowExport, EscrowFactoryClient, EscrowType, OrderFilled};
+pub use shared::{EscrowError, EscrowStage, Immutables, OrderFields, OrderId, StageEvent, StellarOrderFields, TimeLockError, Timelocks};
+pub use src_escrow::SrcEscrowClient;
+
+use shared::{order, other_immutables as immutables, statemachine, timelocks};
+
+/// Timelock schedule as durations after the escrow deployment
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Schedule {
+    pub src_withdrawal: Duration,
+    pub src_public_withdrawal: Duration,
+    pub src_cancellation: Duration,
+    pub src_public_cancellation: Duration,
+    pub dst_withdrawal: Duration,
+    pub dst_public_withdrawal: Duration,
+    pub dst_cancellation: Duration,
+}
+
+impl Schedule {
+    /// Pack the schedule for an escrow deployed at `deployed_at`, checking the stage ordering
+    /// the contracts enforce. Sub-second parts of the durations are dropped
+    pub fn timelocks(&self, env: &Env, deployed_at: u32) -> Result<Timelocks, TimeLockError> {
+        let offset = |duration: Duration| u32::try_from(duration.as_secs()).map_err(|_| TimeLockError::TimelockOffsetTooLarge);
+        let timelocks = Timelocks::new(
+            env,
+            deployed_at,
+            offset(self.src_withdrawal)?,
+            offset(self.src_public_withdrawal)?,
+            offset(self.src_cancellation)?,
+            offset(self.src_public_cancellation)?,
+            offset(self.dst_withdrawal)?,
+            offset(self.dst_public_withdrawal)?,
+            offset(self.dst_cancellation)?,
+        );
+        timelocks::validate_timelocks(&timelocks, env)?;
+        Ok(timelocks)
+    }
+}
+
+/// Hashlock committing to a secret, keccak256 like on the EVM side
+pub fn hashlock(env: &Env, secret: &BytesN<32>) -> BytesN<32> {
+    env.crypto().keccak256(&Bytes::from_array(env, &secret.to_array())).into()
+}
+
+/// Canonical order_hash, same as the factory's compute_order_hash
+pub fn order_hash(env: &Env, fields: &OrderFields) -> Result<BytesN<32>, EscrowError> {
+    order::hash(env, fields)
+}
+
+/// order_hash of an order placed on Stellar, same as the factory's compute_stellar_order_hash
+pub fn stellar_order_hash(env: &Env, fields: &StellarOrderFields) -> BytesN<32> {
+    order::stellar_hash(env, fields)
+}
+
+/// Escrow key of an order at one factory, same as its get_escrow_key on the network
+/// with this passphrase
+pub fn escrow_key(env: &Env, factory: &Address, network_passphrase: &Bytes, order_hash: &BytesN<32>) -> BytesN<32> {
+    let network_id: BytesN<32> = env.crypto().sha256(network_passphrase).into();
+    order::escrow_key(env, &order::domain_separator(env, factory, &network_id), order_hash)
+}
+
+/// Hash of the immutables, matching Solidity's ImmutablesLib.hash
+pub fn immutables_hash(env: &Env, immutables: &Immutables) -> Result<BytesN<32>, TimeLockError> {
+    immutables::hash(env, immutables)
+}
+
+/// Bytes the immutables hash is computed over, same as the factory's encode_immutables
+pub fn encode_immutables(env: &Env, immutables: &Immutables) -> Result<Bytes, TimeLockError> {
+    immutables::encode(env, immutables)
+}
+
+/// Topics of an event without the factory's event tag (see set_event_tag), for an event
+/// expected to carry len topics
+fn untagged(topics: &Vec<Val>, len: u32) -> Vec<Val> {
+    match topics.len() == len + 1 {
+        true => topics.slice(1..),
+        false => topics.clone(),
+    }
+}
+
+/// Decode a ("escrow", order_hash, stage) lifecycle event, None for any other event.
+/// Events of a tagged factory decode the same, as do the other decoders'
+pub fn decode_stage_event(env: &Env, topics: &Vec<Val>, data: &Val) -> Option<(BytesN<32>, EscrowStage, StageEvent)> {
+    let topics = untagged(topics, 3);
+    if topics.len() != 3 || Symbol::try_from_val(env, &topics.get(0)?).ok()? != statemachine::STAGE_EVENT_TOPIC {
+        return None;
+    }
+    let order_hash = BytesN::<32>::try_from_val(env, &topics.get(1)?).ok()?;
+    let stage = EscrowStage::try_from_val(env, &topics.get(2)?).ok()?;
+    let payload = StageEvent::try_from_val(env, data).ok()?;
+    Some((order_hash, stage, payload))
+}
+
+/// Decode an ("OrderFill", order_hash) event emitted by fill_order, None for any other event
+pub fn decode_order_filled(env: &Env, topics: &Vec<Val>, data: &Val) -> Option<(BytesN<32>, OrderFilled)> {
+    let topics = untagged(topics, 2);
+    if topics.len() != 2 || Symbol::try_from_val(env, &topics.get(0)?).ok()? != factory::ORDER_FILLED_TOPIC {
+        return None;
+    }
+    let order_hash = BytesN::<32>::try_from_val(env, &topics.get(1)?).ok()?;
+    let payload = OrderFilled::try_from_val(env, data).ok()?;
+    Some((order_hash, payload))
+}
+
+/// Decode a ("SrcCreate" / "DstCreate", order_hash) event emitted by escrow creation, None
+/// for any other event
+pub fn decode_escrow_created(env: &Env, topics: &Vec<Val>, data: &Val) -> Option<(BytesN<32>, EscrowType, EscrowCreated)> {
+    let topics = untagged(topics, 2);
+    if topics.len() != 2 {
+        return None;
+    }
+    let escrow_type = match Symbol::try_from_val(env, &topics.get(0)?).ok()? {
+        topic if topic == factory::SRC_CREATED_TOPIC => EscrowType::Source,
+        topic if topic == factory::DST_CREATED_TOPIC => EscrowType::Destination,
+        _ => return None,
+    };
+    let order_hash = BytesN::<32>::try_from_val(env, &topics.get(1)?).ok()?;
+    let payload = EscrowCreated::try_from_val(env, data).ok()?;
+    Some((order_hash, escrow_type, payload))
+}
+
+/// Decode the bytes export_escrow returned, None if they're malformed or were written
+/// in a format version this client doesn't know
+pub fn decode_escrow_export(env: &Env, bytes: &Bytes) -> Option<EscrowExport> {
+    let export = EscrowExport::from_xdr(env, bytes).ok()?;
+    (export.version == factory::ESCROW_EXPORT_VERSION).then_some(export)
+}
+
+pub mod funding;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,283 @@
+#![cfg(test)]
+
+use super::*;
+use factory::EscrowFactory;
+use shared::{DualAddress, LopOrder, Stage};
+use soroban_sdk::testutils::{Address as _, Events, Ledger};
+use soroban_sdk::token::StellarAssetClient;
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Address, Vec};
+
+extern crate std;
+
+fn schedule() -> Schedule {
+    Schedule {
+        src_withdrawal: Duration::from_secs(100),
+        src_public_withdrawal: Duration::from_secs(200),
+        src_cancellation: Duration::from_secs(300),
+        src_public_cancellation: Duration::from_secs(400),
+        dst_withdrawal: Duration::from_secs(150),
+        dst_public_withdrawal: Duration::from_secs(250),
+        dst_cancellation: Duration::from_secs(350),
+    }
+}
+
+fn dual(env: &Env, evm: u8, stellar: Address) -> DualAddress {
+    DualAddress {
+        evm: BytesN::from_array(env, &[evm; 20]),
+        stellar,
+    }
+}
+
+fn test_immutables(env: &Env, token: Address) -> Immutables {
+    let secret = BytesN::from_array(env, &[0x42; 32]);
+    Immutables {
+        order_hash: BytesN::from_array(env, &[0x01; 32]),
+        hashlock: hashlock(env, &secret),
+        hashlocks: Vec::new(env),
+        cancel_hashlock: None,
+        sponsor: None,
+        maker: dual(env, 0x11, Address::generate(env)),
+        taker: dual(env, 0x22, Address::generate(env)),
+        token: dual(env, 0x33, token),
+        amount: 1000,
+        safety_deposit: 100,
+        timelocks: schedule().timelocks(env, 1000).unwrap(),
+        expiration: 0,
+        rescue_delay: 0,
+        finality_lag: 0,
+        src_chain_id: 0,
+        dst_chain_id: 0,
+        parts: Vec::new(env),
+        native_deposit: false,
+        deposit_decay_bps: 0,
+        stellar_origin: false,
+        lop_order: LopOrder::Unrecorded,
+        attestation_required: false,
+        parent: None,
+    }
+}
+
+#[test]
+fn test_schedule_packs_like_timelocks_new() {
+    let env = Env::default();
+    let timelocks = schedule().timelocks(&env, 1000).unwrap();
+    assert_eq!(timelocks, Timelocks::new(&env, 1000, 100, 200, 300, 400, 150, 250, 350));
+    assert_eq!(timelocks::get(&timelocks, &env, Stage::DstCancellation), Ok(1350));
+}
+
+#[test]
+fn test_schedule_rejects_bad_durations() {
+    let env = Env::default();
+
+    let mut too_long = schedule();
+    too_long.src_public_cancellation = Duration::from_secs(u32::MAX as u64 + 1);
+    assert_eq!(too_long.timelocks(&env, 1000), Err(TimeLockError::TimelockOffsetTooLarge));
+
+    let mut out_of_order = schedule();
+    out_of_order.dst_withdrawal = Duration::from_secs(300);
+    assert_eq!(
+        out_of_order.timelocks(&env, 1000),
+        Err(TimeLockError::InvalidDestinationChainTimelockOrdering)
+    );
+}
+
+#[test]
+fn test_hashes_match_factory_views() {
+    let env = Env::default();
+    let factory_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let factory = EscrowFactoryClient::new(&env, &factory_id);
+    let immutables = test_immutables(&env, Address::generate(&env));
+
+    assert_eq!(encode_immutables(&env, &immutables).unwrap(), factory.encode_immutables(&immutables));
+    let encoded = factory.encode_immutables(&immutables);
+    let digest: BytesN<32> = env.crypto().keccak256(&encoded).into();
+    assert_eq!(immutables_hash(&env, &immutables).unwrap(), digest);
+
+    let fields = OrderFields {
+        maker: immutables.maker.evm.clone(),
+        taker: immutables.taker.evm.clone(),
+        maker_asset: immutables.token.evm.clone(),
+        taker_asset: BytesN::from_array(&env, &[0x44; 20]),
+        making_amount: 1000,
+        taking_amount: 2500,
+        salt: BytesN::from_array(&env, &[0x55; 32]),
+        src_chain_id: 1,
+        dst_chain_id: 2,
+    };
+    assert_eq!(order_hash(&env, &fields).unwrap(), factory.compute_order_hash(&fields));
+
+    let stellar_fields = StellarOrderFields {
+        maker: immutables.maker.stellar.clone(),
+        maker_evm: None,
+        maker_asset: immutables.token.stellar.clone(),
+        taker_asset: BytesN::from_array(&env, &[0x44; 20]),
+        receiver: BytesN::from_array(&env, &[0x66; 20]),
+        making_amount: 1000,
+        taking_amount: 2500,
+        salt: BytesN::from_array(&env, &[0x55; 32]),
+        src_chain_id: 0,
+        dst_chain_id: 0,
+    };
+    assert_eq!(stellar_order_hash(&env, &stellar_fields), factory.compute_stellar_order_hash(&stellar_fields));
+}
+
+#[test]
+fn test_realistic_orders_escrow_and_hash_like_the_factory() {
+    use test_fixtures::vectors::{self, STELLAR_DECIMALS};
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let factory_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let factory = EscrowFactoryClient::new(&env, &factory_id);
+    let secret = BytesN::from_array(&env, &[0x42; 32]);
+    let (maker, resolver) = (Address::generate(&env), Address::generate(&env));
+
+    // 2500 USDC and 1.5 WETH, in the 7 decimals of their Stellar assets
+    let orders = [
+        (vectors::USDC, vectors::units(2500, STELLAR_DECIMALS)),
+        (vectors::WETH, vectors::units(15, STELLAR_DECIMALS - 1)),
+    ];
+    for (i, (token, amount)) in orders.into_iter().enumerate() {
+        let stellar_token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let mut immutables = vectors::realistic_immutables(&env, &token, stellar_token, hashlock(&env, &secret), amount);
+        immutables.order_hash = BytesN::from_array(&env, &[i as u8 + 1; 32]);
+        // Both orders come from the same dev accounts, whose mappings must not change
+        immutables.maker.stellar = maker.clone();
+        immutables.taker.stellar = resolver.clone();
+        let digest: BytesN<32> = env.crypto().keccak256(&factory.encode_immutables(&immutables)).into();
+        assert_eq!(immutables_hash(&env, &immutables).unwrap(), digest);
+        factory.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    }
+}
+
+#[test]
+fn test_dst_funding_auth_tree_matches_create_dst_escrow() {
+    use soroban_sdk::testutils::AuthorizedInvocation;
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let factory_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let factory = EscrowFactoryClient::new(&env, &factory_id);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let relayer = Address::generate(&env);
+    StellarAssetClient::new(&env, &token).mint(&relayer, &1100);
+    let immutables = test_immutables(&env, token.clone());
+    assert_eq!(funding::dst_funding_transfers(&env, &immutables), std::vec![(token, 1100)]);
+
+    // The relayer's whole auth tree: create_dst_escrow with the token transfer under it
+    let tree = funding::approve_and_create_dst(&env, &factory_id, &relayer, &immutables, 2000, None).unwrap();
+    factory.create_dst_escrow(&relayer, &immutables, &2000, &None);
+    assert_eq!(env.auths(), std::vec![(relayer.clone(), AuthorizedInvocation::from_xdr(&env, &tree))]);
+    assert_eq!(tree.sub_invocations.len(), 1);
+    let entry = funding::relayer_auth_entry(&relayer, tree.clone(), 7, 1000);
+    assert_eq!(entry.root_invocation, tree);
+
+    // An XLM safety deposit is a second transfer, from the native SAC
+    let mut native = test_immutables(&env, Address::generate(&env));
+    native.native_deposit = true;
+    let transfers = funding::dst_funding_transfers(&env, &native);
+    assert_eq!(transfers, std::vec![(native.deposit_token(&env), 100), (native.token.stellar.clone(), 1000)]);
+    let tree = funding::approve_and_create_dst(&env, &factory_id, &relayer, &native, 2000, Some(1500)).unwrap();
+    assert_eq!(tree.sub_invocations.len(), 2);
+}
+
+#[test]
+fn test_escrow_key_is_namespaced_by_factory_and_network() {
+    let env = Env::default();
+    let passphrase = Bytes::from_slice(&env, b"Test SDF Network ; September 2015");
+    let network_id: BytesN<32> = env.crypto().sha256(&passphrase).into();
+    env.ledger().set_network_id(network_id.to_array());
+    let testnet = EscrowFactoryClient::new(&env, &env.register(EscrowFactory, (Address::generate(&env), 86400u64)));
+    let staging = EscrowFactoryClient::new(&env, &env.register(EscrowFactory, (Address::generate(&env), 86400u64)));
+    let order_hash = BytesN::from_array(&env, &[0x01; 32]);
+    let order_id = OrderId::EvmKeccak(order_hash.clone());
+
+    let key = testnet.get_escrow_key(&order_id);
+    assert_eq!(escrow_key(&env, &testnet.address, &passphrase, &order_hash), key);
+    assert_ne!(staging.get_escrow_key(&order_id), key);
+    let mainnet = Bytes::from_slice(&env, b"Public Global Stellar Network ; September 2015");
+    assert_ne!(escrow_key(&env, &testnet.address, &mainnet, &order_hash), key);
+}
+
+#[test]
+fn test_decodes_factory_events() {
+    // Same results whether or not the factory tags its events
+    for tag in [None, Some("desk_a")] {
+        decodes_factory_events(tag);
+    }
+}
+
+fn decodes_factory_events(tag: Option<&str>) {
+    let env = Env::default();
+    env.mock_all_auths();
+    let factory_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let factory = EscrowFactoryClient::new(&env, &factory_id);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let resolver = Address::generate(&env);
+    StellarAssetClient::new(&env, &token).mint(&resolver, &1100);
+    if let Some(tag) = tag {
+        factory.set_event_tag(&Vec::new(&env), &Some(Symbol::new(&env, tag)));
+    }
+
+    let immutables = test_immutables(&env, token);
+    factory.fill_order(&resolver, &immutables, &2000, &resolver);
+
+    let mut stages = std::vec::Vec::new();
+    let mut creations = std::vec::Vec::new();
+    let mut fills = std::vec::Vec::new();
+    for (_, topics, data) in env.events().all().iter() {
+        if let Some((order_hash, stage, payload)) = decode_stage_event(&env, &topics, &data) {
+            stages.push((order_hash, stage, payload.amount));
+        }
+        if let Some((order_hash, escrow_type, created)) = decode_escrow_created(&env, &topics, &data) {
+            creations.push((order_hash, escrow_type, created.immutables_hash, created.timelocks));
+        }
+        if let Some(fill) = decode_order_filled(&env, &topics, &data) {
+            fills.push(fill);
+        }
+    }
+    assert_eq!(stages, std::vec![(immutables.order_hash.clone(), EscrowStage::Created, 1000)]);
+    assert_eq!(
+        creations,
+        std::vec![(
+            immutables.order_hash.clone(),
+            EscrowType::Destination,
+            immutables_hash(&env, &immutables).unwrap(),
+            immutables.timelocks.packed_value.clone()
+        )]
+    );
+    assert_eq!(fills.len(), 1);
+    let (order_hash, filled) = fills.pop().unwrap();
+    assert_eq!(order_hash, immutables.order_hash);
+    assert_eq!(filled.hashlock, immutables.hashlock);
+    assert_eq!(filled.deployed_at, 1000);
+}
+
+#[test]
+fn test_decodes_escrow_export() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let factory_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let factory = EscrowFactoryClient::new(&env, &factory_id);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let resolver = Address::generate(&env);
+    StellarAssetClient::new(&env, &token).mint(&resolver, &1100);
+
+    let immutables = test_immutables(&env, token);
+    factory.fill_order(&resolver, &immutables, &2000, &resolver);
+
+    let export = decode_escrow_export(&env, &factory.export_escrow(&immutables.order_id())).unwrap();
+    assert_eq!(export.order_hash, immutables.order_hash);
+    assert_eq!(export.escrow_type, factory::EscrowType::Destination);
+    assert_eq!(export.status.stage, EscrowStage::Created);
+    assert_eq!(export.locked, 1100);
+    assert_eq!(export.remaining_amount, 1000);
+    assert_eq!(export.immutables.hashlock, immutables.hashlock);
+
+    // Formats from another version, or anything else, aren't decoded
+    let mut future = export.clone();
+    future.version += 1;
+    assert!(decode_escrow_export(&env, &future.to_xdr(&env)).is_none());
+    assert!(decode_escrow_export(&env, &Bytes::from_array(&env, &[0u8; 8])).is_none());
+}
@@ -1,6 +1,8 @@
 use shared::{
-    only_after, only_before, only_taker, only_valid_secret, other_immutables as immutables,
-    timelocks, uni_transfer, BaseEscrowTrait, DualAddress, EscrowError as Error, Immutables, Stage,
+    get_revealed_secret, only_bound_immutables, only_taker, only_valid_secret, other_immutables as immutables,
+    record_revealed_secret, in_cancel_window, in_public_withdraw_window, in_withdraw_window,
+    events, timelocks, uni_transfer, get_stage, only_stage_allows, advance_stage, BaseEscrowTrait, EscrowError as Error, EscrowStage, EscrowType, Immutables,
+    Asset, PayoutSummary,
 };
 use soroban_sdk::{contract, contractimpl, symbol_short, Address, BytesN, Env, Symbol};
 
@@ -9,11 +11,11 @@ pub struct DstEscrow;
 
 #[contractimpl]
 impl DstEscrow {
-    /// Public initialization function called after deployment
-    pub fn init(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Symbol {
+    /// Public initialization function called after deployment, only once
+    pub fn init(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Result<Symbol, Error> {
         // Use the BaseEscrow initialize method
-        <shared::baseescrow::BaseEscrow as BaseEscrowTrait>::initialize(env, factory, rescue_delay, immutables);
-        symbol_short!("init_ok")
+        <shared::baseescrow::BaseEscrow as BaseEscrowTrait>::initialize(env, factory, rescue_delay, immutables)?;
+        Ok(symbol_short!("init_ok"))
     }
 
     /// 🔄 PUBLIC WITHDRAWAL: Anyone can withdraw with secret (lines 48-54 in Solidity)
@@ -21,20 +23,16 @@ impl DstEscrow {
         env: Env,
         secret: BytesN<32>,
         immutables: Immutables,
-    ) -> Result<(), Error> {
+    ) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Withdrawn)?;
         only_valid_secret(&env, &secret, &immutables)?;
         dst_validate_immutables(&env, &immutables)?;
 
-        // Different timelock: DstPublicWithdrawal → DstCancellation window
-        let public_start = timelocks::get(&immutables.timelocks, &env, Stage::DstPublicWithdrawal)
-            .map_err(|_| Error::TimeLockError)?;
-        let public_end = timelocks::get(&immutables.timelocks, &env, Stage::DstCancellation)
-            .map_err(|_| Error::TimeLockError)?;
-        only_after(&env, public_start)?;
-        only_before(&env, public_end)?;
+        // Different timelock: DstPublicWithdrawal → DstCancellation window. The finality lag
+        // delays every withdrawal path, public ones included
+        in_public_withdraw_window(&env, &immutables.timelocks, &EscrowType::Destination, immutables.finality_lag)?;
 
-        _dst_withdraw(&env, secret, &immutables)?;
-        Ok(())
+        _dst_withdraw(&env, secret, &immutables)
     }
     fn get_rescue_delay(env: Env) -> u64 {
         env.storage()
@@ -51,53 +49,64 @@ impl DstEscrow {
     }
 
     // 🔄 PRIVATE WITHDRAWAL: Only taker can withdraw (lines 34-41 in Solidity)
-    pub fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables) -> Result<(), Error> {
+    pub fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Withdrawn)?;
         only_taker(&env, &immutables)?;
         only_valid_secret(&env, &secret, &immutables)?;
         dst_validate_immutables(&env, &immutables)?;
 
         // Different timelock: DstWithdrawal → DstCancellation window
-        let withdraw_start = timelocks::get(&immutables.timelocks, &env, Stage::DstWithdrawal)
-            .map_err(|_| Error::TimeLockError)?;
-        let withdraw_end = timelocks::get(&immutables.timelocks, &env, Stage::DstCancellation)
-            .map_err(|_| Error::TimeLockError)?;
-        only_after(&env, withdraw_start)?;
-        only_before(&env, withdraw_end)?;
-
-        _dst_withdraw(&env, secret, &immutables)?;
-        Ok(())
+        in_withdraw_window(&env, &immutables.timelocks, &EscrowType::Destination, immutables.finality_lag)?;
+
+        _dst_withdraw(&env, secret, &immutables)
     }
 
-    pub fn cancel(env: Env, immutables: Immutables) -> Result<(), Error> {
+    pub fn cancel(env: Env, immutables: Immutables) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Cancelled)?;
         only_taker(&env, &immutables)?;
         dst_validate_immutables(&env, &immutables)?;
 
         // Can only cancel AFTER DstCancellation time (line 65 in Solidity)
-        let cancel_time = timelocks::get(&immutables.timelocks, &env, Stage::DstCancellation)
-            .map_err(|_| Error::TimeLockError)?;
-        only_after(&env, cancel_time)?;
+        in_cancel_window(&env, &immutables.timelocks, &EscrowType::Destination)?;
 
         let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
             .ok_or(Error::AddressMappingMissing)?;
+        let deposit_token = if immutables.native_deposit { Asset::native_address(&env) } else { stellar_token.clone() };
         let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm)
             .ok_or(Error::AddressMappingMissing)?;
 
         // ✅ CRITICAL: In cancel, funds go back to TAKER (resolver), not maker (lines 67-68)
         uni_transfer(&env, &stellar_token, &stellar_taker, immutables.amount)?;
-        // Safety deposit to taker (simplified for now)
+        // Safety deposit back to the sponsor if one provided it, else to taker (simplified for now)
+        let deposit_recipient = immutables.sponsor.clone().unwrap_or(stellar_taker.clone());
         uni_transfer(
             &env,
-            &stellar_token,
-            &stellar_taker,
+            &deposit_token,
+            &deposit_recipient,
             immutables.safety_deposit,
         )?;
 
-        env.events()
-            .publish((symbol_short!("Cancelled"),), immutables.amount);
-        Ok(())
+        advance_stage(&env, &immutables.order_hash, EscrowStage::Cancelled, immutables.amount)?;
+        events::publish(&env, (symbol_short!("Cancelled"),), immutables.amount);
+        Ok(PayoutSummary {
+            recipient: stellar_taker,
+            token: stellar_token,
+            amount: immutables.amount,
+            safety_deposit_recipient: deposit_recipient,
+            safety_deposit_token: deposit_token,
+            safety_deposit_amount: immutables.safety_deposit,
+        })
     }
 
 
+    pub fn dst_revealed_secret(env: Env, hashlock: BytesN<32>) -> Option<BytesN<32>> {
+        get_revealed_secret(&env, &hashlock)
+    }
+
+    pub fn dst_stage(env: Env) -> EscrowStage {
+        get_stage(&env)
+    }
+
     pub fn dst_rescue_delay(env: Env) -> u64 {
         Self::get_rescue_delay(env)
     }
@@ -108,9 +117,10 @@ impl DstEscrow {
 }
 
 // ✅ CRITICAL: Funds go to MAKER (user), not taker (lines 82, 93 in Solidity)
-fn _dst_withdraw(env: &Env, secret: BytesN<32>, immutables: &Immutables) -> Result<(), Error> {
+fn _dst_withdraw(env: &Env, secret: BytesN<32>, immutables: &Immutables) -> Result<PayoutSummary, Error> {
     let stellar_token = immutables::get_stellar_addr(env, &immutables.token.evm)
         .ok_or(Error::AddressMappingMissing)?;
+    let deposit_token = if immutables.native_deposit { Asset::native_address(env) } else { stellar_token.clone() };
     let stellar_maker = immutables::get_stellar_addr(env, &immutables.maker.evm)
         .ok_or(Error::AddressMappingMissing)?;
 
@@ -120,25 +130,30 @@ fn _dst_withdraw(env: &Env, secret: BytesN<32>, immutables: &Immutables) -> Resu
     // For now, send to maker (simplified)
     uni_transfer(
         env,
-        &stellar_token,
+        &deposit_token,
         &stellar_maker,
         immutables.safety_deposit,
     )?;
 
-    env.events()
-        .publish((symbol_short!("Withdraw"), secret), immutables.amount);
-    Ok(())
+    advance_stage(env, &immutables.order_hash, EscrowStage::Withdrawn, immutables.amount)?;
+    record_revealed_secret(env, &secret);
+    events::publish(env, (symbol_short!("Withdraw"), secret), immutables.amount);
+    Ok(PayoutSummary {
+        recipient: stellar_maker.clone(),
+        token: stellar_token,
+        amount: immutables.amount,
+        safety_deposit_recipient: stellar_maker,
+        safety_deposit_token: deposit_token,
+        safety_deposit_amount: immutables.safety_deposit,
+    })
 }
 
 fn dst_validate_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-    if immutables.amount <= 0 || immutables.safety_deposit <= 0 {
-        return Err(Error::InvalidImmutables);
-    }
+    immutables.checked_amount()?;
+    immutables.checked_safety_deposit()?;
     timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
-    if immutables::hash(env, immutables).map_err(|_| Error::TimeLockError)? != immutables.order_hash
-    {
-        return Err(Error::InvalidImmutables);
-    }
+    // Must be the immutables the escrow was initialized with, not substituted parameters
+    only_bound_immutables(env, immutables)?;
     if immutables::get_stellar_addr(env, &immutables.maker.evm).is_none()
         || immutables::get_stellar_addr(env, &immutables.taker.evm).is_none()
         || immutables::get_stellar_addr(env, &immutables.token.evm).is_none()
@@ -6,7 +6,7 @@
 mod dstescrow;
 
 // Re-export the contract
-pub use dstescrow::DstEscrow;
+pub use dstescrow::{DstEscrow, DstEscrowClient};
 
 #[cfg(test)]
 mod test;
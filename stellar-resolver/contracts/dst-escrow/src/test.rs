@@ -1,57 +1,9 @@
 #![cfg(test)]
 
 use super::*;
-use shared::{
-    other_immutables as immutables, timelocks, BaseEscrowTrait, DualAddress, EscrowError,
-    Immutables, Stage, Timelocks,
-};
 use soroban_sdk::testutils::{Address as _, Ledger};
 use soroban_sdk::{Address, BytesN, Env};
-
-// Helper functions for testing
-fn create_test_dual_address(env: &Env) -> DualAddress {
-    let e = Env::default();
-    let evm_addr = BytesN::from_array(env, &[0x42; 20]);
-    let stellar_addr = Address::generate(&e);
-    DualAddress {
-        evm: evm_addr,
-        stellar: stellar_addr,
-    }
-}
-
-fn create_test_secret(env: &Env) -> (BytesN<32>, BytesN<32>) {
-    let secret = BytesN::from_array(env, &[0x42; 32]);
-    // Create hashlock by hashing the secret
-    let secret_bytes = soroban_sdk::Bytes::from_array(env, &secret.to_array());
-    let hashlock = env.crypto().keccak256(&secret_bytes).into();
-    (secret, hashlock)
-}
-
-fn create_test_immutables_with_secret(
-    env: &Env,
-    _secret: BytesN<32>,
-    hashlock: BytesN<32>,
-) -> Immutables {
-    Immutables {
-        order_hash: BytesN::from_array(env, &[0x01; 32]),
-        hashlock,
-        maker: create_test_dual_address(env),
-        taker: create_test_dual_address(env),
-        token: create_test_dual_address(env),
-        amount: 1000,
-        safety_deposit: 100,
-        timelocks: Timelocks::new(
-            env, 1000, // deployed_at
-            100,  // src_withdrawal
-            200,  // src_public_withdrawal
-            300,  // src_cancellation
-            400,  // src_public_cancellation
-            150,  // dst_withdrawal
-            250,  // dst_public_withdrawal
-            350,  // dst_cancellation
-        ),
-    }
-}
+use test_fixtures::{create_test_immutables_with_secret, create_test_secret};
 
 // ===== DSTESCROW TESTS =====
 
@@ -103,3 +55,100 @@ fn test_dstescrow_public_withdraw_timelock() {
         // Should pass timelock validation but may fail on other checks
     });
 }
+
+#[test]
+fn test_dstescrow_rejects_substituted_immutables() {
+    use shared::EscrowError;
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DstEscrow, ());
+    let client = DstEscrowClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    for (party, byte) in [(&mut immutables.maker, 0x11), (&mut immutables.taker, 0x22), (&mut immutables.token, 0x33)] {
+        party.evm = BytesN::from_array(&env, &[byte; 20]);
+    }
+    client.init(&Address::generate(&env), &86_400, &immutables);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+
+    // A larger amount than the escrow was initialized with
+    let mut substituted = immutables.clone();
+    substituted.amount = 5000;
+    assert_eq!(client.try_cancel(&substituted), Err(Ok(EscrowError::ImmutablesMismatch)));
+
+    // Amounts are checked before the binding
+    let mut negative = immutables.clone();
+    negative.safety_deposit = -1;
+    assert_eq!(client.try_cancel(&negative), Err(Ok(EscrowError::InvalidImmutables)));
+}
+
+#[test]
+fn test_dstescrow_init_only_once() {
+    use shared::EscrowError;
+
+    let env = Env::default();
+    let contract_id = env.register(DstEscrow, ());
+    let client = DstEscrowClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    for (party, byte) in [(&mut immutables.maker, 0x11), (&mut immutables.taker, 0x22), (&mut immutables.token, 0x33)] {
+        party.evm = BytesN::from_array(&env, &[byte; 20]);
+    }
+    let factory = Address::generate(&env);
+    client.init(&factory, &86_400, &immutables);
+
+    let mut replaced = immutables.clone();
+    replaced.amount = 1;
+    assert_eq!(
+        client.try_init(&Address::generate(&env), &0, &replaced),
+        Err(Ok(EscrowError::AlreadyInitialized))
+    );
+    assert_eq!(client.dst_factory(), factory);
+    assert_eq!(client.dst_rescue_delay(), 86_400);
+}
+
+#[test]
+fn test_dstescrow_pays_out_once() {
+    use shared::{EscrowError, EscrowStage};
+    use soroban_sdk::token::{StellarAssetClient, TokenClient};
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DstEscrow, ());
+    let client = DstEscrowClient::new(&env, &contract_id);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    // Holds more than one order's worth, e.g. a stray second deposit
+    StellarAssetClient::new(&env, &token).mint(&contract_id, &2200);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    for (party, byte) in [(&mut immutables.maker, 0x11), (&mut immutables.taker, 0x22), (&mut immutables.token, 0x33)] {
+        party.evm = BytesN::from_array(&env, &[byte; 20]);
+    }
+    immutables.token.stellar = token.clone();
+    client.init(&Address::generate(&env), &86_400, &immutables);
+    assert_eq!(client.dst_stage(), EscrowStage::Created);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1175;
+    });
+    client.withdraw(&secret, &immutables);
+    assert_eq!(client.dst_stage(), EscrowStage::Withdrawn);
+    assert_eq!(client.try_withdraw(&secret, &immutables), Err(Ok(EscrowError::InvalidStageTransition)));
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1275;
+    });
+    assert_eq!(client.try_public_withdraw(&secret, &immutables), Err(Ok(EscrowError::InvalidStageTransition)));
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+    assert_eq!(client.try_cancel(&immutables), Err(Ok(EscrowError::InvalidStageTransition)));
+    assert_eq!(TokenClient::new(&env, &token).balance(&contract_id), 1100);
+}
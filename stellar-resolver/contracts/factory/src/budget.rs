@@ -0,0 +1,208 @@
+#![cfg(test)]
+
+//! Cost measurements for the factory entry points.
+//!
+//! Each scenario fills the factory with a number of existing escrows (every one adding
+//! its maker and taker to the address map), then meters create/withdraw/cancel/rescue
+//! with `env.cost_estimate().budget()`. Costs are asserted against the Soroban per
+//! transaction limits. Native test execution underestimates the Wasm costs, so the
+//! limits are only a ceiling, the printed numbers show how costs grow with state.
+
+use super::*;
+use shared::{immutables::DataKey as MappingKey, DualAddress, Immutables, LopOrder, Timelocks};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::token::StellarAssetClient;
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::testutils::EnvTestConfig;
+use soroban_sdk::{Address, Bytes, BytesN, Env, Map, Vec};
+
+extern crate std;
+
+// Soroban network limits per transaction and per ledger entry
+const TX_MAX_CPU_INSTRUCTIONS: u64 = 100_000_000;
+const TX_MAX_MEMORY_BYTES: u64 = 41_943_040;
+const MAX_CONTRACT_DATA_ENTRY_SIZE_BYTES: u32 = 65_536;
+
+const EXISTING_ESCROWS: [u32; 3] = [0, 25, 100];
+
+struct Cost {
+    cpu: u64,
+    mem: u64,
+}
+
+struct Bench {
+    env: Env,
+    factory: Address,
+    token: DualAddress,
+    secret: BytesN<32>,
+    next: u32,
+}
+
+impl Bench {
+    fn new() -> Self {
+        // Snapshots of hundreds of escrows would only bloat the repository
+        let env = Env::new_with_config(EnvTestConfig { capture_snapshot_at_drop: false });
+        env.mock_all_auths();
+        env.ledger().with_mut(|ledger| ledger.timestamp = 1000);
+        let factory = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        StellarAssetClient::new(&env, &token_address).mint(&factory, &i128::MAX);
+        let token = DualAddress {
+            evm: BytesN::from_array(&env, &[0xee; 20]),
+            stellar: token_address,
+        };
+        let secret = BytesN::from_array(&env, &[0x42; 32]);
+        Bench { env, factory, token, secret, next: 0 }
+    }
+
+    fn client(&self) -> EscrowFactoryClient<'_> {
+        EscrowFactoryClient::new(&self.env, &self.factory)
+    }
+
+    // Every escrow gets a fresh maker and taker, so each one grows the address map
+    fn party(&mut self) -> DualAddress {
+        self.next += 1;
+        let mut evm = [0u8; 20];
+        evm[16..20].copy_from_slice(&self.next.to_be_bytes());
+        DualAddress {
+            evm: BytesN::from_array(&self.env, &evm),
+            stellar: Address::generate(&self.env),
+        }
+    }
+
+    fn immutables(&mut self) -> Immutables {
+        let env = self.env.clone();
+        let maker = self.party();
+        let taker = self.party();
+        let mut order_hash = [0u8; 32];
+        order_hash[28..32].copy_from_slice(&self.next.to_be_bytes());
+        Immutables {
+            order_hash: BytesN::from_array(&env, &order_hash),
+            hashlock: env.crypto().keccak256(&Bytes::from_array(&env, &self.secret.to_array())).into(),
+            hashlocks: Vec::new(&env),
+            cancel_hashlock: None,
+            sponsor: None,
+            maker,
+            taker,
+            token: self.token.clone(),
+            amount: 1000,
+            safety_deposit: 100,
+            timelocks: Timelocks::new(&env, 1000, 100, 200, 300, 400, 150, 250, 350),
+            expiration: 0,
+            rescue_delay: 0,
+            finality_lag: 0,
+            src_chain_id: 0,
+            dst_chain_id: 0,
+            parts: Vec::new(&env),
+            native_deposit: false,
+            deposit_decay_bps: 0,
+            stellar_origin: false,
+            lop_order: LopOrder::Unrecorded,
+            attestation_required: false,
+            parent: None,
+        }
+    }
+
+    fn create(&mut self) -> Immutables {
+        let immutables = self.immutables();
+        self.client().create_src_escrow(&Address::generate(&self.env), &immutables, &None);
+        immutables
+    }
+
+    fn populate(&mut self, escrows: u32) {
+        for _ in 0..escrows {
+            self.create();
+        }
+    }
+
+    fn at(&self, timestamp: u64) {
+        self.env.ledger().with_mut(|ledger| ledger.timestamp = timestamp);
+    }
+
+    // Budget of the last top level invocation
+    fn last_cost(&self) -> Cost {
+        let budget = self.env.cost_estimate().budget();
+        Cost {
+            cpu: budget.cpu_instruction_cost(),
+            mem: budget.memory_bytes_cost(),
+        }
+    }
+
+    fn address_map_size(&self) -> u32 {
+        self.env.as_contract(&self.factory, || {
+            let map: Map<BytesN<20>, Address> = self.env.storage().persistent()
+                .get(&MappingKey::AddressMap)
+                .unwrap_or(Map::new(&self.env));
+            map.to_xdr(&self.env).len()
+        })
+    }
+}
+
+fn check(entry_point: &str, existing: u32, cost: &Cost) {
+    std::println!("{entry_point:>8} with {existing:>3} existing escrows: {:>10} cpu {:>9} mem", cost.cpu, cost.mem);
+    assert!(cost.cpu < TX_MAX_CPU_INSTRUCTIONS, "{entry_point} cpu over the transaction limit");
+    assert!(cost.mem < TX_MAX_MEMORY_BYTES, "{entry_point} memory over the transaction limit");
+}
+
+#[test]
+fn test_create_cost() {
+    for existing in EXISTING_ESCROWS {
+        let mut bench = Bench::new();
+        bench.populate(existing);
+        bench.create();
+        check("create", existing, &bench.last_cost());
+    }
+}
+
+#[test]
+fn test_withdraw_cost() {
+    for existing in EXISTING_ESCROWS {
+        let mut bench = Bench::new();
+        bench.populate(existing);
+        let immutables = bench.create();
+        bench.at(1150);
+        bench.client().withdraw(&immutables.order_id(), &bench.secret, &None);
+        check("withdraw", existing, &bench.last_cost());
+    }
+}
+
+#[test]
+fn test_cancel_cost() {
+    for existing in EXISTING_ESCROWS {
+        let mut bench = Bench::new();
+        bench.populate(existing);
+        let immutables = bench.create();
+        bench.at(1350);
+        bench.client().cancel(&immutables.order_id());
+        check("cancel", existing, &bench.last_cost());
+    }
+}
+
+#[test]
+fn test_rescue_cost() {
+    for existing in EXISTING_ESCROWS {
+        let mut bench = Bench::new();
+        bench.populate(existing);
+        let immutables = bench.create();
+        bench.at(1000 + 86400);
+        bench.client().rescue_funds(&immutables.order_id(), &100);
+        check("rescue", existing, &bench.last_cost());
+    }
+}
+
+// The address map is a single ledger entry growing with every new party, so it bounds
+// how many distinct addresses the factory can ever map
+#[test]
+fn test_address_map_entry_size() {
+    let mut bench = Bench::new();
+    bench.populate(1);
+    let base = bench.address_map_size();
+    bench.populate(*EXISTING_ESCROWS.last().unwrap());
+    let size = bench.address_map_size();
+
+    let mappings = 2 * EXISTING_ESCROWS.last().unwrap();
+    let per_mapping = (size - base) / mappings;
+    let capacity = MAX_CONTRACT_DATA_ENTRY_SIZE_BYTES / per_mapping;
+    std::println!("address map: {size} bytes for {} mappings, {per_mapping} bytes each, room for about {capacity}", mappings + 3);
+    assert!(size < MAX_CONTRACT_DATA_ENTRY_SIZE_BYTES, "address map over the ledger entry size limit");
+}
@@ -1,11 +1,12 @@
 #![no_std]
 
 /// Cross-chain escrow factory that manages multiple escrow states internally
-use soroban_sdk::{contract, contractimpl, symbol_short, Address, BytesN, Env, Symbol, contracttype, token};
+use soroban_sdk::{contract, contracterror, contractimpl, panic_with_error, symbol_short, xdr::{FromXdr, ToXdr}, Address, Bytes, BytesN, Env, String, Symbol, TryFromVal, Val, Vec, U256, contracttype, Map};
 use shared::{
-    Immutables, EscrowError as Error, only_taker, only_valid_secret, only_before, only_after, uni_transfer,
-    other_immutables as immutables, timelocks, Stage
+    DualAddress, Immutables, EscrowError as Error, PayoutSummary, only_taker, only_valid_preimage, only_valid_cancel_secret, only_after, uni_transfer, in_private_withdraw_window_within, in_public_withdraw_window, in_cancel_window, in_public_cancel_window, record_revealed_secret, get_revealed_secret, record_revealed_preimage, get_revealed_preimage,
+    other_immutables as immutables, events, timelocks, ttl, Timelocks, Stage, statemachine, permit, crypto, order, Asset, EscrowAmount, OrderFields, OrderId, Permit, PermitAction, StellarOrderFields
 };
+pub use shared::{EscrowStage, EscrowType};
 
 #[contract]
 pub struct EscrowFactory;
@@ -13,308 +14,3397 @@ pub struct EscrowFactory;
 // Storage keys for factory configuration
 const ADMIN: Symbol = symbol_short!("admin");
 const RESCUE_DELAY: Symbol = symbol_short!("rsc_delay");
+const ATTESTED_MAPPINGS: Symbol = symbol_short!("att_maps");
+const TOKEN_POLICY: Symbol = symbol_short!("tkn_plcy");
+const FINALITY_LAG: Symbol = symbol_short!("fin_lag");
+const ADMIN_SIGNERS: Symbol = symbol_short!("adm_sgnrs");
+const ADMIN_THRESHOLD: Symbol = symbol_short!("adm_thrsh");
+const DEPOSIT_SPLIT: Symbol = symbol_short!("dep_split");
+const RELAYER_ONLY: Symbol = symbol_short!("rly_only");
+const WATCHDOGS: Symbol = symbol_short!("watchdogs");
+const STATS: Symbol = symbol_short!("stats");
+const MAX_HORIZON: Symbol = symbol_short!("max_hrzn");
+const MIGRATION_KEY: Symbol = symbol_short!("mig_key");
+const NATIVE_TOKEN: Symbol = symbol_short!("evm_nativ");
+const DEPOSIT_DECAY: Symbol = symbol_short!("dep_decay");
+const ESCROW_TTL: Symbol = symbol_short!("esc_ttl");
+const INITIALIZED: Symbol = symbol_short!("init");
+const CONFIG_FROZEN: Symbol = symbol_short!("cfg_frzn");
+const DEFAULT_TIMELOCKS: Symbol = symbol_short!("def_tlcks");
+const PRIVATE_SECRETS: Symbol = symbol_short!("prv_scrts");
+const SWEEP: Symbol = symbol_short!("sweep");
+const ATTESTOR: Symbol = symbol_short!("attestor");
+const PENDING_CHANGES: Symbol = symbol_short!("prm_chgs");
+const TIMESTAMP_TOLERANCE: Symbol = symbol_short!("ts_tolrnc");
+const SUPPORTED_CHAINS: Symbol = symbol_short!("chains");
+const PAUSED_TOKENS: Symbol = symbol_short!("pausd_tkn");
+
+/// First topic of the event fill_order emits: ("OrderFill", order_hash)
+pub const ORDER_FILLED_TOPIC: Symbol = symbol_short!("OrderFill");
+/// First topics of the events escrow creation emits: ("SrcCreate" / "DstCreate", order_hash)
+pub const SRC_CREATED_TOPIC: Symbol = symbol_short!("SrcCreate");
+pub const DST_CREATED_TOPIC: Symbol = symbol_short!("DstCreate");
+/// First topic of the event every cancel path emits: ("Cancelled", order_hash)
+pub const CANCELLED_TOPIC: Symbol = symbol_short!("Cancelled");
+
+/// Max timelock horizon until the admin sets one: the last stage must open within 30 days
+pub const DEFAULT_MAX_TIMELOCK_HORIZON: u64 = 30 * 24 * 60 * 60;
+
+/// Version of the export_escrow format, bumped whenever EscrowExport changes shape
+pub const ESCROW_EXPORT_VERSION: u32 = 1;
+
+/// Layout new escrow records are stored with, bumped whenever EscrowRecord changes shape.
+/// Records stored before versioning read as version 0
+pub const ESCROW_SCHEMA_VERSION: u32 = 1;
+
+/// Most parts a partial withdrawal schedule can have, one bit each in the withdrawn mask
+pub const MAX_WITHDRAWAL_PARTS: u32 = 32;
+
+/// Most address mappings list_address_mappings returns per call
+pub const MAX_MAPPINGS_PAGE: u32 = 100;
+
+/// Longest a relayer can reserve an order id for with reserve_order (10 minutes)
+pub const MAX_ORDER_RESERVATION: u64 = 10 * 60;
+
+/// Time between announcing a sweep of stale escrows and executing it, so their participants
+/// can still rescue, cancel or withdraw in between (7 days)
+pub const SWEEP_ANNOUNCEMENT_DELAY: u64 = 7 * 24 * 60 * 60;
+
+/// Largest timestamp drift tolerance the admin can set, in seconds
+pub const MAX_TIMESTAMP_TOLERANCE: u64 = 120;
+
+/// Most escrows that can declare the same parent leg
+pub const MAX_CHILDREN: u32 = 16;
+
+/// Time between announcing a parameter change and when it can be executed (3 days)
+pub const PARAM_CHANGE_DELAY: u64 = 3 * 24 * 60 * 60;
+
+// Basis points denominator for ratios
+const BPS_DENOMINATOR: u32 = 10_000;
+
+// Escrow records are kept alive for this many ledgers after each action until the admin sets
+// another TTL (~60 days at 5s/ledger)
+const ESCROW_TTL_LEDGERS: u32 = 1_036_800;
+
+// Number of most recent actions kept in an escrow's history
+const HISTORY_LIMIT: u32 = 16;
 
 // Storage key type for individual escrow states
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum EscrowDataKey {
-    // Escrow state keyed by order_hash (cross-chain consistency)
+    // Escrow state keyed by order_hash (cross-chain consistency), written once at creation
     EscrowState(BytesN<32>),
-    // Escrow stage tracking keyed by order_hash
-    EscrowStage(BytesN<32>),
+    // Small mutable status record keyed by order_hash, rewritten on every transition
+    EscrowStatus(BytesN<32>),
+    // ed25519 public key a maker signs permits with
+    PermitKey(Address),
+    // Next permit nonce expected from a maker
+    PermitNonce(Address),
+    // Token contract present on the allow/deny list
+    TokenListed(Address),
+    // Token whose new escrows are paused whatever the token policy
+    TokenPaused(Address),
+    // Fixed rebate paid in a deposit token to executors of public actions
+    ExecutorRebate(Address),
+    // Balance of a deposit token funded for executor rebates
+    RebatePool(Address),
+    // order_hashes of the escrows declaring an escrow as their parent leg
+    Children(BytesN<32>),
+    // Order size limits for a token
+    TokenLimits(Address),
+    // Bounded log of the latest actions on an escrow keyed by order_hash
+    EscrowHistory(BytesN<32>),
+    // Amount plus safety deposit held by active escrows of a token, plus unclaimed payouts
+    TotalLocked(Address),
+    // Relayer allowed to create escrows
+    Relayer(Address),
+    // Tokens minted into an escrow through fund_escrow keyed by order_hash
+    Funded(BytesN<32>),
+    // Risk parameters of a chain orders may name as their source or destination chain
+    SupportedChain(u64),
+    // Payout that couldn't be pushed to its recipient, keyed by (recipient, token)
+    Claimable(Address, Address),
+    // Address mappings an order's creation set or changed, keyed by order_hash
+    OrderMappings(BytesN<32>),
+    // Bitmask of the withdrawn parts of an escrow's partial withdrawal schedule
+    WithdrawnParts(BytesN<32>),
+    // Watchdog bonded to execute an order's public actions keyed by order_hash
+    Watchdog(BytesN<32>),
+    // Why a cancelled escrow was cancelled keyed by order_hash
+    CancelReason(BytesN<32>),
+    // Running volume and deposit totals of a token
+    TokenStats(Address),
+    // Operator the taker delegated withdrawals of an escrow to keyed by order_hash
+    Operator(BytesN<32>),
+    // Ledger, timestamp and relayer of an escrow's creation keyed by order_hash
+    Creation(BytesN<32>),
+    // (decimals, symbol) of an escrow's token as read at creation keyed by order_hash
+    TokenMetadata(BytesN<32>),
+    // (relayer, expires_at) of a short reservation of an order id not created yet
+    Reservation(BytesN<32>),
+    // Payout and time of the withdraw, cancel or expire that settled an escrow keyed by order_hash
+    Receipt(BytesN<32>),
+    // Tokens a partial rescue_funds already moved out of a Stuck escrow keyed by order_hash
+    RescuedAmount(BytesN<32>),
+    // Set on escrows the admin marked Stuck, whose rescues belong to the funder keyed by order_hash
+    MarkedStuck(BytesN<32>),
+    // Attestor key that attested a claimant, valid while it is still the factory's attestor
+    Attestation(Address),
+}
+
+// Stored escrow state. Records written before schema versions existed are a bare
+// (EscrowType, Immutables) tuple and stay readable as version 0 without a migration
+#[contracttype]
+#[derive(Clone)]
+pub struct EscrowRecord {
+    pub schema_version: u32,
+    pub escrow_type: EscrowType,
+    pub immutables: Immutables,
+}
+
+// How the token list is applied when creating escrows
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TokenPolicy {
+    Open,      // Any token can be escrowed
+    AllowList, // Only listed tokens can be escrowed
+    DenyList,  // Listed tokens can't be escrowed
+}
+
+// Per-token order size limits
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenLimits {
+    pub min_amount: i128,            // Smallest accepted swap amount
+    pub max_amount: i128,            // Largest accepted swap amount, 0 = uncapped
+    pub min_safety_deposit_bps: u32, // Minimum safety deposit as basis points of the amount
+}
+
+// Kind of action recorded in an escrow's history
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EscrowAction {
+    Create,
+    Fund,
+    Withdraw,
+    Cancel,
+    Expire,
+    Rescue,
+    Replace,
+    Import,
+    Reassign,
+}
+
+// Why an escrow was cancelled, recorded next to its status and sent with the Cancelled event
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CancelReason {
+    MakerRequested = 1,  // Maker and taker agreed to unwind (cancel secret or permit)
+    Timeout = 2,         // Cancellation timelock passed without a withdrawal
+    ResolverDefault = 3, // Resolver missed its windows, cancelled in the public phase
+    Emergency = 4,       // Maker pulled out over an incident
+    ParentCancelled = 5, // Parent leg of a multi-leg swap was unwound, see cancel_orphan
+}
+
+impl CancelReason {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(CancelReason::MakerRequested),
+            2 => Some(CancelReason::Timeout),
+            3 => Some(CancelReason::ResolverDefault),
+            4 => Some(CancelReason::Emergency),
+            5 => Some(CancelReason::ParentCancelled),
+            _ => None,
+        }
+    }
+}
+
+// Payload of the Cancelled event
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowCancelled {
+    pub reason: CancelReason,
+    pub recipient: Address,                // Receiver of the returned amount, the escrow's funder
+    pub amount: i128,
+    pub safety_deposit_recipient: Address,
+    pub safety_deposit_amount: i128,
+}
+
+// One entry of an escrow's history
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActionRecord {
+    pub action: EscrowAction,
+    pub caller: Address, // Party the action was performed by or on behalf of
+    pub timestamp: u64,
+    pub amount: i128,    // Tokens moved (or escrowed, for Create)
+}
+
+// Outcome of a simulated operation
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DryRunResult {
+    pub success: bool,                 // Whether the real call would succeed
+    pub error_code: u32,               // EscrowError code when it wouldn't, 0 otherwise
+    pub stage: EscrowStage,            // Stage the escrow would move to
+    pub recipient: Option<Address>,    // Receiver of the swap amount
+    pub safety_deposit_recipient: Option<Address>, // Receiver of the safety deposit
+    pub amount: i128,                  // Swap amount that would be paid out
+    pub safety_deposit_amount: i128,   // Safety deposit that would be paid out
+}
+
+// Share of executor safety deposit bounties kept by the protocol treasury
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositSplit {
+    pub treasury: Address,
+    pub treasury_bps: u32, // Treasury share in basis points, the caller keeps the rest
+}
+
+// Parameters only changed through announce_change / execute_change
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Param {
+    RescueDelay,
+    DepositSplit,
+    DepositDecay,
+}
+
+// A parameter together with its new value
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParamChange {
+    RescueDelay(u64),           // Default rescue delay in seconds
+    DepositSplit(DepositSplit), // Treasury share of public path bounties
+    DepositDecay(u32),          // Deposit share forfeited at the end of the window, in basis points
+}
+
+impl ParamChange {
+    pub fn param(&self) -> Param {
+        match self {
+            ParamChange::RescueDelay(_) => Param::RescueDelay,
+            ParamChange::DepositSplit(_) => Param::DepositSplit,
+            ParamChange::DepositDecay(_) => Param::DepositDecay,
+        }
+    }
+}
+
+// Announced parameter change waiting for its delay
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingChange {
+    pub change: ParamChange,
+    pub executable_at: u64,
+}
+
+// Risk parameters of a supported chain. An order is held to the stricter values of
+// its two chains, a zero leaves the factory-wide setting in place
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChainConfig {
+    pub finality_lag: u64,         // Minimum wait after the withdrawal stage opens
+    pub min_safety_deposit: i128,  // Smallest accepted safety deposit
+    pub fee_bps: u32,              // Treasury share of safety deposit bounties in basis points
+}
+
+// Factory configuration in one snapshot, as get_config returns it. The factory keeps escrows
+// in its own storage and deploys no contracts, so there are no wasm hashes to report.
+// Per-token settings (token list entries, limits, executor rebates) and the default timelock
+// template have their own getters
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FactoryConfig {
+    pub admin: Address,
+    pub admin_signers: Vec<Address>,
+    pub admin_threshold: u32,
+    pub rescue_delay: u64,                     // Default rescue delay in seconds
+    pub config_frozen: bool,
+    pub paused_tokens: Vec<Address>,           // Tokens new escrows can't be created for
+    pub treasury: Option<Address>,             // Deposit split treasury, None = no split
+    pub treasury_bps: u32,                     // Treasury share of public path bounties
+    pub deposit_decay_bps: u32,
+    pub supported_chains: Map<u64, ChainConfig>,
+    pub finality_lag: u64,                     // Default wait for counterpart chain finality
+    pub max_timelock_horizon: u64,
+    pub timestamp_tolerance: u64,
+    pub escrow_ttl: u32,                       // Ledgers every escrow action keeps records alive for
+    pub token_policy: TokenPolicy,
+    pub relayer_only: bool,
+    pub watchdog_min_bond: Option<i128>,       // None = watchdog registrations closed
+    pub watchdog_priority_window: u64,
+}
+
+// Sweep of stale escrows announced by the admin, executable from executable_at on
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SweepAnnouncement {
+    pub order_hashes: Vec<BytesN<32>>,
+    pub recipient: Address,
+    pub executable_at: u64,
+}
+
+// Terms watchdogs register under
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatchdogConfig {
+    pub min_bond: i128,       // Smallest bond, in the order's token
+    pub priority_window: u64, // Seconds after a public action opens during which only the watchdog may execute it
+}
+
+// Watchdog registered for an order
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatchdogBond {
+    pub watchdog: Address,
+    pub bond: i128,
+}
+
+// Complete escrow record as export_escrow serializes it
+#[contracttype]
+#[derive(Clone)]
+pub struct EscrowExport {
+    pub version: u32,              // ESCROW_EXPORT_VERSION of the exporting factory
+    pub order_hash: BytesN<32>,
+    pub escrow_type: EscrowType,
+    pub immutables: Immutables,    // With the Stellar addresses resolved at creation
+    pub status: EscrowStatus,
+    pub funded: i128,              // Tokens minted in through fund_escrow
+    pub remaining_amount: i128,    // Swap amount not paid out yet
+    pub locked: i128,              // Amount plus safety deposit still held
+    pub withdrawn_parts: u32,      // Bitmask of withdrawn schedule parts
+    pub cancel_reason: u32,        // CancelReason code, 0 while not cancelled
+    pub history: Vec<ActionRecord>, // Latest actions with their timestamps
+    pub exported_at: u64,          // Ledger timestamp and sequence of the export
+    pub exported_at_ledger: u32,
+}
+
+// Where an escrow was created, kept next to its record
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CreationRecord {
+    pub ledger: u32,      // Ledger sequence the escrow was created (or imported) in
+    pub timestamp: u64,   // Its close time
+    pub relayer: Address, // Relayer that created it, the admin for imports
+}
+
+// Errors only the factory raises, kept out of the shared escrow errors, which are at the 50
+// cases a contract spec allows. Codes continue after the shared ones; factory functions keep
+// returning the shared error type and fail with these through panic_with_error!
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum FactoryError {
+    AlreadySettled = 51,      // Escrow (or part) was already withdrawn with this secret, get_settlement_receipt has the payout
+    ChangeNotAnnounced = 52,  // No change of the parameter is pending
+    NotSourceEscrow = 53,     // Only source escrows support the call
+    AttestationRequired = 54, // The order only pays attested claimants and the recipient isn't one
+    EscrowExists = 55,        // An escrow was already created under the order id
+}
+
+// Who received what and when, written once by the withdraw, cancel or expire settling an escrow
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SettlementReceipt {
+    pub stage: EscrowStage,     // Withdrawn, Cancelled or Expired
+    pub payout: PayoutSummary,  // Transfers the settlement made
+    pub ledger: u32,            // Ledger sequence it settled in
+    pub timestamp: u64,         // Its close time
+}
+
+// Escrow record plus its creation context, as get_escrow_info returns it
+#[contracttype]
+#[derive(Clone)]
+pub struct EscrowInfo {
+    pub escrow_type: EscrowType,
+    pub immutables: Immutables,
+    pub status: EscrowStatus,
+    pub created_ledger: u32,        // 0 for escrows created before creation records existed
+    pub created_at: u64,
+    pub created_by: Option<Address>,
+    pub confirmations: u32,         // Ledgers closed since the creation ledger
+    // Token metadata cached at creation so clients can scale amounts without reading the token,
+    // None if the token has none or the escrow predates the cache
+    pub token_decimals: Option<u32>,
+    pub token_symbol: Option<String>,
+}
+
+// Escrow counts, maintained on every creation and transition
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EscrowStats {
+    pub source: u32,      // Source escrows ever created
+    pub destination: u32, // Destination escrows ever created
+    pub created: u32,     // Escrows currently in each stage
+    pub withdrawn: u32,
+    pub cancelled: u32,
+    pub rescued: u32,
+    pub expired: u32,
+    pub stuck: u32,
+    pub partially_withdrawn: u32,
+}
+
+// Running totals of a token
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenStats {
+    pub volume: i128,                // Swap amount of every escrow created
+    pub safety_deposits_held: i128,  // Safety deposits not paid out yet
+}
+
+// Tracked versus actual holdings of a token
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolvencyReport {
+    pub total_locked: i128, // What active escrows should hold
+    pub balance: i128,      // What the factory actually holds
+    pub solvent: bool,      // balance >= total_locked
 }
 
+// Payload of the OrderFilled event, everything needed to match the fill to the source chain order
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum EscrowType {
-    Source,
-    Destination,
+pub struct OrderFilled {
+    pub hashlock: BytesN<32>,
+    pub maker: BytesN<20>,  // EVM addresses, as they appear in the source chain order
+    pub taker: BytesN<20>,
+    pub token: BytesN<20>,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub deployed_at: u32,
 }
 
+// Payload of the SrcCreate / DstCreate events, enough for the EVM-side relayer to check the
+// escrow against the order without reading it back
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum EscrowStage {
-    Created,
-    Withdrawn,
-    Cancelled,
-    Rescued,
+pub struct EscrowCreated {
+    pub amount: i128,
+    pub immutables_hash: BytesN<32>, // Solidity-compatible hash of the immutables
+    pub hashlock: BytesN<32>,
+    pub taker: BytesN<20>,           // EVM addresses, as they appear in the order
+    pub token: BytesN<20>,
+    pub safety_deposit: i128,
+    pub timelocks: U256,             // Packed timelocks, deployed_at included
+}
+
+// Stage offsets in seconds after deployment, filled into the immutables of escrows created
+// through create_*_escrow_with_defaults
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimelockTemplate {
+    pub src_withdrawal: u32,
+    pub src_public_withdrawal: u32,
+    pub src_cancellation: u32,
+    pub src_public_cancellation: u32,
+    pub dst_withdrawal: u32,
+    pub dst_public_withdrawal: u32,
+    pub dst_cancellation: u32,
+}
+
+// Mutable part of an escrow, kept apart from the immutables so transitions
+// only read and write this small record
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowStatus {
+    pub stage: EscrowStage,
+    pub filled_amount: i128,    // Swap amount paid out so far
+    pub live_until_ledger: u32, // Ledger the escrow records are kept alive until as of the last transition, later actions extend it
 }
 
 #[contractimpl]
 impl EscrowFactory {
     /// Initialize factory with admin and default rescue delay
-    pub fn __constructor(env: Env, admin: Address, rescue_delay: u64) {
+    pub fn __constructor(env: Env, admin: Address, rescue_delay: u64) -> Result<(), Error> {
+        // Deployment runs this once, the flag keeps any later init path (e.g. after an upgrade)
+        // from replacing the admin or the rescue delay
+        if Self::is_initialized(env.clone()) {
+            return Err(Error::AlreadyInitialized);
+        }
         env.storage().instance().set(&ADMIN, &admin);
         env.storage().instance().set(&RESCUE_DELAY, &rescue_delay);
+        env.storage().instance().set(&INITIALIZED, &true);
+        Ok(())
+    }
+
+    pub fn is_initialized(env: Env) -> bool {
+        env.storage().instance().get(&INITIALIZED).unwrap_or(false)
+    }
+
+    /// Permanently lock the rescue delay, the fee parameters (deposit split and decay, chain
+    /// fees, executor rebates) and the settings read while an order is open (supported chains,
+    /// finality lag, timestamp tolerance, watchdog config), so users don't have to trust the
+    /// admin not to change them under open orders (admin only). Settings only read when an
+    /// order is created stay adjustable
+    pub fn freeze_config(env: Env, approvers: Vec<Address>) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        env.storage().instance().set(&CONFIG_FROZEN, &true);
+        events::publish(&env, (symbol_short!("CfgFrozen"),), ());
+        Ok(())
+    }
+
+    pub fn is_config_frozen(env: Env) -> bool {
+        env.storage().instance().get(&CONFIG_FROZEN).unwrap_or(false)
+    }
+
+    /// Create source chain escrow - stores immutables and returns factory address.
+    /// Fails once the ledger timestamp passes not_after, if one is given.
+    /// In relayer-only mode the relayer must be registered and authorize the call
+    pub fn create_src_escrow(
+        env: Env,
+        relayer: Address,
+        immutables: Immutables,
+        not_after: Option<u64>,
+    ) -> Result<Address, Error> {
+        // Stellar-origin orders are checked against their fields, see create_stellar_src_escrow
+        if immutables.stellar_origin {
+            return Err(Error::InvalidImmutables);
+        }
+        Self::create_escrow(&env, EscrowType::Source, &relayer, immutables, not_after)?;
+        Ok(env.current_contract_address())
+    }
+
+    /// Create a source escrow like create_src_escrow, with timelocks taken from the template set
+    /// by set_default_timelocks and deployed at the current ledger time. The immutables'
+    /// timelocks are ignored, get_escrow_state returns the ones stored
+    pub fn create_src_escrow_with_defaults(
+        env: Env,
+        relayer: Address,
+        immutables: Immutables,
+        not_after: Option<u64>,
+    ) -> Result<Address, Error> {
+        let immutables = Immutables { timelocks: Self::default_timelocks(&env)?, ..immutables };
+        Self::create_src_escrow(env, relayer, immutables, not_after)
+    }
+
+    /// Create the source escrow of an order placed on Stellar, swapping into an EVM chain.
+    /// The immutables must be flagged stellar_origin and match the order: its order_hash is
+    /// compute_stellar_order_hash(order), maker, maker_asset and making_amount are the
+    /// escrow's. The maker authorizes the call, so it needs no EVM address; without one
+    /// immutables.maker.evm is zero and no mapping is made for it
+    pub fn create_stellar_src_escrow(
+        env: Env,
+        relayer: Address,
+        order: StellarOrderFields,
+        immutables: Immutables,
+        not_after: Option<u64>,
+    ) -> Result<Address, Error> {
+        let maker_evm = order.maker_evm.clone().unwrap_or(BytesN::from_array(&env, &[0; 20]));
+        if !immutables.stellar_origin
+            || immutables.order_hash != order::stellar_hash(&env, &order)
+            || immutables.maker.stellar != order.maker
+            || immutables.maker.evm != maker_evm
+            || immutables.token.stellar != order.maker_asset
+            || immutables.amount != order.making_amount
+            || immutables.src_chain_id != order.src_chain_id
+            || immutables.dst_chain_id != order.dst_chain_id
+        {
+            return Err(Error::InvalidImmutables);
+        }
+        order.maker.require_auth();
+        Self::create_escrow(&env, EscrowType::Source, &relayer, immutables, not_after)?;
+        Ok(env.current_contract_address())
+    }
+
+    /// Create destination chain escrow - stores immutables and pulls amount plus safety deposit
+    /// from the relayer, like createDstEscrow on EVM. Fails with InsufficientDeposit unless the
+    /// factory's balances grew by exactly that much, and once the ledger timestamp passes
+    /// not_after, if one is given. In relayer-only mode the relayer must also be registered.
+    /// As on EVM, the dst escrow must be cancellable no later than the src escrow, whose
+    /// cancellation starts at src_cancellation_timestamp, otherwise it's an InvalidCreationTime
+    pub fn create_dst_escrow(
+        env: Env,
+        relayer: Address,
+        immutables: Immutables,
+        src_cancellation_timestamp: u64,
+        not_after: Option<u64>,
+    ) -> Result<Address, Error> {
+        Self::only_cancellable_before_src(&env, &immutables, src_cancellation_timestamp)?;
+        Self::create_escrow(&env, EscrowType::Destination, &relayer, immutables.clone(), not_after)?;
+        // In relayer-only mode create_escrow already required it
+        if !Self::get_relayer_only(env.clone()) {
+            relayer.require_auth();
+        }
+        let amount = Self::pull_funding(&env, &immutables, &relayer)?;
+        Self::track_funding(&env, &immutables.order_hash, relayer, amount);
+        Ok(env.current_contract_address())
+    }
+
+    /// Create a destination escrow like create_dst_escrow, with timelocks from the default
+    /// template, see create_src_escrow_with_defaults
+    pub fn create_dst_escrow_with_defaults(
+        env: Env,
+        relayer: Address,
+        immutables: Immutables,
+        src_cancellation_timestamp: u64,
+        not_after: Option<u64>,
+    ) -> Result<Address, Error> {
+        let immutables = Immutables { timelocks: Self::default_timelocks(&env)?, ..immutables };
+        Self::create_dst_escrow(env, relayer, immutables, src_cancellation_timestamp, not_after)
+    }
+
+    /// Register an EVM -> Stellar mapping attested by both keys: an EIP-191 signature
+    /// from the EVM address over get_mapping_message() and auth from the Stellar address
+    pub fn register_address_mapping(
+        env: Env,
+        evm: BytesN<20>,
+        stellar: Address,
+        signature: BytesN<65>,
+    ) -> Result<(), Error> {
+        stellar.require_auth();
+        let message = Self::get_mapping_message(env.clone(), evm.clone(), stellar.clone());
+        crypto::verify_evm_signature(&env, &message, &signature, &evm)?;
+        immutables::map_and_log(&env, evm.clone(), stellar.clone(), &stellar, None)?;
+        events::publish(&env, (symbol_short!("AddrReg"), evm), stellar);
+        Ok(())
+    }
+
+    /// Point an EVM address at a different Stellar address (admin only). Escrows created
+    /// afterwards resolve to the new address, existing ones keep the address they stored
+    pub fn remap_address(env: Env, approvers: Vec<Address>, evm: BytesN<20>, stellar: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        let caller = approvers.first().unwrap_or(Self::get_admin(env.clone()));
+        immutables::remap_and_log(&env, evm, stellar, &caller);
+        Ok(())
+    }
+
+    /// Page of the EVM -> Stellar address map in EVM address order, at most MAX_MAPPINGS_PAGE
+    /// entries from the start-th one, for operators auditing the table for poisoned entries
+    pub fn list_address_mappings(env: Env, start: u32, limit: u32) -> Vec<DualAddress> {
+        immutables::list_mappings(&env, start, limit.min(MAX_MAPPINGS_PAGE))
+    }
+
+    /// Number of EVM addresses with a mapping, to page through list_address_mappings
+    pub fn get_address_mapping_count(env: Env) -> u32 {
+        immutables::mapping_count(&env)
+    }
+
+    /// Bytes the EVM key signs to attest a mapping: factory address XDR || evm (20) || stellar address XDR
+    pub fn get_mapping_message(env: Env, evm: BytesN<20>, stellar: Address) -> Bytes {
+        let mut message = env.current_contract_address().to_xdr(&env);
+        message.extend_from_array(&evm.to_array());
+        message.append(&stellar.to_xdr(&env));
+        message
+    }
+
+    /// Require attested maker/taker mappings at creation instead of taking them from Immutables (admin only)
+    pub fn set_attested_mappings(env: Env, approvers: Vec<Address>, enabled: bool) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&ATTESTED_MAPPINGS, &enabled);
+        Ok(())
+    }
+
+    pub fn get_attested_mappings(env: Env) -> bool {
+        env.storage().instance().get(&ATTESTED_MAPPINGS).unwrap_or(false)
+    }
+
+    /// Keep revealed secrets out of events (admin only): withdrawals then publish
+    /// ("WithdrwHl", hashlock) instead of ("Withdraw", secret), and counterpart legs pull the
+    /// secret from get_revealed_secret / get_revealed_preimage
+    pub fn set_private_secrets(env: Env, approvers: Vec<Address>, enabled: bool) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&PRIVATE_SECRETS, &enabled);
+        Ok(())
+    }
+
+    pub fn get_private_secrets(env: Env) -> bool {
+        env.storage().instance().get(&PRIVATE_SECRETS).unwrap_or(false)
+    }
+
+    /// Tag every event of this factory with an extra first topic (admin only), so indexers
+    /// shared by several deployments can filter per deployment. None removes the tag
+    pub fn set_event_tag(env: Env, approvers: Vec<Address>, tag: Option<Symbol>) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        match &tag {
+            Some(tag) => env.storage().instance().set(&events::EVENT_TAG_KEY, tag),
+            None => env.storage().instance().remove(&events::EVENT_TAG_KEY),
+        }
+        events::publish(&env, (symbol_short!("EventTag"),), tag);
+        Ok(())
+    }
+
+    pub fn get_event_tag(env: Env) -> Option<Symbol> {
+        events::tag(&env)
+    }
+
+    /// Accept orders whose EVM token is address(0), the EVM native token sentinel (admin only).
+    /// Zeroed maker and taker addresses are always rejected
+    pub fn set_native_token_allowed(env: Env, approvers: Vec<Address>, allowed: bool) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&NATIVE_TOKEN, &allowed);
+        Ok(())
+    }
+
+    pub fn get_native_token_allowed(env: Env) -> bool {
+        env.storage().instance().get(&NATIVE_TOKEN).unwrap_or(false)
+    }
+
+    /// Only let registered relayers create escrows (admin only)
+    pub fn set_relayer_only(env: Env, approvers: Vec<Address>, enabled: bool) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&RELAYER_ONLY, &enabled);
+        Ok(())
+    }
+
+    pub fn get_relayer_only(env: Env) -> bool {
+        env.storage().instance().get(&RELAYER_ONLY).unwrap_or(false)
+    }
+
+    /// Register a relayer allowed to create escrows (admin only)
+    pub fn add_relayer(env: Env, approvers: Vec<Address>, relayer: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().set(&EscrowDataKey::Relayer(relayer.clone()), &true);
+        events::publish(&env, (symbol_short!("RlyAdd"), relayer), ());
+        Ok(())
+    }
+
+    /// Unregister a relayer (admin only)
+    pub fn remove_relayer(env: Env, approvers: Vec<Address>, relayer: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().remove(&EscrowDataKey::Relayer(relayer.clone()));
+        events::publish(&env, (symbol_short!("RlyRm"), relayer), ());
+        Ok(())
+    }
+
+    pub fn is_relayer(env: Env, relayer: Address) -> bool {
+        env.storage().persistent().has(&EscrowDataKey::Relayer(relayer))
+    }
+
+    /// Fund escrow with tokens (resolver deposits funds for user withdrawal)
+    pub fn fund_escrow(
+        env: Env,
+        order_id: OrderId,
+        from: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        // Require authorization from the account that's sending the funds (resolver)
+        from.require_auth();
+        
+        // Get escrow data to validate it exists
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        // A sponsored order is funded with the sponsor's consent
+        if let Some(sponsor) = &immutables.sponsor {
+            sponsor.require_auth();
+        }
+        
+        // Token address resolved at creation
+        let stellar_token = immutables.token.stellar.clone();
+        
+        // Factory contract mints tokens to itself using SAC admin powers
+        // This bypasses trustline requirements since factory is SAC admin
+        Asset::classic(&env, &stellar_token).mint(&env.current_contract_address(), amount)?;
+
+        Self::track_funding(&env, &order_hash, from, amount);
+        
+        events::publish(&env, (symbol_short!("Fund"), order_hash), amount);
+        Ok(())
+    }
+
+    /// Pull more of the escrow's token from a funder (funder auth required) while it is still
+    /// Created, for a funding transfer that fell short or a requirement that grew after an
+    /// auction repriced, instead of cancelling and recreating. Funding can't exceed what the
+    /// escrow holds in the token, amount plus (unless paid in XLM) safety deposit
+    pub fn top_up(env: Env, order_id: OrderId, from: Address, amount: i128) -> Result<i128, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        from.require_auth();
+        let amount = EscrowAmount::new(amount)?.get();
+
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        if Self::load_status(&env, &order_hash).stage != EscrowStage::Created {
+            return Err(Error::InvalidStageTransition);
+        }
+        let required = match immutables.native_deposit {
+            true => immutables.amount,
+            false => immutables.amount + immutables.safety_deposit,
+        };
+        let funded = Self::load_funded(&env, &order_hash) + amount;
+        if funded > required {
+            return Err(Error::AmountAboveMaximum);
+        }
+
+        Self::pull_exact(&env, &immutables.token.stellar, &from, amount)?;
+        Self::track_funding(&env, &order_hash, from.clone(), amount);
+
+        events::publish(&env, (symbol_short!("TopUp"), order_hash), (from, amount, funded));
+        Ok(funded)
+    }
+
+    /// One-call fill for resolvers: validates and creates the dst escrow, pulls amount plus
+    /// safety deposit from the funder (funder auth required) and emits a single OrderFilled
+    /// event carrying the cross-chain correlation data. Like create_dst_escrow it fails with
+    /// InvalidCreationTime if the dst escrow would be cancellable after src_cancellation_timestamp
+    pub fn fill_order(
+        env: Env,
+        relayer: Address,
+        immutables: Immutables,
+        src_cancellation_timestamp: u64,
+        funder: Address,
+    ) -> Result<Address, Error> {
+        funder.require_auth();
+        Self::only_cancellable_before_src(&env, &immutables, src_cancellation_timestamp)?;
+
+        Self::create_escrow(&env, EscrowType::Destination, &relayer, immutables.clone(), None)?;
+
+        let order_hash = immutables.order_hash.clone();
+        let amount = Self::pull_funding(&env, &immutables, &funder)?;
+        Self::track_funding(&env, &order_hash, funder, amount);
+
+        events::publish(
+            &env,
+            (ORDER_FILLED_TOPIC, order_hash.clone()),
+            OrderFilled {
+                hashlock: immutables.hashlock,
+                maker: immutables.maker.evm,
+                taker: immutables.taker.evm,
+                token: immutables.token.evm,
+                amount: immutables.amount,
+                safety_deposit: immutables.safety_deposit,
+                deployed_at: immutables.timelocks.get_deployed_at(&env).map_err(|_| Error::TimeLockError)?,
+            },
+        );
+        Ok(env.current_contract_address())
+    }
+
+    /// Tokens funded into an escrow through fund_escrow, 0 if never funded or unknown
+    pub fn get_order_funded(env: Env, order_id: OrderId) -> i128 {
+        Self::order_hash_of(&env, &order_id).map_or(0, |order_hash| Self::load_funded(&env, &order_hash))
+    }
+
+    /// Replace the immutables of an escrow registered by mistake, keeping its order_hash.
+    /// Only while it is still Created and nothing was funded into it. Approvers are the
+    /// admin signers, or just the original maker
+    pub fn replace_unfunded_escrow(
+        env: Env,
+        approvers: Vec<Address>,
+        order_id: OrderId,
+        new_immutables: Immutables,
+    ) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, old_immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        let maker = old_immutables.maker.stellar.clone();
+        let caller = if approvers.len() == 1 && approvers.first() == Some(maker.clone()) {
+            maker.require_auth();
+            maker.clone()
+        } else {
+            Self::only_admin(&env, &approvers)?;
+            approvers.first().unwrap_or(Self::get_admin(env.clone()))
+        };
+
+        if Self::load_status(&env, &order_hash).stage != EscrowStage::Created {
+            return Err(Error::InvalidStageTransition);
+        }
+        if Self::load_funded(&env, &order_hash) != 0 {
+            return Err(Error::EscrowAlreadyFunded);
+        }
+        // The order_hash stays the escrow's identifier, and its place in a multi-leg swap
+        if new_immutables.order_hash != order_hash || new_immutables.parent != old_immutables.parent {
+            return Err(Error::InvalidImmutables);
+        }
+
+        // Same checks as a fresh creation
+        Self::validate_basic_immutables(&env, &new_immutables)?;
+        if !Self::is_token_allowed(env.clone(), new_immutables.token.stellar.clone()) {
+            return Err(Error::TokenNotAllowed);
+        }
+        Self::map_addresses(&env, &new_immutables, &caller)?;
+        let new_immutables = Self::resolve_addresses(&env, new_immutables)?;
+
+        Self::save_escrow(&env, &order_hash, &escrow_type, &new_immutables);
+        Self::save_status(&env, &order_hash, EscrowStage::Created, 0);
+        Self::add_held(&env, &old_immutables, -old_immutables.amount, -old_immutables.safety_deposit);
+        Self::add_held(&env, &new_immutables, new_immutables.amount, new_immutables.safety_deposit);
+        Self::add_order_stats(&env, &old_immutables, -old_immutables.amount, -old_immutables.safety_deposit);
+        Self::add_order_stats(&env, &new_immutables, new_immutables.amount, new_immutables.safety_deposit);
+        timelocks::store_timelocks(&env, &new_immutables.timelocks);
+        Self::record_action(&env, &order_hash, EscrowAction::Replace, maker, new_immutables.amount);
+
+        events::publish(&env, (symbol_short!("Replaced"), order_hash), new_immutables.amount);
+        Ok(())
+    }
+
+    /// Hand an order to another resolver when its taker went offline before funding (maker
+    /// auth required). Only for source escrows while still Created and nothing was funded into
+    /// them; a destination escrow is the taker's own deposit, so it can't be handed over.
+    /// The new taker is mapped and checked like at creation, the old taker's operator approval
+    /// is dropped. Emits ("Reassign", order_hash) with the new taker's EVM address and the
+    /// immutables hash the counterpart escrow has to match from now on
+    pub fn reassign_taker(env: Env, order_id: OrderId, new_taker: DualAddress) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+        let maker = immutables.maker.stellar.clone();
+        maker.require_auth();
+
+        if escrow_type != EscrowType::Source {
+            panic_with_error!(&env, FactoryError::NotSourceEscrow);
+        }
+        if Self::load_status(&env, &order_hash).stage != EscrowStage::Created {
+            return Err(Error::InvalidStageTransition);
+        }
+        if Self::load_funded(&env, &order_hash) != 0 {
+            return Err(Error::EscrowAlreadyFunded);
+        }
+
+        let mut new_immutables = immutables;
+        new_immutables.taker = new_taker;
+        new_immutables.check_evm_addresses(Self::get_native_token_allowed(env.clone()))?;
+        Self::map_addresses(&env, &new_immutables, &maker)?;
+        let new_immutables = Self::resolve_addresses(&env, new_immutables)?;
+        let immutables_hash = immutables::hash(&env, &new_immutables).map_err(|_| Error::TimeLockError)?;
+
+        Self::save_escrow(&env, &order_hash, &escrow_type, &new_immutables);
+        env.storage().persistent().remove(&EscrowDataKey::Operator(order_hash.clone()));
+        Self::record_action(&env, &order_hash, EscrowAction::Reassign, maker, 0);
+
+        events::publish(&env, (symbol_short!("Reassign"), order_hash), (new_immutables.taker.evm, immutables_hash));
+        Ok(())
+    }
+
+    /// Withdraw from escrow using secret (the order id is the key), returns the payout made.
+    /// A source escrow pays the taker, a destination escrow pays the swap amount to the maker
+    /// and the safety deposit to the taker. On a source escrow an optional target receives
+    /// the swap amount instead of the taker. Taker auth required, an approved operator
+    /// withdraws through operator_withdraw instead.
+    pub fn withdraw(env: Env, order_id: OrderId, secret: BytesN<32>, target: Option<Address>) -> Result<PayoutSummary, Error> {
+        Self::withdraw_preimage(env, order_id, secret.into(), target)
+    }
+
+    /// Withdraw with a preimage of any length up to MAX_PREIMAGE_BYTES, for orders whose
+    /// counterpart HTLC doesn't use 32-byte secrets. Otherwise identical to withdraw
+    pub fn withdraw_preimage(env: Env, order_id: OrderId, preimage: Bytes, target: Option<Address>) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (status, immutables, payout) = Self::prepare_withdraw(&env, &order_hash, &preimage, target)?;
+        only_taker(&env, &immutables)?;
+        Self::settle_withdraw(env, order_hash, preimage, status, immutables, payout)
+    }
+
+    /// Withdraw as the operator the taker approved for the order (operator auth required),
+    /// which may redirect the payout like the taker itself. Lets resolver bots settle with
+    /// a hot key while the taker key stays offline
+    pub fn operator_withdraw(env: Env, order_id: OrderId, operator: Address, secret: BytesN<32>, target: Option<Address>) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        operator.require_auth();
+        if Self::get_operator(env.clone(), order_id) != Some(operator) {
+            return Err(Error::InvalidCaller);
+        }
+        let preimage: Bytes = secret.into();
+        let (status, immutables, payout) = Self::prepare_withdraw(&env, &order_hash, &preimage, target)?;
+        Self::settle_withdraw(env, order_hash, preimage, status, immutables, payout)
+    }
+
+    /// Withdraw on the taker's behalf once the public withdrawal stage opened (caller auth
+    /// required). The swap amount goes where the taker's own withdraw would send it, the
+    /// caller collects the safety deposit as bounty, less the treasury's cut, plus the
+    /// executor rebate
+    pub fn public_withdraw(env: Env, order_id: OrderId, caller: Address, secret: BytesN<32>) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        caller.require_auth();
+        let preimage: Bytes = secret.into();
+        let (escrow_type, status, immutables) = Self::load_withdrawable(&env, &order_hash, &preimage)?;
+        only_valid_preimage(&env, &preimage, &immutables)?;
+        let (public_withdraw_time, _) = in_public_withdraw_window(&env, &immutables.timelocks, &escrow_type, Self::finality_lag(&env, &immutables))?;
+
+        let (recipient, _) = Self::withdraw_recipients(&escrow_type, &immutables);
+        Self::only_attested(&env, &immutables, &recipient);
+        let amount = immutables.checked_amount()?;
+        Self::add_fill(&immutables, status.filled_amount, amount)?;
+
+        let stellar_token = immutables.token.stellar.clone();
+        let (reward, treasury_cut) = Self::split_deposit(&env, &immutables, immutables.checked_safety_deposit()?.get());
+        let (reward, _) = Self::settle_watchdog(&env, &order_hash, &stellar_token, &caller, public_withdraw_time, reward, treasury_cut)?;
+        let payout = PayoutSummary {
+            recipient,
+            token: stellar_token,
+            amount: amount.get(),
+            safety_deposit_recipient: caller,
+            safety_deposit_token: immutables.deposit_token(&env),
+            safety_deposit_amount: reward,
+        };
+        Self::pay_rebate(&env, &order_hash, &payout.safety_deposit_token, &payout.safety_deposit_recipient)?;
+        Self::settle_withdraw(env, order_hash, preimage, status, immutables, payout)
+    }
+
+    /// Delegate the order's withdrawals to an operator (taker auth required), replacing any
+    /// previous one. Revocable at any time with revoke_operator
+    pub fn approve_operator(env: Env, order_id: OrderId, operator: Address) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        immutables.taker.stellar.require_auth();
+        let key = EscrowDataKey::Operator(order_hash.clone());
+        env.storage().persistent().set(&key, &operator);
+        ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+        events::publish(&env, (symbol_short!("OpApprove"), order_hash), operator);
+        Ok(())
+    }
+
+    /// Withdraw the order's operator approval (taker auth required)
+    pub fn revoke_operator(env: Env, order_id: OrderId) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        immutables.taker.stellar.require_auth();
+        env.storage().persistent().remove(&EscrowDataKey::Operator(order_hash.clone()));
+        events::publish(&env, (symbol_short!("OpRevoke"), order_hash), ());
+        Ok(())
+    }
+
+    /// Operator the taker approved for an order, if any
+    pub fn get_operator(env: Env, order_id: OrderId) -> Option<Address> {
+        let order_hash = Self::order_hash_of(&env, &order_id).ok()?;
+        env.storage().persistent().get(&EscrowDataKey::Operator(order_hash))
+    }
+
+    /// Withdraw one part of an escrow's partial withdrawal schedule: the secret unlocks exactly
+    /// its part's amount, with the matching pro-rata share of the safety deposit. The escrow
+    /// is PartiallyWithdrawn until its last part is withdrawn, which writes the settlement
+    /// receipt. Retrying a part that was withdrawn fails with AlreadySettled
+    pub fn withdraw_part(env: Env, order_id: OrderId, secret: BytesN<32>) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        // Find the part the secret unlocks. Like on withdraw, a retry of a part that went
+        // through is told so instead of failing like an invalid transition
+        let hashlock: BytesN<32> = env.crypto().keccak256(&Bytes::from_array(&env, &secret.to_array())).into();
+        let index = immutables.parts.iter()
+            .position(|part| part.hashlock == hashlock)
+            .ok_or(Error::UnknownPart)? as u32;
+        let withdrawn = Self::load_withdrawn_parts(&env, &order_hash);
+        if withdrawn & (1 << index) != 0 {
+            panic_with_error!(&env, FactoryError::AlreadySettled);
+        }
+
+        let status = Self::load_status(&env, &order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Withdrawn)?;
+        Self::only_parent_withdrawn(&env, &immutables)?;
+        only_taker(&env, &immutables)?;
+        let window = Self::check_withdraw_window(&env, &escrow_type, &immutables)?;
+
+        let part = immutables.parts.get(index).ok_or(Error::UnknownPart)?;
+
+        let filled_amount = Self::add_fill(&immutables, status.filled_amount, EscrowAmount::new(part.amount)?)?;
+        let (_, deposit_before) = Self::unpaid(&immutables, status.filled_amount);
+        let (_, deposit_after) = Self::unpaid(&immutables, filled_amount);
+        let deposit_share = deposit_before - deposit_after;
+        let (refund, forfeited) = Self::decay_deposit(&env, &immutables, window, deposit_share)?;
+        let (recipient, stellar_taker) = Self::withdraw_recipients(&escrow_type, &immutables);
+        Self::only_attested(&env, &immutables, &recipient);
+        let payout = PayoutSummary {
+            recipient,
+            token: immutables.token.stellar.clone(),
+            amount: part.amount,
+            safety_deposit_recipient: stellar_taker.clone(),
+            safety_deposit_token: immutables.deposit_token(&env),
+            safety_deposit_amount: refund,
+        };
+        Self::execute_payout(&env, &payout);
+        Self::pay_treasury(&env, &payout.safety_deposit_token, forfeited)?;
+
+        let parts_key = EscrowDataKey::WithdrawnParts(order_hash.clone());
+        env.storage().persistent().set(&parts_key, &(withdrawn | (1 << index)));
+        ttl::extend_persistent(&env, &parts_key, Self::get_escrow_ttl(env.clone()));
+        if filled_amount == immutables.amount {
+            Self::advance_status(&env, &immutables, status.stage, EscrowStage::Withdrawn, filled_amount)?;
+            Self::record_receipt(&env, &order_hash, EscrowStage::Withdrawn, &payout);
+        } else {
+            Self::add_held(&env, &immutables, -payout.amount, -deposit_share);
+            Self::add_order_stats(&env, &immutables, 0, -deposit_share);
+            if status.stage == EscrowStage::Created {
+                Self::advance_status(&env, &immutables, status.stage, EscrowStage::PartiallyWithdrawn, filled_amount)?;
+            } else {
+                Self::save_status(&env, &order_hash, status.stage, filled_amount);
+            }
+        }
+        Self::record_action(&env, &order_hash, EscrowAction::Withdraw, stellar_taker, part.amount);
+        record_revealed_secret(&env, &secret);
+
+        events::publish(&env, (symbol_short!("WithdPart"), order_hash), (index, part.amount));
+        Ok(payout)
+    }
+
+    /// Bitmask of the parts of an escrow's withdrawal schedule withdrawn so far
+    pub fn get_withdrawn_parts(env: Env, order_id: OrderId) -> u32 {
+        Self::order_hash_of(&env, &order_id).map_or(0, |order_hash| Self::load_withdrawn_parts(&env, &order_hash))
+    }
+
+    /// Cancel escrow (maker only, after timelock) - the order id is the key, returns the refund made
+    pub fn cancel(env: Env, order_id: OrderId) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        Self::cancel_escrow(&env, &order_hash, None, CancelReason::Timeout)
+    }
+
+    /// Cancel like cancel, recording the maker's reason instead of Timeout (maker auth required).
+    /// ResolverDefault and ParentCancelled are only recorded by public_cancel and cancel_orphan,
+    /// passing them here fails with InvalidCaller
+    pub fn cancel_with_reason(env: Env, order_id: OrderId, reason: CancelReason) -> Result<PayoutSummary, Error> {
+        if matches!(reason, CancelReason::ResolverDefault | CancelReason::ParentCancelled) {
+            return Err(Error::InvalidCaller);
+        }
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        immutables.maker.stellar.require_auth();
+        Self::cancel_escrow(&env, &order_hash, None, reason)
+    }
+
+    /// Cancel before the cancellation timelock using the pre-image of the order's cancel
+    /// hashlock, shared off-chain once maker and taker agree (maker or a relayer submits)
+    pub fn cooperative_cancel(env: Env, order_id: OrderId, cancel_secret: BytesN<32>) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        Self::cancel_escrow(&env, &order_hash, Some(&cancel_secret), CancelReason::MakerRequested)
+    }
+
+    /// Cancel a leg of a multi-leg swap whose parent leg was cancelled, expired or rescued
+    /// (anyone), without waiting for its own cancellation window. The funds go back like on
+    /// cancel and the reason is recorded as ParentCancelled
+    pub fn cancel_orphan(env: Env, order_id: OrderId) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+        let parent = immutables.parent.clone().ok_or(Error::InvalidStageTransition)?;
+        if !Self::is_unwound(&Self::load_status(&env, &parent).stage) {
+            return Err(Error::InvalidStageTransition);
+        }
+        let status = Self::load_status(&env, &order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Cancelled)?;
+
+        let payout = Self::cancel_payout(&env, &escrow_type, &immutables, &status);
+        Self::settle_cancel(&env, &order_hash, &status, &immutables, &payout, CancelReason::ParentCancelled)?;
+        Ok(payout)
+    }
+
+    /// order_hashes of the escrows declaring the order as their parent leg, in creation order
+    pub fn get_children(env: Env, order_id: OrderId) -> Vec<BytesN<32>> {
+        match Self::order_hash_of(&env, &order_id) {
+            Ok(order_hash) => Self::load_children(&env, &order_hash),
+            Err(_) => Vec::new(&env),
+        }
+    }
+
+    /// Cancel a source escrow once its public cancellation window opened (anyone) - funds go
+    /// back to the maker, the safety deposit is paid to the caller as a reward, like on EVM
+    pub fn public_cancel(env: Env, order_id: OrderId, caller: Address) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        caller.require_auth();
+
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        // Check current stage
+        let status = Self::load_status(&env, &order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Cancelled)?;
+
+        // Destination escrows have no public cancellation window
+        let public_cancel_time = in_public_cancel_window(&env, &immutables.timelocks, &escrow_type)?;
+
+        // Addresses resolved at creation
+        let stellar_token = immutables.token.stellar.clone();
+        let stellar_maker = immutables.maker.stellar.clone();
+
+        // Return what wasn't withdrawn to maker, reward to caller minus the treasury's cut
+        let (amount, safety_deposit) = Self::unpaid(&immutables, status.filled_amount);
+        let (reward, treasury_cut) = Self::split_deposit(&env, &immutables, safety_deposit);
+        let (reward, treasury_cut) = Self::settle_watchdog(&env, &order_hash, &stellar_token, &caller, public_cancel_time, reward, treasury_cut)?;
+        let deposit_token = immutables.deposit_token(&env);
+        Self::push_or_credit(&env, &stellar_token, &stellar_maker, amount);
+        uni_transfer(&env, &deposit_token, &caller, reward)?;
+        Self::pay_treasury(&env, &deposit_token, treasury_cut)?;
+        Self::pay_rebate(&env, &order_hash, &deposit_token, &caller)?;
+
+        // Update stage
+        let reason = CancelReason::ResolverDefault;
+        Self::advance_status(&env, &immutables, status.stage, EscrowStage::Cancelled, status.filled_amount)?;
+        Self::record_cancel_reason(&env, &order_hash, reason);
+        Self::record_action(&env, &order_hash, EscrowAction::Cancel, caller.clone(), amount);
+
+        let payout = PayoutSummary {
+            recipient: stellar_maker,
+            token: stellar_token,
+            amount,
+            safety_deposit_recipient: caller,
+            safety_deposit_token: deposit_token,
+            safety_deposit_amount: reward,
+        };
+        Self::record_receipt(&env, &order_hash, EscrowStage::Cancelled, &payout);
+        Self::publish_cancelled(&env, &order_hash, reason, &payout);
+        Ok(payout)
+    }
+
+    /// Bond as the watchdog of an order's public actions (public_withdraw, public_cancel, expire), watchdog auth
+    /// required. For the configured priority window after an action opens only the watchdog
+    /// may execute it, and doing so returns the bond plus the treasury's cut of the bounty.
+    /// A watchdog that lets the window pass forfeits its bond to whoever executes instead
+    pub fn register_watchdog(env: Env, order_id: OrderId, watchdog: Address, bond: i128) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        watchdog.require_auth();
+
+        let config = Self::get_watchdog_config(env.clone()).ok_or(Error::WatchdogsDisabled)?;
+        if bond <= 0 || bond < config.min_bond {
+            return Err(Error::AmountBelowMinimum);
+        }
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        if !Self::is_unsettled(&Self::load_status(&env, &order_hash).stage) {
+            return Err(Error::InvalidStageTransition);
+        }
+        let key = EscrowDataKey::Watchdog(order_hash.clone());
+        if env.storage().persistent().has(&key) {
+            return Err(Error::WatchdogRegistered);
+        }
+
+        // Bonds are held next to the escrowed funds and count towards the locked total
+        let stellar_token = immutables.token.stellar.clone();
+        Asset::from_address(&env, &stellar_token).transfer(&watchdog, &env.current_contract_address(), bond);
+        Self::add_locked(&env, &stellar_token, bond);
+        env.storage().persistent().set(&key, &WatchdogBond { watchdog: watchdog.clone(), bond });
+        ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+
+        events::publish(&env, (symbol_short!("WdReg"), order_hash), (watchdog, bond));
+        Ok(())
+    }
+
+    /// Watchdog registered for an order, if any
+    pub fn get_watchdog(env: Env, order_id: OrderId) -> Option<WatchdogBond> {
+        Self::load_watchdog(&env, &Self::order_hash_of(&env, &order_id).ok()?)
+    }
+
+    /// Return the bond of a watchdog whose order settled without a public action (anyone),
+    /// returns the amount released, 0 if no watchdog is registered
+    pub fn release_watchdog_bond(env: Env, order_id: OrderId) -> Result<i128, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let Some(registered) = Self::load_watchdog(&env, &order_hash) else {
+            return Ok(0);
+        };
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        if Self::is_unsettled(&Self::load_status(&env, &order_hash).stage) {
+            return Err(Error::InvalidStageTransition);
+        }
+
+        let stellar_token = immutables.token.stellar.clone();
+        env.storage().persistent().remove(&EscrowDataKey::Watchdog(order_hash.clone()));
+        Self::add_locked(&env, &stellar_token, -registered.bond);
+        uni_transfer(&env, &stellar_token, &registered.watchdog, registered.bond)?;
+        events::publish(&env, (symbol_short!("WdRelease"), order_hash), registered.bond);
+        Ok(registered.bond)
+    }
+
+    /// Dry run of withdraw: runs every check (stage, secret, timelocks, mappings, balance)
+    /// and reports the payout that would be made, without writing storage or moving funds
+    pub fn simulate_withdraw(env: Env, order_id: OrderId, secret: BytesN<32>, target: Option<Address>) -> DryRunResult {
+        let prepared = Self::order_hash_of(&env, &order_id)
+            .and_then(|order_hash| Self::prepare_withdraw(&env, &order_hash, &secret.into(), target))
+            .map(|(_, _, payout)| payout);
+        Self::dry_run_result(&env, EscrowStage::Withdrawn, prepared)
+    }
+
+    /// Dry run of cancel, see simulate_withdraw
+    pub fn simulate_cancel(env: Env, order_id: OrderId) -> DryRunResult {
+        let prepared = Self::order_hash_of(&env, &order_id)
+            .and_then(|order_hash| Self::prepare_cancel(&env, &order_hash, None))
+            .map(|(_, _, payout)| payout);
+        Self::dry_run_result(&env, EscrowStage::Cancelled, prepared)
+    }
+
+    /// Register the ed25519 key a maker signs permits with (maker auth required)
+    pub fn register_permit_key(env: Env, maker: Address, public_key: BytesN<32>) {
+        maker.require_auth();
+        env.storage().persistent().set(&EscrowDataKey::PermitKey(maker.clone()), &public_key);
+        events::publish(&env, (symbol_short!("PermitKey"), maker), public_key);
+    }
+
+    /// Get the next permit nonce expected from a maker
+    pub fn get_permit_nonce(env: Env, maker: Address) -> u64 {
+        env.storage().persistent().get(&EscrowDataKey::PermitNonce(maker)).unwrap_or(0)
+    }
+
+    /// Cancel on behalf of the maker using their off-chain signed permit (relayer submits)
+    pub fn cancel_with_permit(env: Env, order_id: OrderId, permit: Permit) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        let stellar_maker = immutables.maker.stellar.clone();
+
+        let public_key: BytesN<32> = env.storage().persistent()
+            .get(&EscrowDataKey::PermitKey(stellar_maker.clone()))
+            .ok_or(Error::PermitKeyMissing)?;
+        let nonce = Self::get_permit_nonce(env.clone(), stellar_maker.clone());
+        permit::verify(&env, &public_key, &order_hash, PermitAction::Cancel, nonce, &permit)?;
+
+        // Consume the nonce so the permit can't be replayed
+        env.storage().persistent().set(&EscrowDataKey::PermitNonce(stellar_maker), &(nonce + 1));
+
+        Self::cancel_escrow(&env, &order_hash, None, CancelReason::MakerRequested)
+    }
+
+    /// Expire escrow after its absolute expiration (anyone) - funds go back to their funder,
+    /// the safety deposit is paid to the caller as a bounty for cleaning up
+    pub fn expire(env: Env, order_id: OrderId, caller: Address) -> Result<PayoutSummary, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        caller.require_auth();
+
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        // Check current stage
+        let status = Self::load_status(&env, &order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Expired)?;
+
+        // Orders without an expiration can only be cancelled through the timelocks
+        if immutables.expiration == 0 {
+            return Err(Error::InvalidTime);
+        }
+        // Expiration isn't a timelock stage, so it keeps the generic InvalidTime
+        only_after(&env, immutables.expiration)?;
+
+        // Addresses resolved at creation
+        let stellar_token = immutables.token.stellar.clone();
+        let funder = Self::refund_recipient(&escrow_type, &immutables);
+
+        // Return what wasn't withdrawn to its funder, bounty to caller minus the treasury's cut
+        let (amount, safety_deposit) = Self::unpaid(&immutables, status.filled_amount);
+        let (bounty, treasury_cut) = Self::split_deposit(&env, &immutables, safety_deposit);
+        let (bounty, treasury_cut) = Self::settle_watchdog(&env, &order_hash, &stellar_token, &caller, immutables.expiration, bounty, treasury_cut)?;
+        let deposit_token = immutables.deposit_token(&env);
+        Self::push_or_credit(&env, &stellar_token, &funder, amount);
+        uni_transfer(&env, &deposit_token, &caller, bounty)?;
+        Self::pay_treasury(&env, &deposit_token, treasury_cut)?;
+        Self::pay_rebate(&env, &order_hash, &deposit_token, &caller)?;
+
+        // Update stage
+        Self::advance_status(&env, &immutables, status.stage, EscrowStage::Expired, status.filled_amount)?;
+        Self::record_action(&env, &order_hash, EscrowAction::Expire, caller.clone(), amount);
+
+        let payout = PayoutSummary {
+            recipient: funder,
+            token: stellar_token,
+            amount,
+            safety_deposit_recipient: caller,
+            safety_deposit_token: deposit_token,
+            safety_deposit_amount: bounty,
+        };
+        Self::record_receipt(&env, &order_hash, EscrowStage::Expired, &payout);
+
+        events::publish(&env, (symbol_short!("Expired"), order_hash), amount);
+        Ok(payout)
+    }
+
+    /// Rescue funds (taker only, after the order's rescue delay or the factory default) - the order id is the key.
+    /// Only an unsettled (Created, PartiallyWithdrawn or Stuck) escrow can be rescued, for no more than it still holds.
+    /// A partial rescue leaves the escrow Stuck so that only further rescues can pay out the rest; the rescue
+    /// that empties it marks it Rescued. Rescues of an escrow the admin marked stuck are the funder's instead
+    /// (funder auth required): the maker of a source escrow, the taker of a destination escrow
+    pub fn rescue_funds(env: Env, order_id: OrderId, amount: i128) -> Result<(), Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+
+        // Settled escrows hold nothing anymore, rescuing them would drain other orders' funds
+        let status = Self::load_status(&env, &order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Rescued)?;
+        let (_, deposit_left) = Self::unpaid(&immutables, status.filled_amount);
+        let rescued = Self::load_rescued(&env, &order_hash);
+        let token_left = Self::rescuable(&immutables, status.filled_amount) - rescued;
+        if amount <= 0 {
+            return Err(Error::AmountBelowMinimum);
+        }
+        if amount > token_left {
+            return Err(Error::InsufficientBalance);
+        }
+        
+        // Marking an escrow stuck blocks its cancellation, so the taker mustn't collect what
+        // the maker could no longer get back
+        let rescuer = if env.storage().persistent().has(&EscrowDataKey::MarkedStuck(order_hash.clone())) {
+            let funder = Self::refund_recipient(&escrow_type, &immutables);
+            funder.require_auth();
+            funder
+        } else {
+            only_taker(&env, &immutables)?;
+            immutables.taker.stellar.clone()
+        };
+        
+        let (rescue_start, _) = Self::rescue_window(&env, &immutables)?;
+        only_after(&env, rescue_start).map_err(|_| Error::RescueNotStarted)?;
+        
+        let stellar_token = immutables.token.stellar.clone();
+        
+        uni_transfer(&env, &stellar_token, &rescuer, amount)?;
+        if amount < token_left {
+            let key = EscrowDataKey::RescuedAmount(order_hash.clone());
+            env.storage().persistent().set(&key, &(rescued + amount));
+            ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+            Self::add_locked(&env, &stellar_token, -amount);
+            if status.stage != EscrowStage::Stuck {
+                Self::advance_status(&env, &immutables, status.stage, EscrowStage::Stuck, status.filled_amount)?;
+            }
+        } else {
+            // Settling releases the whole unpaid balance from the totals, including what earlier
+            // partial rescues already released
+            env.storage().persistent().remove(&EscrowDataKey::RescuedAmount(order_hash.clone()));
+            Self::add_locked(&env, &stellar_token, rescued);
+            if immutables.native_deposit {
+                uni_transfer(&env, &immutables.deposit_token(&env), &rescuer, deposit_left)?;
+            }
+            Self::advance_status(&env, &immutables, status.stage, EscrowStage::Rescued, status.filled_amount)?;
+        }
+        Self::record_action(&env, &order_hash, EscrowAction::Rescue, rescuer, amount);
+        events::publish(&env, (symbol_short!("FundsSave"), stellar_token), amount);
+        Ok(())
+    }
+
+    /// Tokens partial rescues already moved out of a Stuck escrow, 0 if none or unknown
+    pub fn get_rescued_amount(env: Env, order_id: OrderId) -> i128 {
+        Self::order_hash_of(&env, &order_id).map_or(0, |order_hash| Self::load_rescued(&env, &order_hash))
+    }
+
+    /// Flag an escrow that can't settle normally so that only a rescue remains possible (admin only).
+    /// Its rescues then pay the funder, see rescue_funds
+    pub fn mark_stuck(env: Env, approvers: Vec<Address>, order_id: OrderId) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+        let status = Self::load_status(&env, &order_hash);
+        Self::advance_status(&env, &immutables, status.stage, EscrowStage::Stuck, status.filled_amount)?;
+        let key = EscrowDataKey::MarkedStuck(order_hash);
+        env.storage().persistent().set(&key, &true);
+        ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+        Ok(())
+    }
+
+    /// Announce a sweep of stale escrows to recipient (admin only), executable by sweep_stale
+    /// SWEEP_ANNOUNCEMENT_DELAY later. An escrow is stale once its rescue has been open for
+    /// another rescue delay with nobody acting on it: still Created (or marked Stuck), nothing
+    /// withdrawn. Replaces any earlier announcement, returns when the sweep becomes executable
+    pub fn announce_sweep(env: Env, approvers: Vec<Address>, order_ids: Vec<OrderId>, recipient: Address) -> Result<u64, Error> {
+        Self::only_admin(&env, &approvers)?;
+        let order_hashes = Self::stale_order_hashes(&env, &order_ids)?;
+        let executable_at = env.ledger().timestamp().saturating_add(SWEEP_ANNOUNCEMENT_DELAY);
+        let announcement = SweepAnnouncement { order_hashes, recipient, executable_at };
+        env.storage().instance().set(&SWEEP, &announcement);
+        events::publish(&env, (symbol_short!("SweepAnn"),), announcement);
+        Ok(executable_at)
+    }
+
+    /// Execute the announced sweep (admin only): every escrow must still be stale and is
+    /// marked Rescued, its unpaid amount and safety deposit going to the recipient. All or
+    /// nothing, returns how many escrows were swept
+    pub fn sweep_stale(env: Env, approvers: Vec<Address>, order_ids: Vec<OrderId>, recipient: Address) -> Result<u32, Error> {
+        Self::only_admin(&env, &approvers)?;
+        let announcement = Self::get_sweep_announcement(env.clone()).ok_or(Error::SweepNotAnnounced)?;
+        let order_hashes = Self::stale_order_hashes(&env, &order_ids)?;
+        if announcement.order_hashes != order_hashes || announcement.recipient != recipient {
+            return Err(Error::SweepNotAnnounced);
+        }
+        only_after(&env, announcement.executable_at).map_err(|_| Error::RescueNotStarted)?;
+
+        for order_hash in order_hashes.iter() {
+            let (_, immutables) = Self::load_escrow(&env, &order_hash)?;
+            let status = Self::load_status(&env, &order_hash);
+            let (amount_left, deposit_left) = Self::unpaid(&immutables, status.filled_amount);
+            if immutables.native_deposit {
+                uni_transfer(&env, &immutables.token.stellar, &recipient, amount_left)?;
+                uni_transfer(&env, &immutables.deposit_token(&env), &recipient, deposit_left)?;
+            } else {
+                uni_transfer(&env, &immutables.token.stellar, &recipient, amount_left + deposit_left)?;
+            }
+            Self::advance_status(&env, &immutables, status.stage, EscrowStage::Rescued, status.filled_amount)?;
+            Self::record_action(&env, &order_hash, EscrowAction::Rescue, recipient.clone(), amount_left);
+            events::publish(&env, (symbol_short!("Swept"), order_hash), (recipient.clone(), amount_left, deposit_left));
+        }
+        env.storage().instance().remove(&SWEEP);
+        Ok(order_hashes.len())
+    }
+
+    /// When the escrow's rescue opens: the rescue start of its timelocks plus its own rescue
+    /// delay or, without one, the factory default
+    pub fn get_rescue_start(env: Env, order_id: OrderId) -> Result<u64, Error> {
+        let (_, immutables) = Self::load_escrow(&env, &Self::order_hash_of(&env, &order_id)?)?;
+        Ok(Self::rescue_window(&env, &immutables)?.0)
+    }
+
+    pub fn get_sweep_announcement(env: Env) -> Option<SweepAnnouncement> {
+        env.storage().instance().get(&SWEEP)
+    }
+
+    /// Get escrow state by order id
+    pub fn get_escrow_state(env: Env, order_id: OrderId) -> Result<(EscrowType, Immutables), Error> {
+        Self::load_escrow(&env, &Self::order_hash_of(&env, &order_id)?)
+    }
+
+    /// Whether an escrow can still be created under the order id: none exists and no relayer
+    /// holds a live reservation of it
+    pub fn is_order_available(env: Env, order_id: OrderId) -> bool {
+        let Ok(order_hash) = Self::order_hash_of(&env, &order_id) else {
+            return false;
+        };
+        !env.storage().persistent().has(&EscrowDataKey::EscrowState(order_hash.clone()))
+            && Self::live_reservation(&env, &order_hash).is_none()
+    }
+
+    /// Reserve an order id for ttl seconds (at most MAX_ORDER_RESERVATION) while the relayer
+    /// assembles the funding, so competing resolvers don't race to create it. Only the relayer
+    /// can create the escrow until the reservation expires, it can renew its own reservation.
+    /// Returns when the reservation expires, an order id with an escrow fails with EscrowExists
+    pub fn reserve_order(env: Env, order_id: OrderId, relayer: Address, ttl: u64) -> Result<u64, Error> {
+        relayer.require_auth();
+        if ttl == 0 || ttl > MAX_ORDER_RESERVATION {
+            return Err(Error::InvalidImmutables);
+        }
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        if env.storage().persistent().has(&EscrowDataKey::EscrowState(order_hash.clone())) {
+            panic_with_error!(&env, FactoryError::EscrowExists);
+        }
+        if matches!(Self::live_reservation(&env, &order_hash), Some((reserver, _)) if reserver != relayer) {
+            return Err(Error::OrderReserved);
+        }
+        let expires_at = env.ledger().timestamp() + ttl;
+        let key = EscrowDataKey::Reservation(order_hash.clone());
+        env.storage().persistent().set(&key, &(relayer.clone(), expires_at));
+        ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+        events::publish(&env, (symbol_short!("Reserved"), order_hash), (relayer, expires_at));
+        Ok(expires_at)
+    }
+
+    /// Layout an escrow's record is stored with, 0 for records from before versioning
+    pub fn get_escrow_schema_version(env: Env, order_id: OrderId) -> Result<u32, Error> {
+        let (schema_version, _, _) = Self::load_escrow_record(&env, &Self::order_hash_of(&env, &order_id)?)?;
+        Ok(schema_version)
+    }
+
+    /// Swap amount of an escrow not paid out yet, 0 once settled or if unknown
+    pub fn get_remaining_amount(env: Env, order_id: OrderId) -> i128 {
+        Self::order_hash_of(&env, &order_id).map_or(0, |order_hash| Self::remaining_amount(&env, &order_hash))
+    }
+
+    /// Escrow record with the ledger sequence and timestamp it was created at, so off-chain
+    /// agents can compute its confirmation depth and line it up with EVM block numbers
+    pub fn get_escrow_info(env: Env, order_id: OrderId) -> Result<EscrowInfo, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+        let creation: Option<CreationRecord> = env.storage().persistent().get(&EscrowDataKey::Creation(order_hash.clone()));
+        let created_ledger = creation.as_ref().map_or(0, |creation| creation.ledger);
+        let token_metadata: Option<(u32, String)> = env.storage().persistent().get(&EscrowDataKey::TokenMetadata(order_hash.clone()));
+        Ok(EscrowInfo {
+            escrow_type,
+            immutables,
+            status: Self::load_status(&env, &order_hash),
+            created_ledger,
+            created_at: creation.as_ref().map_or(0, |creation| creation.timestamp),
+            created_by: creation.map(|creation| creation.relayer),
+            confirmations: env.ledger().sequence().saturating_sub(created_ledger),
+            token_decimals: token_metadata.as_ref().map(|(decimals, _)| *decimals),
+            token_symbol: token_metadata.map(|(_, symbol)| symbol),
+        })
+    }
+
+    /// Get escrow stage by order id
+    pub fn get_escrow_stage(env: Env, order_id: OrderId) -> EscrowStage {
+        Self::get_escrow_status(env, order_id).stage
+    }
+
+    /// Get the mutable escrow status (stage, filled amount, ttl) by order id
+    pub fn get_escrow_status(env: Env, order_id: OrderId) -> EscrowStatus {
+        Self::order_hash_of(&env, &order_id).map_or(Self::initial_status(), |order_hash| Self::load_status(&env, &order_hash))
+    }
+
+    /// Get the latest actions on an escrow (oldest first), up to the last 16
+    pub fn get_escrow_history(env: Env, order_id: OrderId) -> Vec<ActionRecord> {
+        Self::order_hash_of(&env, &order_id).map_or(Vec::new(&env), |order_hash| Self::load_history(&env, &order_hash))
+    }
+
+    /// Address mappings the creation (or replacement) of an order set or changed
+    pub fn get_order_mappings(env: Env, order_id: OrderId) -> Vec<DualAddress> {
+        Self::order_hash_of(&env, &order_id).map_or(Vec::new(&env), |order_hash| Self::load_order_mappings(&env, &order_hash))
+    }
+
+    /// Secret revealed by a withdraw, keyed by the hashlock it unlocked
+    pub fn get_revealed_secret(env: Env, hashlock: BytesN<32>) -> Option<BytesN<32>> {
+        get_revealed_secret(&env, &hashlock)
+    }
+
+    /// Who received what and when from the withdraw, cancel or expire that settled the escrow,
+    /// None while it isn't settled. Relayers retrying a withdraw that failed with
+    /// AlreadySettled read the payout of the first one here
+    pub fn get_settlement_receipt(env: Env, order_id: OrderId) -> Option<SettlementReceipt> {
+        let order_hash = Self::order_hash_of(&env, &order_id).ok()?;
+        env.storage().persistent().get(&EscrowDataKey::Receipt(order_hash))
+    }
+
+    /// Reason a cancelled escrow was cancelled for, None while it isn't cancelled
+    pub fn get_cancel_reason(env: Env, order_id: OrderId) -> Option<CancelReason> {
+        Self::load_cancel_reason(&env, &Self::order_hash_of(&env, &order_id).ok()?)
+    }
+
+    /// Preimage revealed by a withdraw for a hashlock, whatever its length
+    pub fn get_revealed_preimage(env: Env, hashlock: BytesN<32>) -> Option<Bytes> {
+        get_revealed_preimage(&env, &hashlock)
+    }
+
+    /// Payouts of a token credited to a recipient because the transfer to it failed
+    pub fn get_claimable(env: Env, recipient: Address, token: Address) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::Claimable(recipient, token)).unwrap_or(0)
+    }
+
+    /// Pull the payouts credited to the recipient (recipient auth required), returns the amount
+    pub fn claim(env: Env, recipient: Address, token: Address) -> Result<i128, Error> {
+        recipient.require_auth();
+        let amount = Self::get_claimable(env.clone(), recipient.clone(), token.clone());
+        env.storage().persistent().remove(&EscrowDataKey::Claimable(recipient.clone(), token.clone()));
+        Self::add_locked(&env, &token, -amount);
+        uni_transfer(&env, &token, &recipient, amount)?;
+        events::publish(&env, (symbol_short!("Claimed"), recipient), amount);
+        Ok(amount)
+    }
+
+    /// The whole escrow record as XDR of an EscrowExport, for relayers reconciling their
+    /// databases against chain state. Decode with EscrowExport's from_xdr and check version
+    pub fn export_escrow(env: Env, order_id: OrderId) -> Result<Bytes, Error> {
+        let order_hash = Self::order_hash_of(&env, &order_id)?;
+        let (escrow_type, immutables) = Self::load_escrow(&env, &order_hash)?;
+        // The export format has no field for partial rescues, the next factory would pay them twice
+        if Self::load_rescued(&env, &order_hash) != 0 {
+            return Err(Error::InvalidStageTransition);
+        }
+        let export = EscrowExport {
+            version: ESCROW_EXPORT_VERSION,
+            order_hash: order_hash.clone(),
+            escrow_type,
+            immutables,
+            status: Self::load_status(&env, &order_hash),
+            funded: Self::load_funded(&env, &order_hash),
+            remaining_amount: Self::remaining_amount(&env, &order_hash),
+            locked: Self::order_locked(&env, &order_hash),
+            withdrawn_parts: Self::load_withdrawn_parts(&env, &order_hash),
+            cancel_reason: Self::load_cancel_reason(&env, &order_hash).map_or(0, |reason| reason as u32),
+            history: Self::load_history(&env, &order_hash),
+            exported_at: env.ledger().timestamp(),
+            exported_at_ledger: env.ledger().sequence(),
+        };
+        Ok(export.to_xdr(&env))
+    }
+
+    /// Recreate an escrow exported by a previous factory (admin only). The export must be
+    /// signed by the migration key over get_import_message(export), which binds it to this
+    /// factory. Stage, balances, history and withdrawn parts carry over; the tokens the
+    /// escrow holds have to be moved over separately, check_solvency shows the gap until then
+    pub fn import_escrow(env: Env, approvers: Vec<Address>, export: Bytes, signature: BytesN<64>) -> Result<BytesN<32>, Error> {
+        Self::only_admin(&env, &approvers)?;
+        let public_key = Self::get_migration_key(env.clone()).ok_or(Error::ImportsDisabled)?;
+        // Traps on a bad signature, aborting the whole invocation
+        env.crypto().ed25519_verify(&public_key, &Self::get_import_message(env.clone(), export.clone()), &signature);
+
+        let record = EscrowExport::from_xdr(&env, &export).map_err(|_| Error::InvalidExport)?;
+        if record.version != ESCROW_EXPORT_VERSION || record.order_hash != record.immutables.order_hash {
+            return Err(Error::InvalidExport);
+        }
+        let cancel_reason = match record.cancel_reason {
+            0 => None,
+            code => Some(CancelReason::from_code(code).ok_or(Error::InvalidExport)?),
+        };
+        immutables::hash(&env, &record.immutables).map_err(|_| Error::InvalidExport)?;
+        let order_hash = record.order_hash.clone();
+        if env.storage().persistent().has(&EscrowDataKey::EscrowState(order_hash.clone())) {
+            return Err(Error::InvalidImmutables); // Already exists
+        }
+
+        let immutables = record.immutables;
+        let stage = record.status.stage;
+        let caller = approvers.first().unwrap_or(Self::get_admin(env.clone()));
+
+        // The attested record vouches for its resolved addresses, so they're mapped as recorded
+        for party in [&immutables.maker, &immutables.taker, &immutables.token] {
+            immutables::map_and_log(&env, party.evm.clone(), party.stellar.clone(), &caller, Some(&order_hash))?;
+        }
+        Self::save_escrow(&env, &order_hash, &record.escrow_type, &immutables);
+        Self::record_creation(&env, &order_hash, &caller, &immutables.token.stellar);
+        Self::save_status(&env, &order_hash, stage.clone(), record.status.filled_amount);
+        timelocks::store_timelocks(&env, &immutables.timelocks);
+        let storage = env.storage().persistent();
+        if record.funded != 0 {
+            storage.set(&EscrowDataKey::Funded(order_hash.clone()), &record.funded);
+            ttl::extend_persistent(&env, &EscrowDataKey::Funded(order_hash.clone()), Self::get_escrow_ttl(env.clone()));
+        }
+        if record.withdrawn_parts != 0 {
+            storage.set(&EscrowDataKey::WithdrawnParts(order_hash.clone()), &record.withdrawn_parts);
+            ttl::extend_persistent(&env, &EscrowDataKey::WithdrawnParts(order_hash.clone()), Self::get_escrow_ttl(env.clone()));
+        }
+        if let Some(reason) = cancel_reason {
+            Self::record_cancel_reason(&env, &order_hash, reason);
+        }
+        // The imported history continues here, record_action extends its TTL
+        storage.set(&EscrowDataKey::EscrowHistory(order_hash.clone()), &record.history);
+        Self::record_action(&env, &order_hash, EscrowAction::Import, caller, record.locked);
+
+        // Counters and totals as if the escrow had been created here
+        let (_, deposit_held) = Self::unpaid(&immutables, record.status.filled_amount);
+        let deposit_held = if Self::is_unsettled(&stage) { deposit_held } else { 0 };
+        if immutables.native_deposit {
+            Self::add_held(&env, &immutables, record.locked, deposit_held);
+        } else {
+            Self::add_locked(&env, &immutables.token.stellar, record.locked);
+        }
+        Self::add_order_stats(&env, &immutables, immutables.amount, deposit_held);
+        let mut stats = Self::get_stats(env.clone());
+        match record.escrow_type {
+            EscrowType::Source => stats.source += 1,
+            EscrowType::Destination => stats.destination += 1,
+        }
+        *Self::stage_count(&mut stats, &stage) += 1;
+        env.storage().instance().set(&STATS, &stats);
+
+        events::publish(&env, (symbol_short!("Imported"), order_hash.clone()), stage);
+        Ok(order_hash)
+    }
+
+    /// Domain separator of this factory: keccak256 of its address XDR and the network id
+    pub fn get_domain_separator(env: Env) -> BytesN<32> {
+        order::domain_separator(&env, &env.current_contract_address(), &env.ledger().network_id())
+    }
+
+    /// Key identifying an escrow across factories and networks, for off-chain stores that see
+    /// more than one deployment: keccak256(domain separator || order_hash). Every entry point
+    /// keeps taking the order id
+    pub fn get_escrow_key(env: Env, order_id: OrderId) -> BytesN<32> {
+        order::escrow_key(&env, &Self::get_domain_separator(env.clone()), &order_id.hash())
+    }
+
+    /// Bytes the migration key signs to authorize importing an export into this factory
+    pub fn get_import_message(env: Env, export: Bytes) -> Bytes {
+        let mut message = env.current_contract_address().to_xdr(&env);
+        message.append(&export);
+        message
+    }
+
+    /// Set the ed25519 key attesting exports of the factory being migrated from (admin only)
+    pub fn set_migration_key(env: Env, approvers: Vec<Address>, public_key: BytesN<32>) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&MIGRATION_KEY, &public_key);
+        events::publish(&env, (symbol_short!("MigKey"),), public_key);
+        Ok(())
+    }
+
+    pub fn get_migration_key(env: Env) -> Option<BytesN<32>> {
+        env.storage().instance().get(&MIGRATION_KEY)
+    }
+
+    /// Set the ed25519 key attesting claimants of orders with attestation_required (admin only).
+    /// Replacing it voids every attestation the previous key made
+    pub fn set_attestor(env: Env, approvers: Vec<Address>, public_key: BytesN<32>) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&ATTESTOR, &public_key);
+        events::publish(&env, (symbol_short!("Attestor"),), public_key);
+        Ok(())
+    }
+
+    pub fn get_attestor(env: Env) -> Option<BytesN<32>> {
+        env.storage().instance().get(&ATTESTOR)
+    }
+
+    /// Bytes the attestor signs to attest a claimant's Stellar address to this factory
+    pub fn get_attestation_message(env: Env, claimant: Address) -> Bytes {
+        let mut message = env.current_contract_address().to_xdr(&env);
+        message.append(&claimant.to_xdr(&env));
+        message
+    }
+
+    /// Record the attestor's signature over get_attestation_message(claimant), letting the
+    /// claimant receive withdrawals of orders with attestation_required. Anyone may submit it
+    pub fn submit_attestation(env: Env, claimant: Address, signature: BytesN<64>) -> Result<(), Error> {
+        let attestor = Self::get_attestor(env.clone()).ok_or(Error::InvalidSignature)?;
+        // Traps on a bad signature, aborting the whole invocation
+        env.crypto().ed25519_verify(&attestor, &Self::get_attestation_message(env.clone(), claimant.clone()), &signature);
+        let key = EscrowDataKey::Attestation(claimant.clone());
+        env.storage().persistent().set(&key, &attestor);
+        ttl::extend_persistent(&env, &key, Self::get_escrow_ttl(env.clone()));
+        events::publish(&env, (symbol_short!("Attested"), claimant), attestor);
+        Ok(())
+    }
+
+    /// Whether the claimant holds an attestation by the current attestor
+    pub fn is_attested(env: Env, claimant: Address) -> bool {
+        let attested_by: Option<BytesN<32>> = env.storage().persistent().get(&EscrowDataKey::Attestation(claimant));
+        attested_by.is_some() && attested_by == Self::get_attestor(env)
+    }
+
+    /// Escrow counts by type and by current stage
+    pub fn get_stats(env: Env) -> EscrowStats {
+        env.storage().instance().get(&STATS).unwrap_or_default()
+    }
+
+    /// Volume escrowed in a token and the safety deposits of it still held
+    pub fn get_token_stats(env: Env, token: Address) -> TokenStats {
+        env.storage().persistent().get(&EscrowDataKey::TokenStats(token)).unwrap_or_default()
+    }
+
+    /// Pay executors of public_cancel and expire a fixed rebate in the order's deposit token
+    /// on top of their share of the safety deposit (admin only), so watchdog bots stay viable
+    /// on small orders. Paid out of the rebate pool while it lasts, 0 turns rebates off
+    pub fn set_executor_rebate(env: Env, approvers: Vec<Address>, token: Address, amount: i128) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        if amount < 0 {
+            return Err(Error::AmountBelowMinimum);
+        }
+        env.storage().persistent().set(&EscrowDataKey::ExecutorRebate(token.clone()), &amount);
+        events::publish(&env, (symbol_short!("RebateSet"), token), amount);
+        Ok(())
+    }
+
+    pub fn get_executor_rebate(env: Env, token: Address) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::ExecutorRebate(token)).unwrap_or(0)
+    }
+
+    /// Add to the pool executor rebates of a token are paid from (funder auth required).
+    /// The pool counts towards the locked total, so it's never taken for escrowed funds
+    pub fn fund_rebates(env: Env, funder: Address, token: Address, amount: i128) -> Result<i128, Error> {
+        funder.require_auth();
+        if amount <= 0 {
+            return Err(Error::AmountBelowMinimum);
+        }
+        Asset::from_address(&env, &token).transfer(&funder, &env.current_contract_address(), amount);
+        let pool = Self::get_rebate_pool(env.clone(), token.clone()) + amount;
+        env.storage().persistent().set(&EscrowDataKey::RebatePool(token.clone()), &pool);
+        Self::add_locked(&env, &token, amount);
+        events::publish(&env, (symbol_short!("RebateFnd"), token), (funder, amount));
+        Ok(pool)
+    }
+
+    pub fn get_rebate_pool(env: Env, token: Address) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::RebatePool(token)).unwrap_or(0)
+    }
+
+    /// Amount plus safety deposit the factory holds for active escrows of a token, plus what
+    /// it holds for unclaimed payouts, watchdog bonds and executor rebates
+    pub fn get_total_locked(env: Env, token: Address) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::TotalLocked(token)).unwrap_or(0)
+    }
+
+    /// Amount plus safety deposit an escrow holds in its token, 0 once settled or if unknown.
+    /// An XLM safety deposit isn't included
+    pub fn get_order_locked(env: Env, order_id: OrderId) -> i128 {
+        Self::order_hash_of(&env, &order_id).map_or(0, |order_hash| Self::order_locked(&env, &order_hash))
+    }
+
+    /// Compare the tracked total of a token against the factory's actual balance
+    pub fn check_solvency(env: Env, token: Address) -> SolvencyReport {
+        let total_locked = Self::get_total_locked(env.clone(), token.clone());
+        let balance = Asset::from_address(&env, &token).balance(&env.current_contract_address());
+        SolvencyReport {
+            total_locked,
+            balance,
+            solvent: balance >= total_locked,
+        }
+    }
+
+    /// Canonical order_hash of an order, so both chains and off-chain agents derive the
+    /// identifier themselves instead of trusting the relayer's
+    pub fn compute_order_hash(env: Env, fields: OrderFields) -> Result<BytesN<32>, Error> {
+        order::hash(&env, &fields)
+    }
+
+    /// Order hash of an order placed on Stellar, see create_stellar_src_escrow
+    pub fn compute_stellar_order_hash(env: Env, fields: StellarOrderFields) -> BytesN<32> {
+        order::stellar_hash(&env, &fields)
+    }
+
+    /// Exact bytes immutables are hashed over, for clients to check their ABI packing against
+    pub fn encode_immutables(env: Env, immutables: Immutables) -> Result<Bytes, Error> {
+        immutables::encode(&env, &immutables).map_err(|_| Error::InvalidImmutables)
+    }
+
+    /// Packed uint256 layout of timelocks, as it appears in the immutables encoding
+    pub fn encode_timelocks(env: Env, timelocks: Timelocks) -> Result<BytesN<32>, Error> {
+        let word = timelocks.to_bytes(&env).map_err(|_| Error::TimeLockError)?;
+        Ok(BytesN::from_array(&env, &word))
+    }
+
+    /// Set how the token list is applied to new escrows (admin only)
+    pub fn set_token_policy(env: Env, approvers: Vec<Address>, policy: TokenPolicy) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().instance().set(&TOKEN_POLICY, &policy);
+        events::publish(&env, (symbol_short!("TknPolicy"),), policy);
+        Ok(())
+    }
+
+    pub fn get_token_policy(env: Env) -> TokenPolicy {
+        env.storage().instance().get(&TOKEN_POLICY).unwrap_or(TokenPolicy::Open)
+    }
+
+    /// Add a Stellar token contract to the allow/deny list (admin only)
+    pub fn add_token(env: Env, approvers: Vec<Address>, token: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().set(&EscrowDataKey::TokenListed(token.clone()), &true);
+        events::publish(&env, (symbol_short!("TokenAdd"), token), ());
+        Ok(())
+    }
+
+    /// Remove a Stellar token contract from the allow/deny list (admin only)
+    pub fn remove_token(env: Env, approvers: Vec<Address>, token: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().remove(&EscrowDataKey::TokenListed(token.clone()));
+        events::publish(&env, (symbol_short!("TokenRm"), token), ());
+        Ok(())
+    }
+
+    /// Stop creation of new escrows for a compromised or depegged token (admin only).
+    /// Existing escrows of the token still withdraw, cancel and rescue as usual. Unlike
+    /// announce_change this isn't timelocked: it only ever restricts new escrows, never
+    /// touches escrowed funds, and an incident can't wait PARAM_CHANGE_DELAY
+    pub fn pause_token(env: Env, approvers: Vec<Address>, token: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().set(&EscrowDataKey::TokenPaused(token.clone()), &true);
+        let mut paused = Self::paused_tokens(&env);
+        if !paused.contains(&token) {
+            paused.push_back(token.clone());
+            env.storage().instance().set(&PAUSED_TOKENS, &paused);
+        }
+        events::publish(&env, (symbol_short!("TokenPaus"), token), ());
+        Ok(())
+    }
+
+    /// Let new escrows be created for a paused token again (admin only)
+    pub fn unpause_token(env: Env, approvers: Vec<Address>, token: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().remove(&EscrowDataKey::TokenPaused(token.clone()));
+        let mut paused = Self::paused_tokens(&env);
+        if let Some(index) = paused.first_index_of(&token) {
+            paused.remove(index);
+            env.storage().instance().set(&PAUSED_TOKENS, &paused);
+        }
+        events::publish(&env, (symbol_short!("TokenUnps"), token), ());
+        Ok(())
+    }
+
+    pub fn is_token_paused(env: Env, token: Address) -> bool {
+        env.storage().persistent().has(&EscrowDataKey::TokenPaused(token))
+    }
+
+    /// Whether new escrows can be created for a token under the current policy, never while
+    /// the token is paused
+    pub fn is_token_allowed(env: Env, token: Address) -> bool {
+        if Self::is_token_paused(env.clone(), token.clone()) {
+            return false;
+        }
+        let listed = env.storage().persistent().has(&EscrowDataKey::TokenListed(token));
+        match Self::get_token_policy(env) {
+            TokenPolicy::Open => true,
+            TokenPolicy::AllowList => listed,
+            TokenPolicy::DenyList => !listed,
+        }
+    }
+
+    /// Set order size limits for a token (admin only)
+    pub fn set_token_limits(env: Env, approvers: Vec<Address>, token: Address, limits: TokenLimits) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        if limits.min_amount < 0
+            || (limits.max_amount != 0 && limits.max_amount < limits.min_amount)
+            || limits.min_safety_deposit_bps > BPS_DENOMINATOR
+        {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().persistent().set(&EscrowDataKey::TokenLimits(token.clone()), &limits);
+        events::publish(&env, (symbol_short!("TknLimits"), token), limits);
+        Ok(())
+    }
+
+    /// Remove the order size limits of a token (admin only)
+    pub fn clear_token_limits(env: Env, approvers: Vec<Address>, token: Address) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        env.storage().persistent().remove(&EscrowDataKey::TokenLimits(token.clone()));
+        events::publish(&env, (symbol_short!("TknLimits"), token), ());
+        Ok(())
+    }
+
+    pub fn get_token_limits(env: Env, token: Address) -> Option<TokenLimits> {
+        env.storage().persistent().get(&EscrowDataKey::TokenLimits(token))
+    }
+
+    /// Allow orders to name a chain as their source or destination, with its risk
+    /// parameters; re-adding a chain updates them (admin only)
+    pub fn add_supported_chain(env: Env, approvers: Vec<Address>, chain_id: u64, config: ChainConfig) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        if config.fee_bps > BPS_DENOMINATOR || config.min_safety_deposit < 0 {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().persistent().set(&EscrowDataKey::SupportedChain(chain_id), &config);
+        let mut chains = Self::supported_chain_ids(&env);
+        if !chains.contains(chain_id) {
+            chains.push_back(chain_id);
+            env.storage().instance().set(&SUPPORTED_CHAINS, &chains);
+        }
+        events::publish(&env, (symbol_short!("ChainAdd"), chain_id), config);
+        Ok(())
+    }
+
+    /// Stop accepting orders naming a chain (admin only)
+    pub fn remove_supported_chain(env: Env, approvers: Vec<Address>, chain_id: u64) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        env.storage().persistent().remove(&EscrowDataKey::SupportedChain(chain_id));
+        let mut chains = Self::supported_chain_ids(&env);
+        if let Some(index) = chains.first_index_of(chain_id) {
+            chains.remove(index);
+            env.storage().instance().set(&SUPPORTED_CHAINS, &chains);
+        }
+        events::publish(&env, (symbol_short!("ChainRm"), chain_id), ());
+        Ok(())
+    }
+
+    pub fn is_supported_chain(env: Env, chain_id: u64) -> bool {
+        env.storage().persistent().has(&EscrowDataKey::SupportedChain(chain_id))
+    }
+
+    pub fn get_chain_config(env: Env, chain_id: u64) -> Option<ChainConfig> {
+        env.storage().persistent().get(&EscrowDataKey::SupportedChain(chain_id))
+    }
+
+    /// The whole factory configuration in one call, for deployment tooling and monitoring
+    /// snapshotting it instead of reading each getter
+    pub fn get_config(env: Env) -> FactoryConfig {
+        let split = Self::get_deposit_split(env.clone());
+        let watchdogs = Self::get_watchdog_config(env.clone());
+        let mut supported_chains = Map::new(&env);
+        for chain_id in Self::supported_chain_ids(&env).iter() {
+            if let Some(config) = Self::get_chain_config(env.clone(), chain_id) {
+                supported_chains.set(chain_id, config);
+            }
+        }
+        FactoryConfig {
+            admin: Self::get_admin(env.clone()),
+            admin_signers: Self::get_admin_signers(env.clone()),
+            admin_threshold: Self::get_admin_threshold(env.clone()),
+            rescue_delay: Self::get_rescue_delay(env.clone()),
+            config_frozen: Self::is_config_frozen(env.clone()),
+            paused_tokens: Self::paused_tokens(&env),
+            treasury: split.as_ref().map(|split| split.treasury.clone()),
+            treasury_bps: split.map_or(0, |split| split.treasury_bps),
+            deposit_decay_bps: Self::get_deposit_decay(env.clone()),
+            supported_chains,
+            finality_lag: Self::get_finality_lag(env.clone()),
+            max_timelock_horizon: Self::get_max_timelock_horizon(env.clone()),
+            timestamp_tolerance: Self::get_timestamp_tolerance(env.clone()),
+            escrow_ttl: Self::get_escrow_ttl(env.clone()),
+            token_policy: Self::get_token_policy(env.clone()),
+            relayer_only: Self::get_relayer_only(env.clone()),
+            watchdog_min_bond: watchdogs.as_ref().map(|config| config.min_bond),
+            watchdog_priority_window: watchdogs.map_or(0, |config| config.priority_window),
+        }
+    }
+
+    /// Admin functions
+    pub fn get_admin(env: Env) -> Address {
+        env.storage().instance().get(&ADMIN).unwrap()
+    }
+
+    /// Replace the admin signer set and threshold, e.g. 2-of-3 (current threshold of signers required)
+    pub fn set_admin_signers(env: Env, approvers: Vec<Address>, signers: Vec<Address>, threshold: u32) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        if threshold == 0 || threshold > signers.len() {
+            return Err(Error::InvalidImmutables);
+        }
+        for (i, signer) in signers.iter().enumerate() {
+            if signers.first_index_of(&signer) != Some(i as u32) {
+                return Err(Error::InvalidImmutables);
+            }
+        }
+        env.storage().instance().set(&ADMIN_SIGNERS, &signers);
+        env.storage().instance().set(&ADMIN_THRESHOLD, &threshold);
+        events::publish(&env, (symbol_short!("AdmSgnrs"), threshold), signers);
+        Ok(())
+    }
+
+    /// Addresses allowed to approve admin actions, the constructor admin until a signer set is configured
+    pub fn get_admin_signers(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&ADMIN_SIGNERS)
+            .unwrap_or_else(|| Vec::from_array(&env, [Self::get_admin(env.clone())]))
+    }
+
+    pub fn get_admin_threshold(env: Env) -> u32 {
+        env.storage().instance().get(&ADMIN_THRESHOLD).unwrap_or(1)
+    }
+
+    pub fn get_rescue_delay(env: Env) -> u64 {
+        env.storage().instance().get(&RESCUE_DELAY).unwrap_or(86_400)
+    }
+
+    /// Set the default wait after the withdrawal stage opens for counterpart chain finality (admin only)
+    pub fn set_finality_lag(env: Env, approvers: Vec<Address>, finality_lag: u64) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        env.storage().instance().set(&FINALITY_LAG, &finality_lag);
+        events::publish(&env, (symbol_short!("FinLag"),), finality_lag);
+        Ok(())
+    }
+
+    pub fn get_finality_lag(env: Env) -> u64 {
+        env.storage().instance().get(&FINALITY_LAG).unwrap_or(0)
+    }
+
+    /// Set the longest time after deployment an order's last stage may open, in seconds (admin only)
+    pub fn set_max_timelock_horizon(env: Env, approvers: Vec<Address>, horizon: u64) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        if horizon == 0 {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().instance().set(&MAX_HORIZON, &horizon);
+        events::publish(&env, (symbol_short!("MaxHrzn"),), horizon);
+        Ok(())
+    }
+
+    pub fn get_max_timelock_horizon(env: Env) -> u64 {
+        env.storage().instance().get(&MAX_HORIZON).unwrap_or(DEFAULT_MAX_TIMELOCK_HORIZON)
+    }
+
+    /// Stage offsets create_*_escrow_with_defaults deploy escrows with (admin only), so relayers
+    /// don't have to pack timelocks themselves. The stages must be ordered like any escrow's
+    /// and open within the max timelock horizon
+    pub fn set_default_timelocks(env: Env, approvers: Vec<Address>, template: TimelockTemplate) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        let timelocks = Self::template_timelocks(&env, &template, 1);
+        timelocks::validate_timelocks(&timelocks, &env).map_err(|_| Error::TimeLockError)?;
+        let horizon = timelocks::horizon(&timelocks, &env).map_err(|_| Error::TimeLockError)?;
+        if horizon > Self::get_max_timelock_horizon(env.clone()) {
+            return Err(Error::TimelockHorizonTooLong);
+        }
+        env.storage().instance().set(&DEFAULT_TIMELOCKS, &template);
+        events::publish(&env, (symbol_short!("DefTlocks"),), template);
+        Ok(())
+    }
+
+    pub fn get_default_timelocks(env: Env) -> Option<TimelockTemplate> {
+        env.storage().instance().get(&DEFAULT_TIMELOCKS)
+    }
+
+    /// Announce a change of the rescue delay or a fee parameter (admin only), executable
+    /// PARAM_CHANGE_DELAY later so users can leave before it applies. Replaces any pending
+    /// change of the same parameter, returns when it becomes executable
+    pub fn announce_change(env: Env, approvers: Vec<Address>, change: ParamChange) -> Result<u64, Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        if let ParamChange::DepositSplit(DepositSplit { treasury_bps: bps, .. }) | ParamChange::DepositDecay(bps) = &change {
+            if *bps > BPS_DENOMINATOR {
+                return Err(Error::InvalidImmutables);
+            }
+        }
+        let executable_at = env.ledger().timestamp().saturating_add(PARAM_CHANGE_DELAY);
+        let param = change.param();
+        let pending = PendingChange { change, executable_at };
+        let mut changes = Self::get_pending_changes(env.clone());
+        changes.set(param, pending.clone());
+        env.storage().instance().set(&PENDING_CHANGES, &changes);
+        events::publish(&env, (symbol_short!("ParamAnn"), param), pending);
+        Ok(executable_at)
+    }
+
+    /// Apply the pending change of a parameter once its delay has passed (admin only)
+    pub fn execute_change(env: Env, approvers: Vec<Address>, param: Param) -> Result<ParamChange, Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        let mut changes = Self::get_pending_changes(env.clone());
+        let Some(pending) = changes.get(param) else {
+            panic_with_error!(&env, FactoryError::ChangeNotAnnounced);
+        };
+        only_after(&env, pending.executable_at).map_err(|_| Error::InvalidTime)?;
+
+        match &pending.change {
+            ParamChange::RescueDelay(rescue_delay) => {
+                env.storage().instance().set(&RESCUE_DELAY, rescue_delay);
+                events::publish(&env, (symbol_short!("RscDelay"),), *rescue_delay);
+            }
+            ParamChange::DepositSplit(split) => {
+                env.storage().instance().set(&DEPOSIT_SPLIT, split);
+                events::publish(&env, (symbol_short!("DepSplit"),), split.clone());
+            }
+            ParamChange::DepositDecay(decay_bps) => {
+                env.storage().instance().set(&DEPOSIT_DECAY, decay_bps);
+                events::publish(&env, (symbol_short!("DepDecay"),), *decay_bps);
+            }
+        }
+        changes.remove(param);
+        env.storage().instance().set(&PENDING_CHANGES, &changes);
+        Ok(pending.change)
+    }
+
+    /// Drop the pending change of a parameter (admin only)
+    pub fn cancel_change(env: Env, approvers: Vec<Address>, param: Param) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        let mut changes = Self::get_pending_changes(env.clone());
+        if changes.remove(param).is_none() {
+            panic_with_error!(&env, FactoryError::ChangeNotAnnounced);
+        }
+        env.storage().instance().set(&PENDING_CHANGES, &changes);
+        events::publish(&env, (symbol_short!("ParamCncl"), param), ());
+        Ok(())
+    }
+
+    pub fn get_pending_change(env: Env, param: Param) -> Option<PendingChange> {
+        Self::get_pending_changes(env).get(param)
+    }
+
+    pub fn get_pending_changes(env: Env) -> Map<Param, PendingChange> {
+        env.storage().instance().get(&PENDING_CHANGES).unwrap_or(Map::new(&env))
+    }
+
+    pub fn get_deposit_split(env: Env) -> Option<DepositSplit> {
+        env.storage().instance().get(&DEPOSIT_SPLIT)
+    }
+
+    /// Share of the safety deposit, in basis points, a taker forfeits to the treasury by
+    /// withdrawing at the very end of its window. The share decays linearly from none at the
+    /// window's start; orders can set their own with deposit_decay_bps. Only applies while a
+    /// treasury is configured through a DepositSplit change
+    pub fn get_deposit_decay(env: Env) -> u32 {
+        env.storage().instance().get(&DEPOSIT_DECAY).unwrap_or(0)
+    }
+
+    /// Seconds a taker withdrawal may land after its window's deadline and still pass (admin
+    /// only, at most MAX_TIMESTAMP_TOLERANCE), absorbing close-time skew between the chains.
+    /// Only the withdrawal deadline applies it, no window opens early
+    pub fn set_timestamp_tolerance(env: Env, approvers: Vec<Address>, seconds: u64) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        if seconds > MAX_TIMESTAMP_TOLERANCE {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().instance().set(&TIMESTAMP_TOLERANCE, &seconds);
+        events::publish(&env, (symbol_short!("Tolerance"),), seconds);
+        Ok(())
+    }
+
+    pub fn get_timestamp_tolerance(env: Env) -> u64 {
+        env.storage().instance().get(&TIMESTAMP_TOLERANCE).unwrap_or(0)
+    }
+
+    /// Set how many ledgers every action on an escrow keeps its records alive for (admin
+    /// only), from 1 up to the network's max TTL
+    pub fn set_escrow_ttl(env: Env, approvers: Vec<Address>, ledgers: u32) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        if ledgers == 0 || ledgers > env.storage().max_ttl() {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().instance().set(&ESCROW_TTL, &ledgers);
+        events::publish(&env, (symbol_short!("EscrowTtl"),), ledgers);
+        Ok(())
+    }
+
+    pub fn get_escrow_ttl(env: Env) -> u32 {
+        env.storage().instance().get(&ESCROW_TTL).unwrap_or(ESCROW_TTL_LEDGERS)
+    }
+
+    /// Open watchdog registrations under the given bond and priority window (admin only)
+    pub fn set_watchdog_config(env: Env, approvers: Vec<Address>, config: WatchdogConfig) -> Result<(), Error> {
+        Self::only_admin(&env, &approvers)?;
+        Self::only_unfrozen(&env)?;
+        if config.min_bond < 0 {
+            return Err(Error::InvalidImmutables);
+        }
+        env.storage().instance().set(&WATCHDOGS, &config);
+        events::publish(&env, (symbol_short!("WdConfig"),), config);
+        Ok(())
+    }
+
+    pub fn get_watchdog_config(env: Env) -> Option<WatchdogConfig> {
+        env.storage().instance().get(&WATCHDOGS)
+    }
+
+    /// Helper functions
+    // Pay out a prepared withdrawal once its caller was authorized
+    fn settle_withdraw(env: Env, order_hash: BytesN<32>, preimage: Bytes, status: EscrowStatus, immutables: Immutables, payout: PayoutSummary) -> Result<PayoutSummary, Error> {
+        // Transfer tokens from contract to recipient using standard token interface
+        Self::execute_payout(&env, &payout);
+        Self::pay_treasury(&env, &payout.safety_deposit_token, immutables.safety_deposit - payout.safety_deposit_amount)?;
+        
+        // Update stage
+        let filled_amount = Self::add_fill(&immutables, status.filled_amount, immutables.checked_amount()?)?;
+        Self::advance_status(&env, &immutables, status.stage, EscrowStage::Withdrawn, filled_amount)?;
+        Self::record_action(&env, &order_hash, EscrowAction::Withdraw, payout.safety_deposit_recipient.clone(), immutables.amount);
+        Self::record_receipt(&env, &order_hash, EscrowStage::Withdrawn, &payout);
+        record_revealed_preimage(&env, &preimage);
+        
+        if Self::get_private_secrets(env.clone()) {
+            let hashlock: BytesN<32> = env.crypto().keccak256(&preimage).into();
+            events::publish(&env, (symbol_short!("WithdrwHl"), hashlock), immutables.amount);
+        } else {
+            events::publish(&env, (symbol_short!("Withdraw"), preimage), immutables.amount);
+        }
+        Ok(payout)
+    }
+
+    // Read-only validation of a withdraw, shared by withdraw and simulate_withdraw
+    fn prepare_withdraw(
+        env: &Env,
+        order_hash: &BytesN<32>,
+        preimage: &Bytes,
+        target: Option<Address>,
+    ) -> Result<(EscrowStatus, Immutables, PayoutSummary), Error> {
+        let (escrow_type, status, immutables) = Self::load_withdrawable(env, order_hash, preimage)?;
+
+        // Validate secret and timing based on escrow type, the caller is checked by each entry point
+        only_valid_preimage(env, preimage, &immutables)?;
+        let window = Self::check_withdraw_window(env, &escrow_type, &immutables)?;
+
+        // Addresses resolved at creation. A destination escrow's amount belongs to the maker,
+        // so only a source escrow's taker can redirect it
+        let stellar_token = immutables.token.stellar.clone();
+        let (recipient, deposit_recipient) = Self::withdraw_recipients(&escrow_type, &immutables);
+        let recipient = match (&escrow_type, target) {
+            (EscrowType::Source, Some(target)) => target,
+            (EscrowType::Destination, Some(_)) => return Err(Error::InvalidCaller),
+            (_, None) => recipient,
+        };
+
+        Self::only_attested(env, &immutables, &recipient);
+
+        let amount = immutables.checked_amount()?;
+        Self::add_fill(&immutables, status.filled_amount, amount)?;
+        let payout = PayoutSummary {
+            recipient,
+            token: stellar_token,
+            amount: amount.get(),
+            safety_deposit_recipient: deposit_recipient,
+            safety_deposit_token: immutables.deposit_token(env),
+            safety_deposit_amount: Self::decay_deposit(env, &immutables, window, immutables.checked_safety_deposit()?.get())?.0,
+        };
+        Ok((status, immutables, payout))
+    }
+
+    // Escrow and status of an order that may be withdrawn now, ahead of the caller, secret
+    // and window checks
+    fn load_withdrawable(env: &Env, order_hash: &BytesN<32>, preimage: &Bytes) -> Result<(EscrowType, EscrowStatus, Immutables), Error> {
+        let (escrow_type, immutables) = Self::load_escrow(env, order_hash)?;
+        if !immutables.parts.is_empty() {
+            return Err(Error::PartsScheduled);
+        }
+
+        // Check current stage. A retry of a withdraw that went through, whose result the
+        // caller lost, is told so instead of failing like an invalid transition
+        let status = Self::load_status(env, order_hash);
+        if status.stage == EscrowStage::Withdrawn && only_valid_preimage(env, preimage, &immutables).is_ok() {
+            panic_with_error!(env, FactoryError::AlreadySettled);
+        }
+        statemachine::check_transition(&status.stage, &EscrowStage::Withdrawn)?;
+        Self::only_parent_withdrawn(env, &immutables)?;
+        Ok((escrow_type, status, immutables))
+    }
+
+    // Orders of compliance-gated corridors only pay their amount to attested claimants
+    fn only_attested(env: &Env, immutables: &Immutables, claimant: &Address) {
+        if immutables.attestation_required && !Self::is_attested(env.clone(), claimant.clone()) {
+            panic_with_error!(env, FactoryError::AttestationRequired);
+        }
+    }
+
+    // (amount, safety deposit) recipients of a withdrawal by the taker. On the destination
+    // chain the taker releases the swap amount to the maker and keeps the deposit, like DstEscrow
+    fn withdraw_recipients(escrow_type: &EscrowType, immutables: &Immutables) -> (Address, Address) {
+        let stellar_taker = immutables.taker.stellar.clone();
+        match escrow_type {
+            EscrowType::Source => (stellar_taker.clone(), stellar_taker),
+            EscrowType::Destination => (immutables.maker.stellar.clone(), stellar_taker),
+        }
+    }
+
+    // Party that locked the swap amount and gets it back when the escrow is cancelled or
+    // expires: the maker on the source chain, the taker (resolver) on the destination chain
+    fn refund_recipient(escrow_type: &EscrowType, immutables: &Immutables) -> Address {
+        match escrow_type {
+            EscrowType::Source => immutables.maker.stellar.clone(),
+            EscrowType::Destination => immutables.taker.stellar.clone(),
+        }
+    }
+
+    // Rescue start and when the escrow turns stale, another rescue delay later. Orders may ask
+    // for a longer delay than the factory default
+    fn rescue_window(env: &Env, immutables: &Immutables) -> Result<(u64, u64), Error> {
+        let rescue_delay = match immutables.rescue_delay {
+            0 => Self::get_rescue_delay(env.clone()),
+            delay => delay,
+        };
+        let rescue_start = timelocks::rescue_start(&immutables.timelocks, env, rescue_delay).map_err(|_| Error::TimeLockError)?;
+        Ok((rescue_start, rescue_start.saturating_add(rescue_delay)))
+    }
+
+    // Order hashes of escrows no participant acted on whose rescue has long been open
+    fn stale_order_hashes(env: &Env, order_ids: &Vec<OrderId>) -> Result<Vec<BytesN<32>>, Error> {
+        let mut order_hashes = Vec::new(env);
+        for order_id in order_ids.iter() {
+            let order_hash = Self::order_hash_of(env, &order_id)?;
+            let (_, immutables) = Self::load_escrow(env, &order_hash)?;
+            let status = Self::load_status(env, &order_hash);
+            if !matches!(status.stage, EscrowStage::Created | EscrowStage::Stuck) || status.filled_amount != 0 || Self::load_rescued(env, &order_hash) != 0 {
+                return Err(Error::InvalidStageTransition);
+            }
+            let (_, stale_at) = Self::rescue_window(env, &immutables)?;
+            only_after(env, stale_at).map_err(|_| Error::RescueNotStarted)?;
+            order_hashes.push_back(order_hash);
+        }
+        Ok(order_hashes)
+    }
+
+    // The default template deployed now
+    fn default_timelocks(env: &Env) -> Result<Timelocks, Error> {
+        let template = Self::get_default_timelocks(env.clone()).ok_or(Error::NoDefaultTimelocks)?;
+        let deployed_at = u32::try_from(env.ledger().timestamp()).map_err(|_| Error::TimeLockError)?;
+        Ok(Self::template_timelocks(env, &template, deployed_at))
+    }
+
+    fn template_timelocks(env: &Env, template: &TimelockTemplate, deployed_at: u32) -> Timelocks {
+        Timelocks::new(
+            env,
+            deployed_at,
+            template.src_withdrawal,
+            template.src_public_withdrawal,
+            template.src_cancellation,
+            template.src_public_cancellation,
+            template.dst_withdrawal,
+            template.dst_public_withdrawal,
+            template.dst_cancellation,
+        )
+    }
+
+    // The withdrawal window opens only once the counterpart chain is final, returns it as (start, deadline)
+    fn check_withdraw_window(env: &Env, escrow_type: &EscrowType, immutables: &Immutables) -> Result<(u64, u64), Error> {
+        let finality_lag = Self::finality_lag(env, immutables);
+        in_private_withdraw_window_within(env, &immutables.timelocks, escrow_type, finality_lag, Self::get_timestamp_tolerance(env.clone()))
+    }
+
+    // The order's own finality lag, or the one it inherits
+    fn finality_lag(env: &Env, immutables: &Immutables) -> u64 {
+        match immutables.finality_lag {
+            0 => Self::min_finality_lag(env, immutables),
+            lag => lag,
+        }
+    }
+
+    // (refund, forfeited) split of a withdrawal's safety deposit share: the forfeited part
+    // grows linearly over the window (start, deadline) up to the order's or the factory's
+    // decay, and goes to the treasury. Without a treasury the taker keeps everything
+    fn decay_deposit(env: &Env, immutables: &Immutables, window: (u64, u64), share: i128) -> Result<(i128, i128), Error> {
+        let decay_bps = match immutables.deposit_decay_bps {
+            0 => Self::get_deposit_decay(env.clone()),
+            bps => bps,
+        };
+        let (start, deadline) = window;
+        if decay_bps == 0 || deadline <= start || Self::get_deposit_split(env.clone()).is_none() {
+            return Ok((share, 0));
+        }
+        let elapsed = env.ledger().timestamp().saturating_sub(start).min(deadline - start);
+        // share * rate / scale, split on share's quotient and remainder by scale so large
+        // deposits don't overflow the product
+        let rate = decay_bps as i128 * elapsed as i128;
+        let scale = BPS_DENOMINATOR as i128 * (deadline - start) as i128;
+        let forfeited = (share % scale)
+            .checked_mul(rate)
+            .and_then(|remainder| (share / scale).checked_mul(rate)?.checked_add(remainder / scale))
+            .ok_or(Error::AmountAboveMaximum)?;
+        Ok((share - forfeited, forfeited))
+    }
+
+    // Cancel paths share everything but the timelock / cancel secret check
+    fn cancel_escrow(env: &Env, order_hash: &BytesN<32>, cancel_secret: Option<&BytesN<32>>, reason: CancelReason) -> Result<PayoutSummary, Error> {
+        let (status, immutables, payout) = Self::prepare_cancel(env, order_hash, cancel_secret)?;
+        Self::settle_cancel(env, order_hash, &status, &immutables, &payout, reason)?;
+        Ok(payout)
+    }
+
+    // Return the funds to their funder and mark the escrow Cancelled
+    fn settle_cancel(
+        env: &Env,
+        order_hash: &BytesN<32>,
+        status: &EscrowStatus,
+        immutables: &Immutables,
+        payout: &PayoutSummary,
+        reason: CancelReason,
+    ) -> Result<(), Error> {
+        Self::execute_payout(env, payout);
+        Self::advance_status(env, immutables, status.stage.clone(), EscrowStage::Cancelled, status.filled_amount)?;
+        Self::record_cancel_reason(env, order_hash, reason);
+        Self::record_action(env, order_hash, EscrowAction::Cancel, payout.recipient.clone(), payout.amount);
+        Self::record_receipt(env, order_hash, EscrowStage::Cancelled, payout);
+        Self::publish_cancelled(env, order_hash, reason, payout);
+        Ok(())
+    }
+
+    // Settling is final, so the receipt is written once and kept alive as long as the status
+    fn record_receipt(env: &Env, order_hash: &BytesN<32>, stage: EscrowStage, payout: &PayoutSummary) {
+        let key = EscrowDataKey::Receipt(order_hash.clone());
+        let receipt = SettlementReceipt {
+            stage,
+            payout: payout.clone(),
+            ledger: env.ledger().sequence(),
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage().persistent().set(&key, &receipt);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+    }
+
+    // Cancelling is final, so the reason is written once and kept alive as long as the status
+    fn record_cancel_reason(env: &Env, order_hash: &BytesN<32>, reason: CancelReason) {
+        let key = EscrowDataKey::CancelReason(order_hash.clone());
+        env.storage().persistent().set(&key, &reason);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+    }
+
+    fn publish_cancelled(env: &Env, order_hash: &BytesN<32>, reason: CancelReason, payout: &PayoutSummary) {
+        events::publish(
+            env,
+            (CANCELLED_TOPIC, order_hash.clone()),
+            EscrowCancelled {
+                reason,
+                recipient: payout.recipient.clone(),
+                amount: payout.amount,
+                safety_deposit_recipient: payout.safety_deposit_recipient.clone(),
+                safety_deposit_amount: payout.safety_deposit_amount,
+            },
+        );
+    }
+
+    // Read-only validation of a cancel, shared by cancel and simulate_cancel.
+    // A valid cancel secret replaces the cancellation timelock
+    fn prepare_cancel(
+        env: &Env,
+        order_hash: &BytesN<32>,
+        cancel_secret: Option<&BytesN<32>>,
+    ) -> Result<(EscrowStatus, Immutables, PayoutSummary), Error> {
+        let (escrow_type, immutables) = Self::load_escrow(env, order_hash)?;
+
+        // Check current stage
+        let status = Self::load_status(env, order_hash);
+        statemachine::check_transition(&status.stage, &EscrowStage::Cancelled)?;
+
+        // Validate maker and timing based on escrow type
+        Self::only_maker(env, &immutables)?;
+
+        match cancel_secret {
+            Some(cancel_secret) => only_valid_cancel_secret(env, cancel_secret, &immutables)?,
+            None => {
+                in_cancel_window(env, &immutables.timelocks, &escrow_type)?;
+            }
+        }
+
+        let payout = Self::cancel_payout(env, &escrow_type, &immutables, &status);
+        Ok((status, immutables, payout))
+    }
+
+    // What a cancel returns: the unpaid amount to the funder, the deposit to whoever provided it
+    fn cancel_payout(env: &Env, escrow_type: &EscrowType, immutables: &Immutables, status: &EscrowStatus) -> PayoutSummary {
+        // Addresses resolved at creation
+        let funder = Self::refund_recipient(escrow_type, immutables);
+
+        // Parts already withdrawn stay with the taker
+        let (amount, safety_deposit) = Self::unpaid(immutables, status.filled_amount);
+        PayoutSummary {
+            recipient: funder.clone(),
+            token: immutables.token.stellar.clone(),
+            amount,
+            safety_deposit_recipient: immutables.sponsor.clone().unwrap_or(funder),
+            safety_deposit_token: immutables.deposit_token(env),
+            safety_deposit_amount: safety_deposit,
+        }
+    }
+
+    // (caller share, treasury share) of an order's safety deposit bounty, the order's
+    // chains can set their own fee but only a configured treasury collects it
+    fn split_deposit(env: &Env, immutables: &Immutables, safety_deposit: i128) -> (i128, i128) {
+        let treasury_bps = match (Self::get_deposit_split(env.clone()), Self::chain_params(env, immutables).fee_bps) {
+            (None, _) => 0,
+            (Some(split), 0) => split.treasury_bps,
+            (Some(_), fee_bps) => fee_bps,
+        };
+        let treasury_cut = safety_deposit * treasury_bps as i128 / BPS_DENOMINATOR as i128;
+        (safety_deposit - treasury_cut, treasury_cut)
+    }
+
+    // Stricter parameters of the order's two chains, all zero for orders not bound to chains
+    fn chain_params(env: &Env, immutables: &Immutables) -> ChainConfig {
+        let mut params = ChainConfig::default();
+        for chain_id in [immutables.src_chain_id, immutables.dst_chain_id] {
+            if let Some(config) = Self::get_chain_config(env.clone(), chain_id) {
+                params.finality_lag = params.finality_lag.max(config.finality_lag);
+                params.min_safety_deposit = params.min_safety_deposit.max(config.min_safety_deposit);
+                params.fee_bps = params.fee_bps.max(config.fee_bps);
+            }
+        }
+        params
+    }
+
+    // Lag applied when the order doesn't set one, and the least it may set
+    fn min_finality_lag(env: &Env, immutables: &Immutables) -> u64 {
+        Self::get_finality_lag(env.clone()).max(Self::chain_params(env, immutables).finality_lag)
+    }
+
+    fn pay_treasury(env: &Env, token: &Address, amount: i128) -> Result<(), Error> {
+        if let Some(split) = Self::get_deposit_split(env.clone()) {
+            uni_transfer(env, token, &split.treasury, amount)?;
+        }
+        Ok(())
+    }
+
+    // Executor rebate of a public action, as much of the configured one as the pool still
+    // holds. The event records the ledger it was paid in next to the amount
+    fn pay_rebate(env: &Env, order_hash: &BytesN<32>, token: &Address, executor: &Address) -> Result<i128, Error> {
+        let pool = Self::get_rebate_pool(env.clone(), token.clone());
+        let rebate = Self::get_executor_rebate(env.clone(), token.clone()).min(pool);
+        if rebate <= 0 {
+            return Ok(0);
+        }
+        env.storage().persistent().set(&EscrowDataKey::RebatePool(token.clone()), &(pool - rebate));
+        Self::add_locked(env, token, -rebate);
+        uni_transfer(env, token, executor, rebate)?;
+        events::publish(env, (symbol_short!("Rebate"), order_hash.clone()), (executor.clone(), token.clone(), rebate, env.ledger().sequence()));
+        Ok(rebate)
+    }
+
+    // Settle an order's watchdog on a public action that opened at opens_at and returns the
+    // (caller, treasury) bounty shares. The watchdog's own execution waives the treasury's
+    // cut, anyone else is held off for the priority window and then collects the bond
+    fn settle_watchdog(
+        env: &Env,
+        order_hash: &BytesN<32>,
+        token: &Address,
+        caller: &Address,
+        opens_at: u64,
+        bounty: i128,
+        treasury_cut: i128,
+    ) -> Result<(i128, i128), Error> {
+        let Some(registered) = Self::load_watchdog(env, order_hash) else {
+            return Ok((bounty, treasury_cut));
+        };
+        let own_execution = registered.watchdog == *caller;
+        if !own_execution {
+            let priority_window = Self::get_watchdog_config(env.clone()).map_or(0, |config| config.priority_window);
+            if env.ledger().timestamp() < opens_at.saturating_add(priority_window) {
+                return Err(Error::WatchdogPriority);
+            }
+        }
+
+        env.storage().persistent().remove(&EscrowDataKey::Watchdog(order_hash.clone()));
+        Self::add_locked(env, token, -registered.bond);
+        uni_transfer(env, token, caller, registered.bond)?;
+        if own_execution {
+            return Ok((bounty + treasury_cut, 0));
+        }
+        events::publish(env, (symbol_short!("WdForfeit"), order_hash.clone()), (registered.watchdog, registered.bond));
+        Ok((bounty, treasury_cut))
+    }
+
+    fn execute_payout(env: &Env, payout: &PayoutSummary) {
+        Self::push_or_credit(env, &payout.token, &payout.recipient, payout.amount);
+        Self::push_or_credit(env, &payout.safety_deposit_token, &payout.safety_deposit_recipient, payout.safety_deposit_amount);
+    }
+
+    // Pay out, or credit the recipient to claim() later when the token rejects the transfer,
+    // so one unreachable recipient can't wedge the escrow
+    fn push_or_credit(env: &Env, token: &Address, recipient: &Address, amount: i128) {
+        if amount <= 0 || Asset::from_address(env, token).try_transfer(&env.current_contract_address(), recipient, amount) {
+            return;
+        }
+        let key = EscrowDataKey::Claimable(recipient.clone(), token.clone());
+        let claimable = Self::get_claimable(env.clone(), recipient.clone(), token.clone()) + amount;
+        env.storage().persistent().set(&key, &claimable);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+        // Still held by the factory, so it keeps counting towards the locked total
+        Self::add_locked(env, token, amount);
+        events::publish(env, (symbol_short!("Credited"), recipient.clone()), amount);
+    }
+
+    fn dry_run_result(env: &Env, stage: EscrowStage, prepared: Result<PayoutSummary, Error>) -> DryRunResult {
+        // The factory must also hold enough of the token to pay out
+        let checked = prepared.and_then(|payout| {
+            let balance = |token: &Address| Asset::from_address(env, token).balance(&env.current_contract_address());
+            let sufficient = if payout.safety_deposit_token == payout.token {
+                balance(&payout.token) >= payout.amount + payout.safety_deposit_amount
+            } else {
+                balance(&payout.token) >= payout.amount && balance(&payout.safety_deposit_token) >= payout.safety_deposit_amount
+            };
+            if !sufficient {
+                return Err(Error::InsufficientBalance);
+            }
+            Ok(payout)
+        });
+        match checked {
+            Ok(payout) => DryRunResult {
+                success: true,
+                error_code: 0,
+                stage,
+                recipient: Some(payout.recipient),
+                safety_deposit_recipient: Some(payout.safety_deposit_recipient),
+                amount: payout.amount,
+                safety_deposit_amount: payout.safety_deposit_amount,
+            },
+            Err(error) => DryRunResult {
+                success: false,
+                error_code: error as u32,
+                stage,
+                recipient: None,
+                safety_deposit_recipient: None,
+                amount: 0,
+                safety_deposit_amount: 0,
+            },
+        }
     }
 
-    /// Create source chain escrow - stores immutables and returns factory address
-    pub fn create_src_escrow(
-        env: Env,
-        immutables: Immutables,
-    ) -> Result<Address, Error> {
-        // Basic validation (amounts and timelocks) - like create_dst_escrow
-        Self::validate_basic_immutables(&env, &immutables)?;
-        
-        // Use order_hash as the unique escrow identifier (cross-chain consistency)
-        let order_hash = immutables.order_hash.clone();
-        
-        // Check if escrow already exists (prevent duplicate order_hash)
-        if env.storage().persistent().has(&EscrowDataKey::EscrowState(order_hash.clone())) {
-            return Err(Error::InvalidImmutables); // Already exists
+    // A dst escrow must be cancellable no later than its src escrow, or the resolver could be
+    // left with the maker's secret revealed and no way to claim the src side in time
+    fn only_cancellable_before_src(env: &Env, immutables: &Immutables, src_cancellation_timestamp: u64) -> Result<(), Error> {
+        let dst_cancellation = timelocks::get(&immutables.timelocks, env, Stage::DstCancellation).map_err(|_| Error::TimeLockError)?;
+        if dst_cancellation > src_cancellation_timestamp {
+            return Err(Error::InvalidCreationTime);
         }
-        
-        // Store escrow data keyed by order_hash
-        env.storage().persistent().set(&EscrowDataKey::EscrowState(order_hash.clone()), &(EscrowType::Source, immutables.clone()));
-        env.storage().persistent().set(&EscrowDataKey::EscrowStage(order_hash.clone()), &EscrowStage::Created);
-        
-        // Map addresses for cross-chain resolution (CREATE FIRST, like create_dst_escrow)
-        immutables::map_evm_to_stellar(&env, immutables.maker.evm.clone(), immutables.maker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.taker.evm.clone(), immutables.taker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.token.evm.clone(), immutables.token.stellar.clone());
-        
-        // Now validate that address mappings were created successfully (like create_dst_escrow)
-        Self::validate_address_mappings(&env, &immutables)?;
-        
-        // Store timelocks
-        timelocks::store_timelocks(&env, &immutables.timelocks);
-        
-        env.events().publish((symbol_short!("SrcCreate"), order_hash), immutables.amount);
-        Ok(env.current_contract_address())
+        Ok(())
     }
 
-    /// Create destination chain escrow - stores immutables and accepts XLM funding
-    pub fn create_dst_escrow(
-        env: Env,
-        immutables: Immutables,
-    ) -> Result<Address, Error> {
+    fn create_escrow(env: &Env, escrow_type: EscrowType, relayer: &Address, immutables: Immutables, not_after: Option<u64>) -> Result<(), Error> {
+        // Only known relayers can take an order_hash, so it can't be squatted with bogus immutables
+        if Self::get_relayer_only(env.clone()) {
+            if !Self::is_relayer(env.clone(), relayer.clone()) {
+                return Err(Error::InvalidCaller);
+            }
+            relayer.require_auth();
+        }
+
+        // Reject stale relayer transactions whose timelock schedule has already started to elapse
+        if let Some(deadline) = not_after {
+            if env.ledger().timestamp() > deadline {
+                return Err(Error::CreationDeadlinePassed);
+            }
+        }
+
         // Basic validation (amounts and timelocks)
-        Self::validate_basic_immutables(&env, &immutables)?;
-        
+        Self::validate_basic_immutables(env, &immutables)?;
+        // Stellar is the source chain of Stellar-origin orders
+        if immutables.stellar_origin && escrow_type != EscrowType::Source {
+            return Err(Error::InvalidImmutables);
+        }
+
+        if !Self::is_token_allowed(env.clone(), immutables.token.stellar.clone()) {
+            return Err(Error::TokenNotAllowed);
+        }
+
         // Use order_hash as the unique escrow identifier (cross-chain consistency)
         let order_hash = immutables.order_hash.clone();
-        
+
         // Check if escrow already exists (prevent duplicate order_hash)
         if env.storage().persistent().has(&EscrowDataKey::EscrowState(order_hash.clone())) {
             return Err(Error::InvalidImmutables); // Already exists
         }
-        
-        // Store escrow data keyed by order_hash
-        env.storage().persistent().set(&EscrowDataKey::EscrowState(order_hash.clone()), &(EscrowType::Destination, immutables.clone()));
-        env.storage().persistent().set(&EscrowDataKey::EscrowStage(order_hash.clone()), &EscrowStage::Created);
-        
+        // A reserved order id is left to its relayer, who uses up the reservation
+        if let Some((reserver, _)) = Self::live_reservation(env, &order_hash) {
+            if reserver != *relayer {
+                return Err(Error::OrderReserved);
+            }
+            relayer.require_auth();
+            env.storage().persistent().remove(&EscrowDataKey::Reservation(order_hash.clone()));
+        }
+        Self::link_parent(env, &immutables)?;
+
         // Map addresses for cross-chain resolution
-        immutables::map_evm_to_stellar(&env, immutables.maker.evm.clone(), immutables.maker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.taker.evm.clone(), immutables.taker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.token.evm.clone(), immutables.token.stellar.clone());
-        
-        // Now validate that address mappings were created successfully
-        Self::validate_address_mappings(&env, &immutables)?;
-        
-        // Note: Contract funding is handled separately via fund_escrow() function
-        
+        Self::map_addresses(env, &immutables, relayer)?;
+
+        // Capture the resolved addresses in the stored record
+        let immutables = Self::resolve_addresses(env, immutables)?;
+
+        // Store escrow data keyed by order_hash
+        Self::save_escrow(env, &order_hash, &escrow_type, &immutables);
+        Self::record_creation(env, &order_hash, relayer, &immutables.token.stellar);
+        Self::save_status(env, &order_hash, EscrowStage::Created, 0);
+        Self::add_held(env, &immutables, immutables.amount, immutables.safety_deposit);
+        Self::count_created(env, &escrow_type, &immutables);
+        statemachine::emit_stage(env, &order_hash, EscrowStage::Created, immutables.amount);
+        Self::record_action(env, &order_hash, EscrowAction::Create, immutables.maker.stellar.clone(), immutables.amount);
+
         // Store timelocks
-        timelocks::store_timelocks(&env, &immutables.timelocks);
-        
-        env.events().publish((symbol_short!("DstCreate"), order_hash), immutables.amount);
-        Ok(env.current_contract_address())
+        timelocks::store_timelocks(env, &immutables.timelocks);
+
+        let topic = match escrow_type {
+            EscrowType::Source => SRC_CREATED_TOPIC,
+            EscrowType::Destination => DST_CREATED_TOPIC,
+        };
+        let event = EscrowCreated {
+            amount: immutables.amount,
+            immutables_hash: immutables::hash(env, &immutables).map_err(|_| Error::TimeLockError)?,
+            hashlock: immutables.hashlock,
+            taker: immutables.taker.evm,
+            token: immutables.token.evm,
+            safety_deposit: immutables.safety_deposit,
+            timelocks: immutables.timelocks.packed_value,
+        };
+        events::publish(env, (topic, order_hash), event);
+        Ok(())
     }
 
-    /// Fund escrow with tokens (resolver deposits funds for user withdrawal)
-    pub fn fund_escrow(
-        env: Env,
-        order_hash: BytesN<32>,
-        from: Address,
-        amount: i128,
-    ) -> Result<(), Error> {
-        // Require authorization from the account that's sending the funds (resolver)
-        from.require_auth();
-        
-        // Get escrow data to validate it exists
-        let (_, immutables) = Self::get_escrow_state(env.clone(), order_hash.clone())?;
-        
-        // Get the token address
-        let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        
-        // Factory contract mints tokens to itself using SAC admin powers
-        // This bypasses trustline requirements since factory is SAC admin
-        let token_client = token::StellarAssetClient::new(&env, &stellar_token);
-        token_client.mint(&env.current_contract_address(), &amount);
-        
-        env.events().publish((symbol_short!("Fund"), order_hash), amount);
+    // Mappings the order sets or changes are logged and remembered under its order_hash
+    fn map_addresses(env: &Env, immutables: &Immutables, caller: &Address) -> Result<(), Error> {
+        let mut parties = Vec::new(env);
+        if Self::get_attested_mappings(env.clone()) {
+            // Maker and taker must have registered their mapping themselves
+            // A Stellar-origin maker without an EVM address authorized the escrow itself
+            for party in [&immutables.maker, &immutables.taker] {
+                if !party.is_zero_evm() && immutables::get_stellar_addr(env, &party.evm) != Some(party.stellar.clone()) {
+                    return Err(Error::MappingNotAttested);
+                }
+            }
+        } else {
+            parties.push_back(immutables.maker.clone());
+            parties.push_back(immutables.taker.clone());
+        }
+
+        // Token contracts have no EVM key to attest with
+        parties.push_back(immutables.token.clone());
+
+        let key = EscrowDataKey::OrderMappings(immutables.order_hash.clone());
+        let mut mapped = Self::load_order_mappings(env, &immutables.order_hash);
+        for party in parties.iter() {
+            // Nothing to map for a maker without an EVM address
+            if party.is_zero_evm() {
+                continue;
+            }
+            if immutables::map_and_log(env, party.evm.clone(), party.stellar.clone(), caller, Some(&immutables.order_hash))? {
+                mapped.push_back(party);
+            }
+        }
+        if !mapped.is_empty() {
+            env.storage().persistent().set(&key, &mapped);
+            ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+        }
         Ok(())
     }
 
-    /// Withdraw from escrow using secret (order_hash is the key)
-    pub fn withdraw(env: Env, order_hash: BytesN<32>, secret: BytesN<32>) -> Result<(), Error> {
-        let (escrow_type, immutables) = Self::get_escrow_state(env.clone(), order_hash.clone())?;
-        
-        // Check current stage
-        let stage: EscrowStage = env.storage().persistent().get(&EscrowDataKey::EscrowStage(order_hash.clone())).unwrap_or(EscrowStage::Created);
-        if stage != EscrowStage::Created {
-            return Err(Error::InvalidTime);
+    // Guard of the parameter changes freeze_config locks
+    fn only_unfrozen(env: &Env) -> Result<(), Error> {
+        if Self::is_config_frozen(env.clone()) {
+            return Err(Error::ConfigFrozen);
         }
-        
-        // Validate secret and timing based on escrow type
-        only_taker(&env, &immutables)?;
-        only_valid_secret(&env, &secret, &immutables)?;
-        
-        match escrow_type {
-            EscrowType::Source => {
-                let withdraw_deadline = timelocks::get(&immutables.timelocks, &env, Stage::SrcPublicWithdrawal).map_err(|_| Error::TimeLockError)?;
-                only_before(&env, withdraw_deadline)?;
-            },
-            EscrowType::Destination => {
-                let withdraw_deadline = timelocks::get(&immutables.timelocks, &env, Stage::DstPublicWithdrawal).map_err(|_| Error::TimeLockError)?;
-                only_before(&env, withdraw_deadline)?;
+        Ok(())
+    }
+
+    // Admin actions need auth from at least the threshold of distinct configured signers.
+    // With a single admin an empty approvers list stands for the admin itself
+    fn only_admin(env: &Env, approvers: &Vec<Address>) -> Result<(), Error> {
+        let signers = Self::get_admin_signers(env.clone());
+        let approvers = if approvers.is_empty() && signers.len() == 1 {
+            signers.clone()
+        } else {
+            approvers.clone()
+        };
+        for (i, approver) in approvers.iter().enumerate() {
+            if !signers.contains(&approver) || approvers.first_index_of(&approver) != Some(i as u32) {
+                return Err(Error::InvalidCaller);
             }
         }
-        
-        // Get addresses
-        let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        
-        // Transfer tokens from contract to taker using standard token interface
-        uni_transfer(&env, &stellar_token, &stellar_taker, immutables.amount)?;
-        uni_transfer(&env, &stellar_token, &stellar_taker, immutables.safety_deposit)?;
-        
-        // Update stage
-        env.storage().persistent().set(&EscrowDataKey::EscrowStage(order_hash.clone()), &EscrowStage::Withdrawn);
-        
-        env.events().publish((symbol_short!("Withdraw"), secret), immutables.amount);
+        if approvers.len() < Self::get_admin_threshold(env.clone()) {
+            return Err(Error::ThresholdNotMet);
+        }
+        for approver in approvers.iter() {
+            approver.require_auth();
+        }
         Ok(())
     }
 
-    /// Cancel escrow (maker only, after timelock) - order_hash is the key
-    pub fn cancel(env: Env, order_hash: BytesN<32>) -> Result<(), Error> {
-        let (escrow_type, immutables) = Self::get_escrow_state(env.clone(), order_hash.clone())?;
-        
-        // Check current stage
-        let stage: EscrowStage = env.storage().persistent().get(&EscrowDataKey::EscrowStage(order_hash.clone())).unwrap_or(EscrowStage::Created);
-        if stage != EscrowStage::Created {
-            return Err(Error::InvalidTime);
+    // Resolve an order id at the boundary: its hash, unless the escrow stored under that hash
+    // was created for an order of the other origin. Ids of unknown orders resolve, the lookups
+    // behind them find nothing. Every entry point keyed by order comes through here, so this is
+    // where an escrow in use gets its records' TTL bumped
+    fn order_hash_of(env: &Env, order_id: &OrderId) -> Result<BytesN<32>, Error> {
+        let order_hash = order_id.hash();
+        match Self::load_escrow(env, &order_hash) {
+            Ok((_, immutables)) if immutables.order_id() != *order_id => return Err(Error::InvalidOrderId),
+            Ok(_) => Self::extend_escrow_ttl(env, &order_hash),
+            Err(_) => {}
         }
-        
-        // Validate maker and timing based on escrow type
-        Self::only_maker(&env, &immutables)?;
-        
-        match escrow_type {
-            EscrowType::Source => {
-                let cancel_time = timelocks::get(&immutables.timelocks, &env, Stage::SrcCancellation).map_err(|_| Error::TimeLockError)?;
-                only_after(&env, cancel_time)?;
-            },
-            EscrowType::Destination => {
-                let cancel_time = timelocks::get(&immutables.timelocks, &env, Stage::DstCancellation).map_err(|_| Error::TimeLockError)?;
-                only_after(&env, cancel_time)?;
+        Ok(order_hash)
+    }
+
+    // Keep every record of an escrow alive for the configured TTL, the optional ones if present
+    fn extend_escrow_ttl(env: &Env, order_hash: &BytesN<32>) {
+        let ledgers = Self::get_escrow_ttl(env.clone());
+        let keys = [
+            EscrowDataKey::EscrowState(order_hash.clone()),
+            EscrowDataKey::EscrowStatus(order_hash.clone()),
+            EscrowDataKey::Creation(order_hash.clone()),
+            EscrowDataKey::EscrowHistory(order_hash.clone()),
+            EscrowDataKey::Funded(order_hash.clone()),
+            EscrowDataKey::OrderMappings(order_hash.clone()),
+            EscrowDataKey::WithdrawnParts(order_hash.clone()),
+            EscrowDataKey::Watchdog(order_hash.clone()),
+            EscrowDataKey::CancelReason(order_hash.clone()),
+            EscrowDataKey::Operator(order_hash.clone()),
+            EscrowDataKey::TokenMetadata(order_hash.clone()),
+            EscrowDataKey::Receipt(order_hash.clone()),
+            EscrowDataKey::RescuedAmount(order_hash.clone()),
+            EscrowDataKey::MarkedStuck(order_hash.clone()),
+            EscrowDataKey::Children(order_hash.clone()),
+        ];
+        for key in keys.iter() {
+            ttl::extend_persistent(env, key, ledgers);
+        }
+    }
+
+    fn load_escrow(env: &Env, order_hash: &BytesN<32>) -> Result<(EscrowType, Immutables), Error> {
+        let (_, escrow_type, immutables) = Self::load_escrow_record(env, order_hash)?;
+        Ok((escrow_type, immutables))
+    }
+
+    // Reservation of an order id that hasn't expired yet, as (relayer, expires_at)
+    fn live_reservation(env: &Env, order_hash: &BytesN<32>) -> Option<(Address, u64)> {
+        let reservation: (Address, u64) = env.storage().persistent().get(&EscrowDataKey::Reservation(order_hash.clone()))?;
+        (env.ledger().timestamp() < reservation.1).then_some(reservation)
+    }
+
+    // Reads the record in whichever layout it was stored with, returns its schema version too
+    fn load_escrow_record(env: &Env, order_hash: &BytesN<32>) -> Result<(u32, EscrowType, Immutables), Error> {
+        let stored: Val = env.storage().persistent().get(&EscrowDataKey::EscrowState(order_hash.clone()))
+            .ok_or(Error::InvalidImmutables)?;
+        if let Ok(record) = EscrowRecord::try_from_val(env, &stored) {
+            return match record.schema_version {
+                ESCROW_SCHEMA_VERSION => Ok((record.schema_version, record.escrow_type, record.immutables)),
+                _ => Err(Error::UnknownSchemaVersion),
+            };
+        }
+        let (escrow_type, immutables) = <(EscrowType, Immutables)>::try_from_val(env, &stored)
+            .map_err(|_| Error::UnknownSchemaVersion)?;
+        Ok((0, escrow_type, immutables))
+    }
+
+    // Every write stores the current layout, so a replaced legacy record gets upgraded
+    fn save_escrow(env: &Env, order_hash: &BytesN<32>, escrow_type: &EscrowType, immutables: &Immutables) {
+        let record = EscrowRecord {
+            schema_version: ESCROW_SCHEMA_VERSION,
+            escrow_type: escrow_type.clone(),
+            immutables: immutables.clone(),
+        };
+        env.storage().persistent().set(&EscrowDataKey::EscrowState(order_hash.clone()), &record);
+    }
+
+    fn load_funded(env: &Env, order_hash: &BytesN<32>) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::Funded(order_hash.clone())).unwrap_or(0)
+    }
+
+    fn load_withdrawn_parts(env: &Env, order_hash: &BytesN<32>) -> u32 {
+        env.storage().persistent().get(&EscrowDataKey::WithdrawnParts(order_hash.clone())).unwrap_or(0)
+    }
+
+    fn load_watchdog(env: &Env, order_hash: &BytesN<32>) -> Option<WatchdogBond> {
+        env.storage().persistent().get(&EscrowDataKey::Watchdog(order_hash.clone()))
+    }
+
+    fn load_history(env: &Env, order_hash: &BytesN<32>) -> Vec<ActionRecord> {
+        env.storage().persistent().get(&EscrowDataKey::EscrowHistory(order_hash.clone()))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn load_order_mappings(env: &Env, order_hash: &BytesN<32>) -> Vec<DualAddress> {
+        env.storage().persistent().get(&EscrowDataKey::OrderMappings(order_hash.clone()))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn load_cancel_reason(env: &Env, order_hash: &BytesN<32>) -> Option<CancelReason> {
+        env.storage().persistent().get(&EscrowDataKey::CancelReason(order_hash.clone()))
+    }
+
+    fn remaining_amount(env: &Env, order_hash: &BytesN<32>) -> i128 {
+        match Self::load_escrow(env, order_hash) {
+            Ok((_, immutables)) => {
+                let status = Self::load_status(env, order_hash);
+                if !Self::is_unsettled(&status.stage) {
+                    return 0;
+                }
+                immutables.amount - status.filled_amount
             }
+            _ => 0,
         }
-        
-        // Get addresses
-        let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        
-        // Return funds to maker
-        uni_transfer(&env, &stellar_token, &stellar_maker, immutables.amount)?;
-        uni_transfer(&env, &stellar_token, &stellar_maker, immutables.safety_deposit)?;
-        
-        // Update stage
-        env.storage().persistent().set(&EscrowDataKey::EscrowStage(order_hash.clone()), &EscrowStage::Cancelled);
-        
-        env.events().publish((symbol_short!("Cancelled"),), immutables.amount);
+    }
+
+    fn order_locked(env: &Env, order_hash: &BytesN<32>) -> i128 {
+        match Self::load_escrow(env, order_hash) {
+            Ok((_, immutables)) => {
+                let status = Self::load_status(env, order_hash);
+                if !Self::is_unsettled(&status.stage) {
+                    return 0;
+                }
+                Self::rescuable(&immutables, status.filled_amount) - Self::load_rescued(env, order_hash)
+            }
+            _ => 0,
+        }
+    }
+
+    // Status of an escrow that was never created
+    fn initial_status() -> EscrowStatus {
+        EscrowStatus {
+            stage: EscrowStage::Created,
+            filled_amount: 0,
+            live_until_ledger: 0,
+        }
+    }
+
+    fn load_status(env: &Env, order_hash: &BytesN<32>) -> EscrowStatus {
+        env.storage().persistent().get(&EscrowDataKey::EscrowStatus(order_hash.clone()))
+            .unwrap_or(Self::initial_status())
+    }
+
+    // Every stage change goes through the shared state machine
+    fn advance_status(env: &Env, immutables: &Immutables, from: EscrowStage, to: EscrowStage, filled_amount: i128) -> Result<(), Error> {
+        statemachine::transition(env, &immutables.order_hash, from.clone(), to.clone(), immutables.amount)?;
+        // Settling moves the escrow's remaining funds out, so they stop counting as locked
+        if !Self::is_unsettled(&to) {
+            let (amount, safety_deposit) = Self::unpaid(immutables, Self::load_status(env, &immutables.order_hash).filled_amount);
+            Self::add_held(env, immutables, -amount, -safety_deposit);
+            Self::add_order_stats(env, immutables, 0, -safety_deposit);
+        }
+        // Escrows created before the counters existed were never counted in their stage
+        let mut stats = Self::get_stats(env.clone());
+        let left = Self::stage_count(&mut stats, &from);
+        *left = left.saturating_sub(1);
+        *Self::stage_count(&mut stats, &to) += 1;
+        env.storage().instance().set(&STATS, &stats);
+        Self::save_status(env, &immutables.order_hash, to, filled_amount);
         Ok(())
     }
 
-    /// Rescue funds (taker only, after rescue delay) - order_hash is the key
-    pub fn rescue_funds(env: Env, order_hash: BytesN<32>, amount: i128) -> Result<(), Error> {
-        let (_, immutables) = Self::get_escrow_state(env.clone(), order_hash.clone())?;
-        
-        only_taker(&env, &immutables)?;
-        
-        let rescue_delay: u64 = env.storage().instance().get(&RESCUE_DELAY).unwrap_or(86_400);
-        let rescue_start = timelocks::rescue_start(&immutables.timelocks, &env, rescue_delay).map_err(|_| Error::TimeLockError)?;
-        only_after(&env, rescue_start)?;
-        
-        let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        
-        uni_transfer(&env, &stellar_token, &stellar_taker, amount)?;
-        env.events().publish((symbol_short!("FundsSave"), stellar_token), amount);
+    fn count_created(env: &Env, escrow_type: &EscrowType, immutables: &Immutables) {
+        let mut stats = Self::get_stats(env.clone());
+        match escrow_type {
+            EscrowType::Source => stats.source += 1,
+            EscrowType::Destination => stats.destination += 1,
+        }
+        stats.created += 1;
+        env.storage().instance().set(&STATS, &stats);
+        Self::add_order_stats(env, immutables, immutables.amount, immutables.safety_deposit);
+    }
+
+    // Volume counts towards the swap token, the safety deposit towards the token it's held in
+    fn add_order_stats(env: &Env, immutables: &Immutables, volume: i128, safety_deposits: i128) {
+        if immutables.native_deposit {
+            Self::add_token_stats(env, &immutables.token.stellar, volume, 0);
+            Self::add_token_stats(env, &immutables.deposit_token(env), 0, safety_deposits);
+        } else {
+            Self::add_token_stats(env, &immutables.token.stellar, volume, safety_deposits);
+        }
+    }
+
+    fn stage_count<'a>(stats: &'a mut EscrowStats, stage: &EscrowStage) -> &'a mut u32 {
+        match stage {
+            EscrowStage::Created => &mut stats.created,
+            EscrowStage::Withdrawn => &mut stats.withdrawn,
+            EscrowStage::Cancelled => &mut stats.cancelled,
+            EscrowStage::Rescued => &mut stats.rescued,
+            EscrowStage::Expired => &mut stats.expired,
+            EscrowStage::Stuck => &mut stats.stuck,
+            EscrowStage::PartiallyWithdrawn => &mut stats.partially_withdrawn,
+        }
+    }
+
+    fn add_token_stats(env: &Env, token: &Address, volume: i128, safety_deposits: i128) {
+        let key = EscrowDataKey::TokenStats(token.clone());
+        let mut stats = Self::get_token_stats(env.clone(), token.clone());
+        stats.volume += volume;
+        stats.safety_deposits_held += safety_deposits;
+        env.storage().persistent().set(&key, &stats);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+    }
+
+    // Created, PartiallyWithdrawn and Stuck escrows still hold their funds
+    fn is_unsettled(stage: &EscrowStage) -> bool {
+        matches!(stage, EscrowStage::Created | EscrowStage::PartiallyWithdrawn | EscrowStage::Stuck)
+    }
+
+    // Stages in which an escrow's funds went back instead of to the counterparty
+    fn is_unwound(stage: &EscrowStage) -> bool {
+        matches!(stage, EscrowStage::Cancelled | EscrowStage::Expired | EscrowStage::Rescued)
+    }
+
+    // Register an escrow under the parent leg it declares. The parent must be another escrow
+    // of this factory that hasn't been unwound, with room for one more child, and its maker
+    // must consent, so nobody can hang legs off someone else's order
+    fn link_parent(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+        let Some(parent) = &immutables.parent else {
+            return Ok(());
+        };
+        if *parent == immutables.order_hash {
+            return Err(Error::InvalidImmutables);
+        }
+        let (_, parent_immutables) = Self::load_escrow(env, parent).map_err(|_| Error::InvalidImmutables)?;
+        if Self::is_unwound(&Self::load_status(env, parent).stage) {
+            return Err(Error::InvalidImmutables);
+        }
+        parent_immutables.maker.stellar.require_auth();
+        let key = EscrowDataKey::Children(parent.clone());
+        let mut children = Self::load_children(env, parent);
+        if children.len() >= MAX_CHILDREN {
+            return Err(Error::InvalidImmutables);
+        }
+        children.push_back(immutables.order_hash.clone());
+        env.storage().persistent().set(&key, &children);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
         Ok(())
     }
 
-    /// Get escrow state by order_hash
-    pub fn get_escrow_state(env: Env, order_hash: BytesN<32>) -> Result<(EscrowType, Immutables), Error> {
-        env.storage().persistent().get(&EscrowDataKey::EscrowState(order_hash))
-            .ok_or(Error::InvalidImmutables)
+    fn supported_chain_ids(env: &Env) -> Vec<u64> {
+        env.storage().instance().get(&SUPPORTED_CHAINS).unwrap_or(Vec::new(env))
     }
 
-    /// Get escrow stage by order_hash
-    pub fn get_escrow_stage(env: Env, order_hash: BytesN<32>) -> EscrowStage {
-        env.storage().persistent().get(&EscrowDataKey::EscrowStage(order_hash))
-            .unwrap_or(EscrowStage::Created)
+    fn paused_tokens(env: &Env) -> Vec<Address> {
+        env.storage().instance().get(&PAUSED_TOKENS).unwrap_or(Vec::new(env))
     }
 
-    /// Admin functions
-    pub fn get_admin(env: Env) -> Address {
-        env.storage().instance().get(&ADMIN).unwrap()
+    fn load_children(env: &Env, order_hash: &BytesN<32>) -> Vec<BytesN<32>> {
+        env.storage().persistent().get(&EscrowDataKey::Children(order_hash.clone())).unwrap_or(Vec::new(env))
     }
 
-    pub fn get_rescue_delay(env: Env) -> u64 {
-        env.storage().instance().get(&RESCUE_DELAY).unwrap_or(86_400)
+    // A leg of a multi-leg swap only pays out after the leg it depends on did
+    fn only_parent_withdrawn(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+        match &immutables.parent {
+            Some(parent) if Self::load_status(env, parent).stage != EscrowStage::Withdrawn => Err(Error::InvalidStageTransition),
+            _ => Ok(()),
+        }
+    }
+
+    // Lock (or, negative, release) an escrow's amount and safety deposit, each under the
+    // token it's held in
+    fn add_held(env: &Env, immutables: &Immutables, amount: i128, safety_deposit: i128) {
+        if immutables.native_deposit {
+            Self::add_locked(env, &immutables.token.stellar, amount);
+            Self::add_locked(env, &immutables.deposit_token(env), safety_deposit);
+        } else {
+            Self::add_locked(env, &immutables.token.stellar, amount + safety_deposit);
+        }
+    }
+
+    // Filled amount after paying out another amount, which can never exceed the order's amount
+    fn add_fill(immutables: &Immutables, filled_amount: i128, amount: EscrowAmount) -> Result<i128, Error> {
+        match filled_amount.checked_add(amount.get()) {
+            Some(filled) if filled <= immutables.amount => Ok(filled),
+            _ => Err(Error::FillExceedsAmount),
+        }
+    }
+
+    // (amount, safety deposit) still held once filled_amount was withdrawn. The safety
+    // deposit is released pro-rata, the last part gets the rounding remainder
+    fn unpaid(immutables: &Immutables, filled_amount: i128) -> (i128, i128) {
+        let deposit_paid = if filled_amount >= immutables.amount {
+            immutables.safety_deposit
+        } else {
+            immutables.safety_deposit * filled_amount / immutables.amount
+        };
+        (immutables.amount - filled_amount, immutables.safety_deposit - deposit_paid)
+    }
+
+    // Unpaid tokens held in the swap token. An XLM safety deposit isn't rescuable in the token,
+    // it's returned alongside instead
+    fn rescuable(immutables: &Immutables, filled_amount: i128) -> i128 {
+        let (amount, safety_deposit) = Self::unpaid(immutables, filled_amount);
+        if immutables.native_deposit {
+            return amount;
+        }
+        amount + safety_deposit
+    }
+
+    fn load_rescued(env: &Env, order_hash: &BytesN<32>) -> i128 {
+        env.storage().persistent().get(&EscrowDataKey::RescuedAmount(order_hash.clone())).unwrap_or(0)
+    }
+
+    fn add_locked(env: &Env, token: &Address, delta: i128) {
+        let key = EscrowDataKey::TotalLocked(token.clone());
+        let total = Self::get_total_locked(env.clone(), token.clone()) + delta;
+        env.storage().persistent().set(&key, &total);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+    }
+
+    // Also reads the token's decimals and symbol once, so get_escrow_info doesn't have to
+    fn record_creation(env: &Env, order_hash: &BytesN<32>, relayer: &Address, token: &Address) {
+        let creation = CreationRecord {
+            ledger: env.ledger().sequence(),
+            timestamp: env.ledger().timestamp(),
+            relayer: relayer.clone(),
+        };
+        env.storage().persistent().set(&EscrowDataKey::Creation(order_hash.clone()), &creation);
+        if let Some(metadata) = Asset::from_address(env, token).metadata() {
+            env.storage().persistent().set(&EscrowDataKey::TokenMetadata(order_hash.clone()), &metadata);
+        }
+    }
+
+    fn save_status(env: &Env, order_hash: &BytesN<32>, stage: EscrowStage, filled_amount: i128) {
+        let status = EscrowStatus {
+            stage,
+            filled_amount,
+            live_until_ledger: env.ledger().sequence() + Self::get_escrow_ttl(env.clone()),
+        };
+        env.storage().persistent().set(&EscrowDataKey::EscrowStatus(order_hash.clone()), &status);
+        // Keep all records alive together so the status never outlives its immutables
+        Self::extend_escrow_ttl(env, order_hash);
+    }
+
+    // Count tokens put into an escrow, unfunded escrows are the only replaceable ones
+    // Pull amount plus safety deposit from the funder, the deposit in XLM for native deposit
+    // orders. Returns what was pulled in the swap token
+    fn pull_funding(env: &Env, immutables: &Immutables, funder: &Address) -> Result<i128, Error> {
+        let deposit_token = immutables.deposit_token(env);
+        let amount = if deposit_token == immutables.token.stellar {
+            immutables.amount + immutables.safety_deposit
+        } else {
+            Self::pull_exact(env, &deposit_token, funder, immutables.safety_deposit)?;
+            immutables.amount
+        };
+        Self::pull_exact(env, &immutables.token.stellar, funder, amount)?;
+        Ok(amount)
+    }
+
+    // The factory's balance diff is the proof of payment: a rejected transfer, or a token
+    // crediting less than it was asked to move, is an InsufficientDeposit
+    fn pull_exact(env: &Env, token: &Address, from: &Address, amount: i128) -> Result<(), Error> {
+        let asset = Asset::from_address(env, token);
+        let factory = env.current_contract_address();
+        let before = asset.balance(&factory);
+        if !asset.try_transfer(from, &factory, amount) || asset.balance(&factory) - before != amount {
+            return Err(Error::InsufficientDeposit);
+        }
+        Ok(())
+    }
+
+    fn track_funding(env: &Env, order_hash: &BytesN<32>, from: Address, amount: i128) {
+        let key = EscrowDataKey::Funded(order_hash.clone());
+        let funded = Self::load_funded(env, order_hash) + amount;
+        env.storage().persistent().set(&key, &funded);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
+        Self::record_action(env, order_hash, EscrowAction::Fund, from, amount);
+    }
+
+    // Append to the escrow's history, dropping the oldest entry once the log is full
+    fn record_action(env: &Env, order_hash: &BytesN<32>, action: EscrowAction, caller: Address, amount: i128) {
+        let key = EscrowDataKey::EscrowHistory(order_hash.clone());
+        let mut history = Self::load_history(env, order_hash);
+        if history.len() >= HISTORY_LIMIT {
+            history.pop_front();
+        }
+        history.push_back(ActionRecord {
+            action,
+            caller,
+            timestamp: env.ledger().timestamp(),
+            amount,
+        });
+        env.storage().persistent().set(&key, &history);
+        ttl::extend_persistent(env, &key, Self::get_escrow_ttl(env.clone()));
     }
 
-    /// Helper functions
     fn only_maker(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+        // A Stellar-origin maker without an EVM address has no mapping, only its stored address
+        if immutables.maker.is_zero_evm() {
+            return Ok(());
+        }
         let _stellar_maker = immutables::get_stellar_addr(env, &immutables.maker.evm)
             .ok_or(Error::AddressMappingMissing)?;
         // TODO: Add proper caller authentication
         Ok(())
     }
 
-    fn validate_src_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-        if immutables.amount <= 0 || immutables.safety_deposit <= 0 {
+    fn validate_basic_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+        immutables.checked_amount()?;
+        immutables.checked_safety_deposit()?;
+        immutables.check_evm_addresses(Self::get_native_token_allowed(env.clone()))?;
+        timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
+        // Funds can't be locked beyond the horizon, every stage must open within it
+        let horizon = timelocks::horizon(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
+        if horizon > Self::get_max_timelock_horizon(env.clone()) {
+            return Err(Error::TimelockHorizonTooLong);
+        }
+        // Expiration is optional but must lie after deployment when set
+        if immutables.expiration != 0 && immutables.expiration <= immutables.timelocks.get_deployed_at(env).map_err(|_| Error::TimeLockError)? as u64 {
             return Err(Error::InvalidImmutables);
         }
-        timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
-        // Verify Stellar addresses exist
-        if immutables::get_stellar_addr(env, &immutables.maker.evm).is_none() ||
-           immutables::get_stellar_addr(env, &immutables.taker.evm).is_none() ||
-           immutables::get_stellar_addr(env, &immutables.token.evm).is_none() {
-            return Err(Error::AddressMappingMissing);
+        // A per-order rescue delay can only lengthen the factory's safety margin
+        if immutables.rescue_delay != 0 && immutables.rescue_delay < Self::get_rescue_delay(env.clone()) {
+            return Err(Error::InvalidImmutables);
         }
-        Ok(())
-    }
-
-    fn validate_basic_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-        if immutables.amount <= 0 || immutables.safety_deposit <= 0 {
+        // Orders bound to a chain pair must name two distinct supported chains
+        if immutables.src_chain_id != 0 || immutables.dst_chain_id != 0 {
+            if immutables.src_chain_id == immutables.dst_chain_id {
+                return Err(Error::InvalidImmutables);
+            }
+            for chain_id in [immutables.src_chain_id, immutables.dst_chain_id] {
+                if !Self::is_supported_chain(env.clone(), chain_id) {
+                    return Err(Error::UnsupportedChain);
+                }
+            }
+            if immutables.safety_deposit < Self::chain_params(env, immutables).min_safety_deposit {
+                return Err(Error::SafetyDepositTooLow);
+            }
+        }
+        // Likewise a per-order finality lag can only lengthen the factory and chain defaults
+        if immutables.finality_lag != 0 && immutables.finality_lag < Self::min_finality_lag(env, immutables) {
             return Err(Error::InvalidImmutables);
         }
-        timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
+        // An order can forfeit at most its whole safety deposit
+        if immutables.deposit_decay_bps > BPS_DENOMINATOR {
+            return Err(Error::InvalidImmutables);
+        }
+        // A partial withdrawal schedule must split exactly the amount, one secret per part
+        if !immutables.parts.is_empty() {
+            if immutables.parts.len() > MAX_WITHDRAWAL_PARTS {
+                return Err(Error::InvalidImmutables);
+            }
+            let mut total: i128 = 0;
+            for (i, part) in immutables.parts.iter().enumerate() {
+                let part_amount = EscrowAmount::new(part.amount)?;
+                if immutables.parts.iter().skip(i + 1).any(|other| other.hashlock == part.hashlock) {
+                    return Err(Error::InvalidImmutables);
+                }
+                total = total.checked_add(part_amount.get()).ok_or(Error::InvalidImmutables)?;
+            }
+            if total != immutables.amount {
+                return Err(Error::InvalidImmutables);
+            }
+        }
+        // Per-token order size limits
+        if let Some(limits) = Self::get_token_limits(env.clone(), immutables.token.stellar.clone()) {
+            if immutables.amount < limits.min_amount {
+                return Err(Error::AmountBelowMinimum);
+            }
+            if limits.max_amount != 0 && immutables.amount > limits.max_amount {
+                return Err(Error::AmountAboveMaximum);
+            }
+            let required = immutables.amount
+                .checked_mul(limits.min_safety_deposit_bps as i128)
+                .ok_or(Error::InvalidImmutables)?;
+            let provided = immutables.safety_deposit
+                .checked_mul(BPS_DENOMINATOR as i128)
+                .ok_or(Error::InvalidImmutables)?;
+            if provided < required {
+                return Err(Error::SafetyDepositTooLow);
+            }
+        }
         Ok(())
     }
 
-    fn validate_address_mappings(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-        // Verify Stellar addresses exist (should be available after mapping creation)
-        if immutables::get_stellar_addr(env, &immutables.maker.evm).is_none() ||
-           immutables::get_stellar_addr(env, &immutables.taker.evm).is_none() ||
-           immutables::get_stellar_addr(env, &immutables.token.evm).is_none() {
-            return Err(Error::AddressMappingMissing);
+    // Resolve the parties through the mapping once, at creation. Payouts only use the
+    // stored copies, so later mapping changes can't redirect an existing escrow
+    fn resolve_addresses(env: &Env, mut immutables: Immutables) -> Result<Immutables, Error> {
+        for party in [&mut immutables.maker, &mut immutables.taker, &mut immutables.token] {
+            // Only a Stellar-origin maker can lack an EVM address, it keeps its Stellar one
+            if party.is_zero_evm() {
+                continue;
+            }
+            party.stellar = immutables::get_stellar_addr(env, &party.evm)
+                .ok_or(Error::AddressMappingMissing)?;
         }
-        Ok(())
+        Self::check_distinct_parties(env, &immutables)?;
+        Ok(immutables)
     }
 
-    fn validate_dst_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-        Self::validate_basic_immutables(env, immutables)?;
-        Self::validate_address_mappings(env, immutables)?;
+    // Orders whose parties coincide pass every other check but only move funds in circles:
+    // maker and taker must differ on both chains, the token can't be either of them and
+    // no party can be the factory itself
+    fn check_distinct_parties(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+        let (maker, taker, token) = (&immutables.maker, &immutables.taker, &immutables.token);
+        if maker.evm == taker.evm || maker.stellar == taker.stellar {
+            return Err(Error::SelfTransfer);
+        }
+        if token.stellar == maker.stellar || token.stellar == taker.stellar {
+            return Err(Error::SelfTransfer);
+        }
+        let factory = env.current_contract_address();
+        let mut parties = [Some(&maker.stellar), Some(&taker.stellar), Some(&token.stellar), immutables.sponsor.as_ref()].into_iter().flatten();
+        if parties.any(|party| *party == factory) {
+            return Err(Error::SelfTransfer);
+        }
         Ok(())
     }
 }
 
 mod test;
+
+mod budget;
@@ -2,16 +2,29 @@
 
 use super::*;
 use shared::{
-    other_immutables as immutables, DualAddress, EscrowError, Immutables, Timelocks,
+    other_immutables as immutables, DualAddress, EscrowError, Timelocks,
 };
 use soroban_sdk::testutils::{Address as _, Ledger};
-use soroban_sdk::{Address, BytesN, Env};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Address, BytesN, Env, Vec};
 use soroban_sdk::token::{StellarAssetClient as TokenAdmin, TokenClient};
+use test_fixtures::{
+    create_test_dual_address, create_test_dual_address_with_stellar, create_test_immutables_with_secret, create_test_secret,
+};
+
+extern crate std;
+
+// What a try_ call returns when it fails with a FactoryError, which the client can't decode
+// as the shared error type its functions declare
+fn factory_error<T>(error: FactoryError) -> Result<T, Result<EscrowError, soroban_sdk::InvokeError>> {
+    Err(Err(soroban_sdk::InvokeError::Contract(error as u32)))
+}
 
-// Helper functions for testing (following dst/src escrow patterns)
+// Cancellation start of the src escrow the test dst escrows are created against
+const SRC_CANCELLATION: u64 = 1400;
 
 // Token setup helper function
-fn setup_token(env: &Env) -> (Address, TokenAdmin) {
+fn setup_token(env: &Env) -> (Address, TokenAdmin<'_>) {
     let token_admin = Address::generate(env);
     let token = env.register_stellar_asset_contract_v2(token_admin.clone());
     let token_admin_client = TokenAdmin::new(env, &token.address());
@@ -22,57 +35,14 @@ fn setup_token(env: &Env) -> (Address, TokenAdmin) {
     (token.address(), token_admin_client)
 }
 
-fn create_test_dual_address(env: &Env) -> DualAddress {
-    let e = Env::default();
-    let evm_addr = BytesN::from_array(env, &[0x42; 20]);
-    let stellar_addr = Address::generate(&e);
-    DualAddress {
-        evm: evm_addr,
-        stellar: stellar_addr,
-    }
-}
-
-fn create_test_dual_address_with_stellar(env: &Env, stellar_addr: Address, evm_suffix: u8) -> DualAddress {
-    let mut evm_bytes = [0x42; 20];
-    evm_bytes[19] = evm_suffix; // Make unique EVM addresses
-    let evm_addr = BytesN::from_array(env, &evm_bytes);
-    DualAddress {
-        evm: evm_addr,
-        stellar: stellar_addr,
-    }
-}
-
-fn create_test_secret(env: &Env) -> (BytesN<32>, BytesN<32>) {
-    let secret = BytesN::from_array(env, &[0x42; 32]);
-    let secret_bytes = soroban_sdk::Bytes::from_array(env, &secret.to_array());
-    let hashlock = env.crypto().keccak256(&secret_bytes).into();
-    (secret, hashlock)
-}
-
-fn create_test_immutables_with_secret(
-    env: &Env,
-    _secret: BytesN<32>,
-    hashlock: BytesN<32>,
-) -> Immutables {
-    Immutables {
-        order_hash: BytesN::from_array(env, &[0x01; 32]),
-        hashlock,
-        maker: create_test_dual_address(env),
-        taker: create_test_dual_address(env),
-        token: create_test_dual_address(env),
-        amount: 1000,
-        safety_deposit: 100,
-        timelocks: Timelocks::new(
-            env, 1000, // deployed_at
-            100,  // src_withdrawal
-            200,  // src_public_withdrawal
-            300,  // src_cancellation
-            400,  // src_public_cancellation
-            150,  // dst_withdrawal
-            250,  // dst_public_withdrawal
-            350,  // dst_cancellation
-        ),
-    }
+// Announce a parameter change and execute it once the delay has passed, leaving the clock as it was
+fn apply_change(env: &Env, client: &EscrowFactoryClient, change: ParamChange) {
+    let approvers = Vec::new(env);
+    client.announce_change(&approvers, &change);
+    let now = env.ledger().timestamp();
+    env.ledger().with_mut(|ledger| ledger.timestamp = now + PARAM_CHANGE_DELAY);
+    client.execute_change(&approvers, &change.param());
+    env.ledger().with_mut(|ledger| ledger.timestamp = now);
 }
 
 // ===== FACTORY TESTS =====
@@ -96,6 +66,73 @@ fn test_factory_constructor() {
     assert_eq!(stored_delay, 86400);
 }
 
+#[test]
+fn test_factory_cannot_be_initialized_twice() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin.clone(), 86400u64));
+
+    env.as_contract(&contract_id, || {
+        assert!(EscrowFactory::is_initialized(env.clone()));
+        assert_eq!(
+            EscrowFactory::__constructor(env.clone(), Address::generate(&env), 0),
+            Err(EscrowError::AlreadyInitialized)
+        );
+        assert_eq!(EscrowFactory::get_admin(env.clone()), admin);
+        assert_eq!(EscrowFactory::get_rescue_delay(env.clone()), 86400);
+    });
+}
+
+#[test]
+fn test_freeze_config_locks_fee_parameters() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let treasury = Address::generate(&env);
+
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 2000 }));
+    assert!(!client.is_config_frozen());
+    client.freeze_config(&Vec::new(&env));
+    assert!(client.is_config_frozen());
+
+    assert_eq!(
+        client.try_announce_change(&Vec::new(&env), &ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 0 })),
+        Err(Ok(EscrowError::ConfigFrozen))
+    );
+    assert_eq!(
+        client.try_announce_change(&Vec::new(&env), &ParamChange::DepositDecay(5000)),
+        Err(Ok(EscrowError::ConfigFrozen))
+    );
+    assert_eq!(client.try_freeze_config(&Vec::new(&env)), Err(Ok(EscrowError::ConfigFrozen)));
+    assert_eq!(client.get_deposit_decay(), 0);
+    assert_eq!(client.get_rescue_delay(), 86400);
+
+    // Chain fees and the settings open orders read are locked as well
+    let ethereum = ChainConfig { finality_lag: 60, min_safety_deposit: 100, fee_bps: 2500 };
+    assert_eq!(
+        client.try_add_supported_chain(&Vec::new(&env), &1, &ethereum),
+        Err(Ok(EscrowError::ConfigFrozen))
+    );
+    assert_eq!(client.try_remove_supported_chain(&Vec::new(&env), &1), Err(Ok(EscrowError::ConfigFrozen)));
+    assert_eq!(
+        client.try_set_executor_rebate(&Vec::new(&env), &treasury, &10),
+        Err(Ok(EscrowError::ConfigFrozen))
+    );
+    assert_eq!(client.try_set_finality_lag(&Vec::new(&env), &30), Err(Ok(EscrowError::ConfigFrozen)));
+    assert_eq!(client.try_set_timestamp_tolerance(&Vec::new(&env), &5), Err(Ok(EscrowError::ConfigFrozen)));
+    assert_eq!(
+        client.try_set_watchdog_config(&Vec::new(&env), &WatchdogConfig { min_bond: 10, priority_window: 60 }),
+        Err(Ok(EscrowError::ConfigFrozen))
+    );
+    assert!(!client.is_supported_chain(&1));
+    assert_eq!(client.get_finality_lag(), 0);
+
+    // Settings only read when an order is created stay adjustable
+    client.set_max_timelock_horizon(&Vec::new(&env), &86_400);
+    assert_eq!(client.get_max_timelock_horizon(), 86_400);
+}
+
 #[test]
 fn test_factory_get_admin() {
     let env = Env::default();
@@ -132,7 +169,7 @@ fn test_factory_create_src_escrow_basic() {
     
     env.as_contract(&contract_id, || {
         // Note: This will likely fail due to missing address mappings, but we're testing the basic call
-        let _result = EscrowFactory::create_src_escrow(env.clone(), immutables);
+        let _result = EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables, None);
         // Test that the function can be called without panicking
     });
 }
@@ -142,17 +179,109 @@ fn test_factory_create_dst_escrow_basic() {
     let env = Env::default();
     let admin = Address::generate(&env);
     let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
     
     let (secret, hashlock) = create_test_secret(&env);
-    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
     
     env.as_contract(&contract_id, || {
-        // Note: This will likely fail due to missing address mappings, but we're testing the basic call
-        let _result = EscrowFactory::create_dst_escrow(env.clone(), immutables);
-        // Test that the function can be called without panicking
+        // The relayer funds amount plus safety deposit in the same call
+        let result = EscrowFactory::create_dst_escrow(env.clone(), relayer.clone(), immutables, SRC_CANCELLATION, None);
+        assert!(result.is_ok());
+    });
+    assert_eq!(TokenClient::new(&env, &token_address).balance(&contract_id), 1100);
+}
+
+#[test]
+fn test_create_dst_escrow_requires_full_deposit() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1099);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+    // One stroop short of amount plus safety deposit, nothing is registered or moved
+    assert_eq!(
+        client.try_create_dst_escrow(&relayer, &immutables, &SRC_CANCELLATION, &None),
+        Err(Ok(EscrowError::InsufficientDeposit))
+    );
+    assert!(client.try_get_escrow_state(&immutables.order_id()).is_err());
+    assert_eq!(token_client.balance(&relayer), 1099);
+
+    token_admin.mint(&relayer, &1);
+    client.create_dst_escrow(&relayer, &immutables, &SRC_CANCELLATION, &None);
+    assert_eq!(token_client.balance(&relayer), 0);
+    assert_eq!(token_client.balance(&contract_id), 1100);
+    assert_eq!(client.get_order_funded(&immutables.order_id()), 1100);
+    assert_eq!(client.get_order_locked(&immutables.order_id()), 1100);
+}
+
+#[test]
+fn test_create_escrow_respects_not_after_deadline() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1050;
+    });
+
+    env.as_contract(&contract_id, || {
+        // A relayer transaction that landed after its deadline is rejected without storing anything
+        assert_eq!(
+            EscrowFactory::create_dst_escrow(env.clone(), relayer.clone(), immutables.clone(), SRC_CANCELLATION, Some(1049)),
+            Err(EscrowError::CreationDeadlinePassed)
+        );
+        assert!(EscrowFactory::get_escrow_state(env.clone(), immutables.order_id()).is_err());
+
+        // The deadline itself is still accepted
+        assert!(EscrowFactory::create_dst_escrow(env.clone(), relayer.clone(), immutables.clone(), SRC_CANCELLATION, Some(1050)).is_ok());
     });
 }
 
+#[test]
+fn test_dst_escrow_cancellation_cannot_outlast_src() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+
+    // DstCancellation opens at 1350, a second after the src escrow could already be cancelled
+    assert_eq!(
+        client.try_create_dst_escrow(&relayer, &immutables, &1349, &None),
+        Err(Ok(EscrowError::InvalidCreationTime))
+    );
+    assert!(client.try_get_escrow_state(&immutables.order_id()).is_err());
+
+    // Both opening at the same time is fine
+    client.create_dst_escrow(&relayer, &immutables, &1350, &None);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Created);
+}
+
 // ===== SINGLETON ARCHITECTURE CRITICAL TESTS =====
 
 #[test]
@@ -160,6 +289,9 @@ fn test_multiple_concurrent_escrows() {
     let env = Env::default();
     let admin = Address::generate(&env);
     let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
     
     env.as_contract(&contract_id, || {
         // Create 3 different escrows with unique order_hash values
@@ -170,6 +302,7 @@ fn test_multiple_concurrent_escrows() {
         let (secret2, hashlock2) = create_test_secret(&env);
         let mut immutables2 = create_test_immutables_with_secret(&env, secret2, hashlock2);
         immutables2.order_hash = BytesN::from_array(&env, &[0x02; 32]); // Different hash
+        immutables2.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x02); // Funded by the relayer
         
         let (secret3, hashlock3) = create_test_secret(&env);
         let mut immutables3 = create_test_immutables_with_secret(&env, secret3, hashlock3);
@@ -189,9 +322,9 @@ fn test_multiple_concurrent_escrows() {
         immutables::map_evm_to_stellar(&env, immutables3.token.evm.clone(), immutables3.token.stellar.clone());
         
         // Deploy all three escrows
-        let result1 = EscrowFactory::create_src_escrow(env.clone(), immutables1.clone());
-        let result2 = EscrowFactory::create_dst_escrow(env.clone(), immutables2.clone());
-        let result3 = EscrowFactory::create_src_escrow(env.clone(), immutables3.clone());
+        let result1 = EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables1.clone(), None);
+        let result2 = EscrowFactory::create_dst_escrow(env.clone(), relayer.clone(), immutables2.clone(), SRC_CANCELLATION, None);
+        let result3 = EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables3.clone(), None);
         
         // All should succeed independently
         assert!(result1.is_ok(), "First escrow creation should succeed");
@@ -200,8 +333,11 @@ fn test_multiple_concurrent_escrows() {
         
         // Verify each escrow is stored independently by order_hash
         let order_hash1 = immutables1.order_hash.clone();
+        let order_id1 = immutables1.order_id();
         let order_hash2 = immutables2.order_hash.clone();
+        let order_id2 = immutables2.order_id();
         let order_hash3 = immutables3.order_hash.clone();
+        let order_id3 = immutables3.order_id();
         
         // All should have different order_hash values (we set them differently)
         assert_ne!(order_hash1, order_hash2, "Order hashes should be different");
@@ -209,9 +345,9 @@ fn test_multiple_concurrent_escrows() {
         assert_ne!(order_hash1, order_hash3, "Order hashes should be different");
         
         // Verify each escrow maintains correct state (keyed by order_hash)
-        let (type1, stored1) = EscrowFactory::get_escrow_state(env.clone(), order_hash1.clone()).unwrap();
-        let (type2, stored2) = EscrowFactory::get_escrow_state(env.clone(), order_hash2.clone()).unwrap();
-        let (type3, stored3) = EscrowFactory::get_escrow_state(env.clone(), order_hash3.clone()).unwrap();
+        let (type1, stored1) = EscrowFactory::get_escrow_state(env.clone(), order_id1.clone()).unwrap();
+        let (type2, stored2) = EscrowFactory::get_escrow_state(env.clone(), order_id2.clone()).unwrap();
+        let (type3, stored3) = EscrowFactory::get_escrow_state(env.clone(), order_id3.clone()).unwrap();
         
         assert_eq!(type1, EscrowType::Source);
         assert_eq!(type2, EscrowType::Destination);
@@ -222,9 +358,9 @@ fn test_multiple_concurrent_escrows() {
         assert_eq!(stored3.order_hash, immutables3.order_hash);
         
         // All should be in Created state
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hash1), EscrowStage::Created);
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hash2), EscrowStage::Created);
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hash3), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id1), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id2), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id3), EscrowStage::Created);
     });
 }
 
@@ -243,41 +379,41 @@ fn test_withdrawal_state_isolation() {
     
     env.as_contract(&contract_id, || {
         // Create 3 escrows (A, B, C) with the actual token
-        let (secretA, hashlockA) = create_test_secret(&env);
-        let mut immutablesA = create_test_immutables_with_secret(&env, secretA.clone(), hashlockA);
-        immutablesA.order_hash = BytesN::from_array(&env, &[0xAA; 32]);
-        immutablesA.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xAA);
+        let (secret_a, hashlock_a) = create_test_secret(&env);
+        let mut immutables_a = create_test_immutables_with_secret(&env, secret_a.clone(), hashlock_a);
+        immutables_a.order_hash = BytesN::from_array(&env, &[0xAA; 32]);
+        immutables_a.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xAA);
         
-        let (secretB, hashlockB) = create_test_secret(&env);
-        let mut immutablesB = create_test_immutables_with_secret(&env, secretB.clone(), hashlockB);
-        immutablesB.order_hash = BytesN::from_array(&env, &[0xBB; 32]);
-        immutablesB.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xBB);
+        let (secret_b, hashlock_b) = create_test_secret(&env);
+        let mut immutables_b = create_test_immutables_with_secret(&env, secret_b.clone(), hashlock_b);
+        immutables_b.order_hash = BytesN::from_array(&env, &[0xBB; 32]);
+        immutables_b.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xBB);
         
-        let (secretC, hashlockC) = create_test_secret(&env);
-        let mut immutablesC = create_test_immutables_with_secret(&env, secretC.clone(), hashlockC);
-        immutablesC.order_hash = BytesN::from_array(&env, &[0xCC; 32]);
-        immutablesC.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xCC);
+        let (secret_c, hashlock_c) = create_test_secret(&env);
+        let mut immutables_c = create_test_immutables_with_secret(&env, secret_c.clone(), hashlock_c);
+        immutables_c.order_hash = BytesN::from_array(&env, &[0xCC; 32]);
+        immutables_c.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0xCC);
         
         // Set up address mappings for all escrows
-        for immutables in [&immutablesA, &immutablesB, &immutablesC] {
+        for immutables in [&immutables_a, &immutables_b, &immutables_c] {
             immutables::map_evm_to_stellar(&env, immutables.maker.evm.clone(), immutables.maker.stellar.clone());
             immutables::map_evm_to_stellar(&env, immutables.taker.evm.clone(), immutables.taker.stellar.clone());
             immutables::map_evm_to_stellar(&env, immutables.token.evm.clone(), immutables.token.stellar.clone());
         }
         
         // Deploy all three escrows
-        EscrowFactory::create_src_escrow(env.clone(), immutablesA.clone()).unwrap();
-        EscrowFactory::create_src_escrow(env.clone(), immutablesB.clone()).unwrap();
-        EscrowFactory::create_src_escrow(env.clone(), immutablesC.clone()).unwrap();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables_a.clone(), None).unwrap();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables_b.clone(), None).unwrap();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables_c.clone(), None).unwrap();
         
-        let order_hashA = immutablesA.order_hash.clone();
-        let order_hashB = immutablesB.order_hash.clone();
-        let order_hashC = immutablesC.order_hash.clone();
+        let order_id_a = immutables_a.order_id();
+        let order_id_b = immutables_b.order_id();
+        let order_id_c = immutables_c.order_id();
         
         // Verify all start in Created state
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hashA.clone()), EscrowStage::Created);
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hashB.clone()), EscrowStage::Created);
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hashC.clone()), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id_a.clone()), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id_b.clone()), EscrowStage::Created);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id_c.clone()), EscrowStage::Created);
         
         // Set time to allow withdrawals
         env.ledger().with_mut(|ledger| {
@@ -288,33 +424,33 @@ fn test_withdrawal_state_isolation() {
         let initial_balance = token_client.balance(&contract_id);
         
         // Attempt withdrawal from escrow B only
-        let withdrawal_result = EscrowFactory::withdraw(env.clone(), order_hashB.clone(), secretB);
+        let withdrawal_result = EscrowFactory::withdraw(env.clone(), order_id_b.clone(), secret_b, None);
         
         // Check state isolation: A and C should remain unaffected regardless of B's outcome
-        let stageA_after = EscrowFactory::get_escrow_stage(env.clone(), order_hashA.clone());
-        let stageC_after = EscrowFactory::get_escrow_stage(env.clone(), order_hashC.clone());
+        let stage_a_after = EscrowFactory::get_escrow_stage(env.clone(), order_id_a.clone());
+        let stage_c_after = EscrowFactory::get_escrow_stage(env.clone(), order_id_c.clone());
         
         // A and C must still be in Created state (unaffected by B's operation)
-        assert_eq!(stageA_after, EscrowStage::Created, "Escrow A should remain unaffected");
-        assert_eq!(stageC_after, EscrowStage::Created, "Escrow C should remain unaffected");
+        assert_eq!(stage_a_after, EscrowStage::Created, "Escrow A should remain unaffected");
+        assert_eq!(stage_c_after, EscrowStage::Created, "Escrow C should remain unaffected");
         
         // Verify escrow data integrity for A and C
-        let (_, storedA) = EscrowFactory::get_escrow_state(env.clone(), order_hashA).unwrap();
-        let (_, storedC) = EscrowFactory::get_escrow_state(env.clone(), order_hashC).unwrap();
+        let (_, stored_a) = EscrowFactory::get_escrow_state(env.clone(), order_id_a).unwrap();
+        let (_, stored_c) = EscrowFactory::get_escrow_state(env.clone(), order_id_c).unwrap();
         
-        assert_eq!(storedA.order_hash, immutablesA.order_hash, "Escrow A data should be intact");
-        assert_eq!(storedC.order_hash, immutablesC.order_hash, "Escrow C data should be intact");
-        assert_eq!(storedA.amount, immutablesA.amount, "Escrow A amount should be intact");
-        assert_eq!(storedC.amount, immutablesC.amount, "Escrow C amount should be intact");
+        assert_eq!(stored_a.order_hash, immutables_a.order_hash, "Escrow A data should be intact");
+        assert_eq!(stored_c.order_hash, immutables_c.order_hash, "Escrow C data should be intact");
+        assert_eq!(stored_a.amount, immutables_a.amount, "Escrow A amount should be intact");
+        assert_eq!(stored_c.amount, immutables_c.amount, "Escrow C amount should be intact");
         
         // If withdrawal succeeded, check that B's state changed but only B's state
         if withdrawal_result.is_ok() {
-            let stageB_after = EscrowFactory::get_escrow_stage(env.clone(), order_hashB);
-            assert_eq!(stageB_after, EscrowStage::Withdrawn, "Escrow B should be withdrawn if successful");
+            let stage_b_after = EscrowFactory::get_escrow_stage(env.clone(), order_id_b);
+            assert_eq!(stage_b_after, EscrowStage::Withdrawn, "Escrow B should be withdrawn if successful");
             
             // Verify token transfer happened (balance reduced)
             let final_balance = token_client.balance(&contract_id);
-            let expected_transferred = immutablesB.amount + immutablesB.safety_deposit;
+            let expected_transferred = immutables_b.amount + immutables_b.safety_deposit;
             assert_eq!(final_balance, initial_balance - expected_transferred, "Token balance should reflect withdrawal");
         }
     });
@@ -339,12 +475,12 @@ fn test_duplicate_order_hash_prevention() {
         immutables::map_evm_to_stellar(&env, immutables.token.evm.clone(), immutables.token.stellar.clone());
         
         // First deployment should succeed
-        let result1 = EscrowFactory::create_src_escrow(env.clone(), immutables.clone());
+        let result1 = EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None);
         assert!(result1.is_ok(), "First deployment should succeed");
         
         // Verify escrow was created and is in Created state
-        let order_hash = immutables.order_hash.clone();
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hash.clone()), EscrowStage::Created);
+        let order_id = immutables.order_id();
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id.clone()), EscrowStage::Created);
         
         // Create a different escrow but with the SAME order_hash
         let (secret2, hashlock2) = create_test_secret(&env);
@@ -359,7 +495,7 @@ fn test_duplicate_order_hash_prevention() {
         immutables::map_evm_to_stellar(&env, immutables2.token.evm.clone(), immutables2.token.stellar.clone());
         
         // Second deployment with same order_hash should fail
-        let result2 = EscrowFactory::create_src_escrow(env.clone(), immutables2);
+        let result2 = EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables2, None);
         assert!(result2.is_err(), "Duplicate order_hash deployment should fail");
         
         // Should get InvalidImmutables error (our contract's "already exists" error)
@@ -368,14 +504,3546 @@ fn test_duplicate_order_hash_prevention() {
         }
         
         // Verify original escrow remains intact and unaffected
-        let (stored_type, stored_data) = EscrowFactory::get_escrow_state(env.clone(), order_hash.clone()).unwrap();
+        let (stored_type, stored_data) = EscrowFactory::get_escrow_state(env.clone(), order_id.clone()).unwrap();
         assert_eq!(stored_type, EscrowType::Source);
         assert_eq!(stored_data.amount, 1000, "Original escrow amount should be unchanged");
         assert_eq!(stored_data.safety_deposit, 100, "Original escrow deposit should be unchanged");
-        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_hash), EscrowStage::Created, "Original escrow state should be unchanged");
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id), EscrowStage::Created, "Original escrow state should be unchanged");
         
         // Try with destination escrow (different function, same hash) - should also fail
-        let result3 = EscrowFactory::create_dst_escrow(env.clone(), immutables.clone());
+        let result3 = EscrowFactory::create_dst_escrow(env.clone(), Address::generate(&env), immutables.clone(), SRC_CANCELLATION, None);
         assert!(result3.is_err(), "Duplicate order_hash deployment should fail even with different escrow type");
     });
-}
\ No newline at end of file
+}
+#[test]
+fn test_withdraw_returns_payout_summary() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+
+        let payout = EscrowFactory::withdraw(env.clone(), immutables.order_id(), secret, None).unwrap();
+        let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm).unwrap();
+        assert_eq!(payout.recipient, stellar_taker);
+        assert_eq!(payout.token, token_address);
+        assert_eq!(payout.amount, 1000);
+        assert_eq!(payout.safety_deposit_recipient, stellar_taker);
+        assert_eq!(payout.safety_deposit_amount, 100);
+    });
+}
+
+#[test]
+fn test_withdraw_records_revealed_secret() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock.clone());
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        assert_eq!(EscrowFactory::get_revealed_secret(env.clone(), hashlock.clone()), None);
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        EscrowFactory::withdraw(env.clone(), immutables.order_id(), secret.clone(), None).unwrap();
+        assert_eq!(EscrowFactory::get_revealed_secret(env.clone(), hashlock), Some(secret));
+    });
+}
+
+#[test]
+fn test_private_secrets_publish_only_the_hashlock() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{IntoVal, Symbol, TryIntoVal};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+    assert!(!client.get_private_secrets());
+    client.set_private_secrets(&Vec::new(&env), &true);
+    assert!(client.get_private_secrets());
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock.clone());
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    client.withdraw(&immutables.order_id(), &secret, &None);
+
+    // The secret is only readable from storage
+    let withdraw_topics: std::vec::Vec<(Symbol, BytesN<32>)> = env.events().all().iter()
+        .filter_map(|(_, topics, _)| {
+            let first: Symbol = topics.get(0).unwrap().try_into_val(&env).ok()?;
+            (first == symbol_short!("Withdraw") || first == symbol_short!("WithdrwHl"))
+                .then(|| (first, topics.get(1).unwrap().into_val(&env)))
+        })
+        .collect();
+    assert_eq!(withdraw_topics, std::vec![(symbol_short!("WithdrwHl"), hashlock.clone())]);
+    assert_eq!(client.get_revealed_secret(&hashlock), Some(secret));
+}
+
+#[test]
+fn test_event_tag_prefixes_every_event() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{Symbol, TryIntoVal};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+    let tag = Symbol::new(&env, "desk_a");
+    assert_eq!(client.get_event_tag(), None);
+    client.set_event_tag(&Vec::new(&env), &Some(tag.clone()));
+    assert_eq!(client.get_event_tag(), Some(tag.clone()));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    client.withdraw(&immutables.order_id(), &secret, &None);
+
+    // Creation, mapping, stage and withdraw events alike
+    let factory_events: std::vec::Vec<_> = env.events().all().iter().filter(|(emitter, _, _)| *emitter == contract_id).collect();
+    assert!(!factory_events.is_empty());
+    for (_, topics, _) in factory_events {
+        let first: Symbol = topics.get(0).unwrap().try_into_val(&env).unwrap();
+        assert_eq!(first, tag);
+    }
+
+    client.set_event_tag(&Vec::new(&env), &None);
+    assert_eq!(client.get_event_tag(), None);
+}
+
+#[test]
+fn test_withdraw_with_arbitrary_length_preimage() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let preimage = Bytes::from_array(&env, &[0x5a; 45]);
+    let hashlock: BytesN<32> = env.crypto().keccak256(&preimage).into();
+    let mut immutables = create_test_immutables_with_secret(&env, BytesN::from_array(&env, &[0; 32]), hashlock.clone());
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    assert_eq!(
+        client.try_withdraw_preimage(&immutables.order_id(), &Bytes::new(&env), &None),
+        Err(Ok(EscrowError::InvalidPreimageLength))
+    );
+    let oversized = Bytes::from_slice(&env, &[0x5a; shared::baseescrow::MAX_PREIMAGE_BYTES as usize + 1]);
+    assert_eq!(
+        client.try_withdraw_preimage(&immutables.order_id(), &oversized, &None),
+        Err(Ok(EscrowError::InvalidPreimageLength))
+    );
+    // The 32-byte entry point can't open a 45-byte hashlock
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &BytesN::from_array(&env, &[0x5a; 32]), &None),
+        Err(Ok(EscrowError::InvalidSecret))
+    );
+
+    let payout = client.withdraw_preimage(&immutables.order_id(), &preimage, &None);
+    assert_eq!(payout.amount, 1000);
+    assert_eq!(client.get_revealed_preimage(&hashlock), Some(preimage));
+    assert_eq!(client.get_revealed_secret(&hashlock), None);
+}
+
+#[test]
+fn test_escrow_history_records_actions() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 100u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        let order_hash = immutables.order_hash.clone();
+        let order_id = immutables.order_id();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        EscrowFactory::withdraw(env.clone(), order_id.clone(), secret, None).unwrap();
+
+        let history = EscrowFactory::get_escrow_history(env.clone(), order_id.clone());
+        assert_eq!(history.len(), 2);
+        let created = history.get(0).unwrap();
+        assert_eq!(created.action, EscrowAction::Create);
+        assert_eq!(created.caller, immutables.maker.stellar);
+        assert_eq!(created.amount, 1000);
+        let withdrawn = history.get(1).unwrap();
+        assert_eq!(withdrawn.action, EscrowAction::Withdraw);
+        assert_eq!(withdrawn.caller, immutables.taker.stellar);
+        assert_eq!(withdrawn.timestamp, 1150);
+
+        // The log keeps only the latest entries
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1200;
+        });
+        for amount in 1..=20 {
+            EscrowFactory::record_action(&env, &order_hash, EscrowAction::Rescue, immutables.taker.stellar.clone(), amount);
+        }
+        let history = EscrowFactory::get_escrow_history(env.clone(), order_id);
+        assert_eq!(history.len(), 16);
+        assert_eq!(history.get(0).unwrap().amount, 5);
+        assert_eq!(history.last().unwrap().action, EscrowAction::Rescue);
+        assert_eq!(history.last().unwrap().amount, 20);
+    });
+}
+
+#[test]
+fn test_per_order_rescue_delay() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 100u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+        // Shorter than the factory minimum
+        immutables.rescue_delay = 50;
+        assert_eq!(
+            EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None),
+            Err(EscrowError::InvalidImmutables)
+        );
+
+        immutables.rescue_delay = 500;
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        // The factory default has passed but the order's own delay hasn't
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1200;
+        });
+        assert_eq!(
+            EscrowFactory::rescue_funds(env.clone(), immutables.order_id(), 10),
+            Err(EscrowError::RescueNotStarted)
+        );
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1600;
+        });
+        assert!(EscrowFactory::rescue_funds(env.clone(), immutables.order_id(), 10).is_ok());
+    });
+}
+
+#[test]
+fn test_lifecycle_events_use_standard_topic() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{IntoVal, Symbol, TryIntoVal, Val};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let order_hash = immutables.order_hash.clone();
+    let order_id = immutables.order_id();
+
+    env.as_contract(&contract_id, || {
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1300;
+        });
+        EscrowFactory::cancel(env.clone(), order_id.clone()).unwrap();
+    });
+
+    // One ("escrow", order_hash, stage) event per stage entered
+    let mut stages = std::vec::Vec::new();
+    for (emitter, topics, data) in env.events().all().iter() {
+        let first: Result<Symbol, _> = topics.get(0).unwrap().try_into_val(&env);
+        if emitter != contract_id || first != Ok(shared::statemachine::STAGE_EVENT_TOPIC) {
+            continue;
+        }
+        let topic_hash: BytesN<32> = topics.get(1).unwrap().into_val(&env);
+        assert_eq!(topic_hash, order_hash);
+        let stage: EscrowStage = topics.get(2).unwrap().into_val(&env);
+        let payload: shared::StageEvent = Val::into_val(&data, &env);
+        stages.push((stage, payload.amount, payload.timestamp));
+    }
+    assert_eq!(
+        stages,
+        std::vec![(EscrowStage::Created, 1000, 0), (EscrowStage::Cancelled, 1000, 1300)]
+    );
+}
+
+#[test]
+fn test_creation_event_carries_order_parameters() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{IntoVal, Symbol, TryIntoVal};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    let created: std::vec::Vec<EscrowCreated> = env.events().all().iter()
+        .filter(|(_, topics, _)| {
+            let first: Result<Symbol, _> = topics.get(0).unwrap().try_into_val(&env);
+            first == Ok(SRC_CREATED_TOPIC)
+        })
+        .map(|(_, _, data)| data.into_val(&env))
+        .collect();
+    let digest: BytesN<32> = env.crypto().keccak256(&client.encode_immutables(&immutables)).into();
+    assert_eq!(
+        created,
+        std::vec![EscrowCreated {
+            amount: 1000,
+            immutables_hash: digest,
+            hashlock: immutables.hashlock.clone(),
+            taker: immutables.taker.evm.clone(),
+            token: immutables.token.evm.clone(),
+            safety_deposit: 100,
+            timelocks: immutables.timelocks.packed_value.clone(),
+        }]
+    );
+}
+
+#[test]
+fn test_finality_lag_delays_withdrawal() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    client.set_finality_lag(&Vec::new(&env), &60);
+    assert_eq!(client.get_finality_lag(), 60);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+    // Orders can't shorten the factory default
+    immutables.finality_lag = 30;
+    assert_eq!(client.try_create_src_escrow(&Address::generate(&env), &immutables, &None), Err(Ok(EscrowError::InvalidImmutables)));
+    immutables.finality_lag = 0;
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    // SrcWithdrawal opens at 1100, plus the 60s lag
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &secret, &None),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1160;
+    });
+    assert!(client.try_withdraw(&immutables.order_id(), &secret, &None).is_ok());
+}
+
+#[test]
+fn test_sponsor_funds_and_gets_deposit_back() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    // fund_escrow mints through the SAC, so the factory is the token admin here
+    let token_address = env.register_stellar_asset_contract_v2(contract_id.clone()).address();
+    let token_client = TokenClient::new(&env, &token_address);
+
+    let sponsor = Address::generate(&env);
+    let resolver = Address::generate(&env);
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.sponsor = Some(sponsor.clone());
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    // Funding needs the sponsor's consent next to the resolver's
+    client.fund_escrow(&immutables.order_id(), &resolver, &1100);
+    let authorizers: std::vec::Vec<Address> = env.auths().into_iter().map(|(address, _)| address).collect();
+    assert!(authorizers.contains(&resolver));
+    assert!(authorizers.contains(&sponsor));
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    let payout = client.cancel(&immutables.order_id());
+    assert_eq!(payout.safety_deposit_recipient, sponsor);
+    assert_eq!(token_client.balance(&sponsor), 100);
+    assert_eq!(token_client.balance(&payout.recipient), 1000);
+}
+
+#[test]
+fn test_cooperative_cancel_with_cancel_secret() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let cancel_secret = BytesN::from_array(&env, &[0x42; 32]);
+        let cancel_hashlock: BytesN<32> = env.crypto()
+            .keccak256(&soroban_sdk::Bytes::from_array(&env, &cancel_secret.to_array()))
+            .into();
+
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock.clone());
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        immutables.cancel_hashlock = Some(cancel_hashlock);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        // Orders without a cancel hashlock can't be cancelled early
+        let mut plain = create_test_immutables_with_secret(&env, secret, hashlock);
+        plain.order_hash = BytesN::from_array(&env, &[0x0b; 32]);
+        plain.token = immutables.token.clone();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), plain.clone(), None).unwrap();
+        assert_eq!(
+            EscrowFactory::cooperative_cancel(env.clone(), plain.order_id(), cancel_secret.clone()),
+            Err(EscrowError::InvalidSecret)
+        );
+
+        // Well before SrcCancellation (1300)
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        let wrong = BytesN::from_array(&env, &[0x43; 32]);
+        assert_eq!(
+            EscrowFactory::cooperative_cancel(env.clone(), immutables.order_id(), wrong),
+            Err(EscrowError::InvalidSecret)
+        );
+
+        let payout = EscrowFactory::cooperative_cancel(env.clone(), immutables.order_id(), cancel_secret).unwrap();
+        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm).unwrap();
+        assert_eq!(payout.recipient, stellar_maker);
+        assert_eq!(token_client.balance(&stellar_maker), 1100);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), immutables.order_id()), EscrowStage::Cancelled);
+    });
+}
+
+#[test]
+fn test_cancel_returns_payout_summary() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1300; // SrcCancellation
+        });
+
+        let payout = EscrowFactory::cancel(env.clone(), immutables.order_id()).unwrap();
+        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm).unwrap();
+        assert_eq!(payout.recipient, stellar_maker);
+        assert_eq!(payout.amount + payout.safety_deposit_amount, 1100);
+        assert_eq!(token_client.balance(&stellar_maker), 1100);
+    });
+}
+
+#[test]
+fn test_expire_pays_maker_and_caller_bounty() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+    let caller = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.expiration = 1250;
+    let order_id = immutables.order_id();
+
+    env.as_contract(&contract_id, || {
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+    });
+
+    // Too early
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1249;
+    });
+    env.as_contract(&contract_id, || {
+        assert_eq!(
+            EscrowFactory::expire(env.clone(), order_id.clone(), caller.clone()),
+            Err(EscrowError::InvalidTime)
+        );
+    });
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1250;
+    });
+    env.as_contract(&contract_id, || {
+        let payout = EscrowFactory::expire(env.clone(), order_id.clone(), caller.clone()).unwrap();
+        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm).unwrap();
+        assert_eq!(payout.recipient, stellar_maker);
+        assert_eq!(payout.safety_deposit_recipient, caller);
+        assert_eq!(token_client.balance(&stellar_maker), 1000);
+        assert_eq!(token_client.balance(&caller), 100);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id.clone()), EscrowStage::Expired);
+    });
+
+    // Cannot be expired twice
+    env.as_contract(&contract_id, || {
+        assert!(EscrowFactory::expire(env.clone(), order_id.clone(), caller.clone()).is_err());
+    });
+}
+
+#[test]
+fn test_expire_without_expiration_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let caller = Address::generate(&env);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1_000_000;
+        });
+        assert_eq!(
+            EscrowFactory::expire(env.clone(), immutables.order_id(), caller.clone()),
+            Err(EscrowError::InvalidTime)
+        );
+    });
+}
+
+#[test]
+fn test_create_rejects_expiration_before_deployment() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        immutables.expiration = 999;
+        assert_eq!(
+            EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables, None),
+            Err(EscrowError::InvalidImmutables)
+        );
+    });
+}
+
+#[test]
+fn test_escrow_status_record_tracks_transitions() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        let order_id = immutables.order_id();
+
+        let status = EscrowFactory::get_escrow_status(env.clone(), order_id.clone());
+        assert_eq!(status.stage, EscrowStage::Created);
+        assert_eq!(status.filled_amount, 0);
+        assert!(status.live_until_ledger > env.ledger().sequence());
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        EscrowFactory::withdraw(env.clone(), order_id.clone(), secret, None).unwrap();
+
+        let status = EscrowFactory::get_escrow_status(env.clone(), order_id.clone());
+        assert_eq!(status.stage, EscrowStage::Withdrawn);
+        assert_eq!(status.filled_amount, immutables.amount);
+
+        // The immutable record is untouched by the transition
+        let (_, stored) = EscrowFactory::get_escrow_state(env.clone(), order_id).unwrap();
+        assert_eq!(stored.amount, immutables.amount);
+    });
+}
+
+#[test]
+fn test_withdraw_after_cancel_is_illegal_transition() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        let order_id = immutables.order_id();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1300;
+        });
+        EscrowFactory::cancel(env.clone(), order_id.clone()).unwrap();
+
+        assert_eq!(
+            EscrowFactory::withdraw(env.clone(), order_id.clone(), secret, None),
+            Err(EscrowError::InvalidStageTransition)
+        );
+        assert_eq!(
+            EscrowFactory::cancel(env.clone(), order_id.clone()),
+            Err(EscrowError::InvalidStageTransition)
+        );
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id), EscrowStage::Cancelled);
+    });
+}
+
+// ===== PERMIT TESTS =====
+
+fn sign_permit(
+    env: &Env,
+    signing_key: &ed25519_dalek::SigningKey,
+    contract_id: &Address,
+    order_hash: &BytesN<32>,
+    nonce: u64,
+    expiry: u64,
+) -> Permit {
+    use ed25519_dalek::Signer;
+
+    let message = shared::permit::payload(env, contract_id, order_hash, PermitAction::Cancel, nonce, expiry);
+    let message: std::vec::Vec<u8> = message.iter().collect();
+    let signature = signing_key.sign(&message).to_bytes();
+    Permit {
+        action: PermitAction::Cancel,
+        nonce,
+        expiry,
+        signature: BytesN::from_array(env, &signature),
+    }
+}
+
+#[test]
+fn test_cancel_with_permit() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&[0x07; 32]);
+    let public_key = BytesN::from_array(&env, &signing_key.verifying_key().to_bytes());
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let order_hash = immutables.order_hash.clone();
+    let order_id = immutables.order_id();
+
+    let stellar_maker = env.as_contract(&contract_id, || {
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm).unwrap();
+        EscrowFactory::register_permit_key(env.clone(), stellar_maker.clone(), public_key.clone());
+        stellar_maker
+    });
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    env.as_contract(&contract_id, || {
+        // Wrong nonce is rejected
+        let stale = sign_permit(&env, &signing_key, &contract_id, &order_hash, 5, 2000);
+        assert_eq!(
+            EscrowFactory::cancel_with_permit(env.clone(), order_id.clone(), stale),
+            Err(EscrowError::InvalidPermit)
+        );
+
+        // Expired permit is rejected
+        let expired = sign_permit(&env, &signing_key, &contract_id, &order_hash, 0, 1300);
+        assert_eq!(
+            EscrowFactory::cancel_with_permit(env.clone(), order_id.clone(), expired),
+            Err(EscrowError::PermitExpired)
+        );
+
+        let permit = sign_permit(&env, &signing_key, &contract_id, &order_hash, 0, 2000);
+        let payout = EscrowFactory::cancel_with_permit(env.clone(), order_id.clone(), permit).unwrap();
+        assert_eq!(payout.recipient, stellar_maker);
+        assert_eq!(token_client.balance(&stellar_maker), 1100);
+        assert_eq!(EscrowFactory::get_permit_nonce(env.clone(), stellar_maker.clone()), 1);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id.clone()), EscrowStage::Cancelled);
+    });
+}
+
+#[test]
+fn test_cancel_with_permit_requires_registered_key() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&[0x07; 32]);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        let permit = sign_permit(&env, &signing_key, &contract_id, &immutables.order_hash, 0, 2000);
+        assert_eq!(
+            EscrowFactory::cancel_with_permit(env.clone(), immutables.order_id(), permit),
+            Err(EscrowError::PermitKeyMissing)
+        );
+    });
+}
+
+// ===== ATTESTED ADDRESS MAPPING TESTS =====
+
+fn evm_key_and_address(env: &Env, seed: u8) -> (k256::ecdsa::SigningKey, BytesN<20>) {
+    let signing_key = k256::ecdsa::SigningKey::from_bytes(&[seed; 32].into()).unwrap();
+    let point = signing_key.verifying_key().to_encoded_point(false);
+    let key_hash: BytesN<32> = env
+        .crypto()
+        .keccak256(&soroban_sdk::Bytes::from_slice(env, &point.as_bytes()[1..]))
+        .into();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&key_hash.to_array()[12..]);
+    (signing_key, BytesN::from_array(env, &address))
+}
+
+fn evm_sign(env: &Env, signing_key: &k256::ecdsa::SigningKey, message: &soroban_sdk::Bytes) -> BytesN<65> {
+    let digest: BytesN<32> = shared::crypto::eth_signed_message_hash(env, message).into();
+    let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&digest.to_array()).unwrap();
+    let mut bytes = [0u8; 65];
+    bytes[..64].copy_from_slice(&signature.to_bytes());
+    bytes[64] = 27 + recovery_id.to_byte();
+    BytesN::from_array(env, &bytes)
+}
+
+fn register_attested(env: &Env, contract_id: &Address, seed: u8) -> DualAddress {
+    let (signing_key, evm) = evm_key_and_address(env, seed);
+    let stellar = Address::generate(env);
+    env.as_contract(contract_id, || {
+        let message = EscrowFactory::get_mapping_message(env.clone(), evm.clone(), stellar.clone());
+        let signature = evm_sign(env, &signing_key, &message);
+        EscrowFactory::register_address_mapping(env.clone(), evm.clone(), stellar.clone(), signature).unwrap();
+    });
+    DualAddress { evm, stellar }
+}
+
+#[test]
+fn test_register_address_mapping_with_evm_signature() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+
+    let maker = register_attested(&env, &contract_id, 0x11);
+    env.as_contract(&contract_id, || {
+        assert_eq!(immutables::get_stellar_addr(&env, &maker.evm), Some(maker.stellar.clone()));
+    });
+}
+
+#[test]
+fn test_register_address_mapping_rejects_foreign_signature() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+
+    let (_, victim_evm) = evm_key_and_address(&env, 0x11);
+    let (attacker_key, _) = evm_key_and_address(&env, 0x22);
+    let attacker_stellar = Address::generate(&env);
+
+    env.as_contract(&contract_id, || {
+        let message = EscrowFactory::get_mapping_message(env.clone(), victim_evm.clone(), attacker_stellar.clone());
+        let signature = evm_sign(&env, &attacker_key, &message);
+        assert_eq!(
+            EscrowFactory::register_address_mapping(env.clone(), victim_evm.clone(), attacker_stellar.clone(), signature),
+            Err(EscrowError::InvalidSignature)
+        );
+        assert!(immutables::get_stellar_addr(&env, &victim_evm).is_none());
+    });
+}
+
+#[test]
+fn test_attested_mode_requires_registered_mappings() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+
+    let maker = register_attested(&env, &contract_id, 0x11);
+    let taker = register_attested(&env, &contract_id, 0x22);
+
+    env.as_contract(&contract_id, || {
+        EscrowFactory::set_attested_mappings(env.clone(), Vec::new(&env), true).unwrap();
+        assert!(EscrowFactory::get_attested_mappings(env.clone()));
+    });
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.maker = maker;
+    immutables.taker = taker.clone();
+
+    env.as_contract(&contract_id, || {
+        // Impersonating the maker with a different Stellar address is rejected
+        let mut forged = immutables.clone();
+        forged.maker.stellar = Address::generate(&env);
+        assert_eq!(
+            EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), forged, None),
+            Err(EscrowError::MappingNotAttested)
+        );
+
+        assert!(EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).is_ok());
+    });
+}
+
+// ===== TOKEN POLICY TESTS =====
+
+#[test]
+fn test_token_allow_list() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    let token = immutables.token.stellar.clone();
+
+    assert_eq!(client.get_token_policy(), TokenPolicy::Open);
+    client.set_token_policy(&Vec::new(&env), &TokenPolicy::AllowList);
+    assert!(!client.is_token_allowed(&token));
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &immutables, &None),
+        Err(Ok(EscrowError::TokenNotAllowed))
+    );
+
+    client.add_token(&Vec::new(&env), &token);
+    assert!(client.is_token_allowed(&token));
+    assert!(client.try_create_src_escrow(&Address::generate(&env), &immutables, &None).is_ok());
+}
+
+#[test]
+fn test_token_deny_list() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    let token = immutables.token.stellar.clone();
+
+    client.set_token_policy(&Vec::new(&env), &TokenPolicy::DenyList);
+    client.add_token(&Vec::new(&env), &token);
+    assert!(!client.is_token_allowed(&token));
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &immutables, &None),
+        Err(Ok(EscrowError::TokenNotAllowed))
+    );
+
+    client.remove_token(&Vec::new(&env), &token);
+    assert!(client.is_token_allowed(&token));
+}
+
+#[test]
+fn test_paused_token_only_blocks_new_escrows() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    client.pause_token(&Vec::new(&env), &token_address);
+    assert!(client.is_token_paused(&token_address));
+    assert!(!client.is_token_allowed(&token_address));
+    let mut next = immutables.clone();
+    next.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &next, &None),
+        Err(Ok(EscrowError::TokenNotAllowed))
+    );
+
+    // The open escrow settles as usual
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    client.withdraw(&immutables.order_id(), &secret, &None);
+
+    client.unpause_token(&Vec::new(&env), &token_address);
+    assert!(client.is_token_allowed(&token_address));
+    assert!(client.try_create_src_escrow(&Address::generate(&env), &next, &None).is_ok());
+}
+
+// ===== TOKEN LIMITS TESTS =====
+
+#[test]
+fn test_token_limits_enforced_on_create() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    let token = immutables.token.stellar.clone();
+
+    client.set_token_limits(&Vec::new(&env), &token, &TokenLimits {
+        min_amount: 500,
+        max_amount: 5000,
+        min_safety_deposit_bps: 1000, // 10%
+    });
+
+    let mut dust = immutables.clone();
+    dust.amount = 499;
+    assert_eq!(client.try_create_src_escrow(&Address::generate(&env), &dust, &None), Err(Ok(EscrowError::AmountBelowMinimum)));
+
+    let mut oversized = immutables.clone();
+    oversized.amount = 5001;
+    oversized.safety_deposit = 1000;
+    assert_eq!(client.try_create_src_escrow(&Address::generate(&env), &oversized, &None), Err(Ok(EscrowError::AmountAboveMaximum)));
+
+    let mut thin_deposit = immutables.clone();
+    thin_deposit.safety_deposit = 99;
+    assert_eq!(client.try_create_src_escrow(&Address::generate(&env), &thin_deposit, &None), Err(Ok(EscrowError::SafetyDepositTooLow)));
+
+    // 1000 amount with 100 deposit is exactly 10%
+    assert!(client.try_create_src_escrow(&Address::generate(&env), &immutables, &None).is_ok());
+}
+
+#[test]
+fn test_token_limits_validation_and_clear() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let token = Address::generate(&env);
+
+    let inverted = TokenLimits { min_amount: 10, max_amount: 5, min_safety_deposit_bps: 0 };
+    assert_eq!(client.try_set_token_limits(&Vec::new(&env), &token, &inverted), Err(Ok(EscrowError::InvalidImmutables)));
+
+    let limits = TokenLimits { min_amount: 10, max_amount: 0, min_safety_deposit_bps: 0 };
+    client.set_token_limits(&Vec::new(&env), &token, &limits);
+    assert_eq!(client.get_token_limits(&token), Some(limits));
+    client.clear_token_limits(&Vec::new(&env), &token);
+    assert_eq!(client.get_token_limits(&token), None);
+}
+
+#[test]
+fn test_withdraw_to_target() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+    let cold_wallet = Address::generate(&env);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+
+        let payout = EscrowFactory::withdraw(env.clone(), immutables.order_id(), secret, Some(cold_wallet.clone())).unwrap();
+        let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm).unwrap();
+        assert_eq!(payout.recipient, cold_wallet);
+        assert_eq!(payout.safety_deposit_recipient, stellar_taker);
+        assert_eq!(token_client.balance(&cold_wallet), 1000);
+        assert_eq!(token_client.balance(&stellar_taker), 100);
+    });
+}
+
+#[test]
+fn test_destination_withdraw_pays_maker() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_dst_escrow(env.clone(), relayer.clone(), immutables.clone(), SRC_CANCELLATION, None).unwrap();
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+
+        // The swap amount is the maker's, the taker can't send it elsewhere
+        assert_eq!(
+            EscrowFactory::withdraw(env.clone(), immutables.order_id(), secret.clone(), Some(Address::generate(&env))),
+            Err(EscrowError::InvalidCaller)
+        );
+
+        let payout = EscrowFactory::withdraw(env.clone(), immutables.order_id(), secret, None).unwrap();
+        assert_eq!(payout.recipient, immutables.maker.stellar);
+        assert_eq!(payout.safety_deposit_recipient, immutables.taker.stellar);
+        assert_eq!(token_client.balance(&immutables.maker.stellar), 1000);
+        assert_eq!(token_client.balance(&immutables.taker.stellar), 100);
+    });
+}
+
+// ===== DRY RUN TESTS =====
+
+#[test]
+fn test_simulate_withdraw_reports_payout_without_side_effects() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+        let order_id = immutables.order_id();
+
+        // A wrong secret is reported through the error code
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        let wrong = BytesN::from_array(&env, &[0x99; 32]);
+        let result = EscrowFactory::simulate_withdraw(env.clone(), order_id.clone(), wrong, None);
+        assert!(!result.success);
+        assert_eq!(result.error_code, EscrowError::InvalidSecret as u32);
+        assert_eq!(result.recipient, None);
+
+        let result = EscrowFactory::simulate_withdraw(env.clone(), order_id.clone(), secret, None);
+        assert!(result.success);
+        assert_eq!(result.stage, EscrowStage::Withdrawn);
+        assert_eq!(result.amount, 1000);
+        assert_eq!(result.safety_deposit_amount, 100);
+
+        // Nothing moved, nothing changed
+        assert_eq!(token_client.balance(&contract_id), 10000);
+        assert_eq!(EscrowFactory::get_escrow_stage(env.clone(), order_id), EscrowStage::Created);
+    });
+}
+
+#[test]
+fn test_simulate_cancel_detects_insufficient_balance() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &500);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+        immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), immutables.clone(), None).unwrap();
+
+        let early = EscrowFactory::simulate_cancel(env.clone(), immutables.order_id());
+        assert_eq!(early.error_code, EscrowError::CancellationNotReachable as u32);
+
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1300;
+        });
+        let result = EscrowFactory::simulate_cancel(env.clone(), immutables.order_id());
+        assert!(!result.success);
+        assert_eq!(result.error_code, EscrowError::InsufficientBalance as u32);
+    });
+}
+
+// ===== MULTISIG ADMIN TESTS =====
+
+#[test]
+fn test_admin_threshold_of_signers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin.clone(), 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let token = Address::generate(&env);
+
+    // The constructor admin is a 1-of-1 signer set
+    assert_eq!(client.get_admin_signers(), Vec::from_array(&env, [admin.clone()]));
+    assert_eq!(client.get_admin_threshold(), 1);
+
+    let (a, b, c) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+    let signers = Vec::from_array(&env, [a.clone(), b.clone(), c.clone()]);
+    assert_eq!(
+        client.try_set_admin_signers(&Vec::new(&env), &signers, &4),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    client.set_admin_signers(&Vec::new(&env), &signers, &2);
+    assert_eq!(client.get_admin_signers(), signers);
+
+    // Below threshold, unknown or repeated approvers are rejected
+    assert_eq!(
+        client.try_add_token(&Vec::from_array(&env, [a.clone()]), &token),
+        Err(Ok(EscrowError::ThresholdNotMet))
+    );
+    assert_eq!(
+        client.try_add_token(&Vec::from_array(&env, [a.clone(), admin.clone()]), &token),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+    assert_eq!(
+        client.try_add_token(&Vec::from_array(&env, [a.clone(), a.clone()]), &token),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+    assert_eq!(client.try_add_token(&Vec::new(&env), &token), Err(Ok(EscrowError::ThresholdNotMet)));
+
+    // Two of three signers authorizing in the same invocation is enough
+    client.add_token(&Vec::from_array(&env, [a.clone(), c.clone()]), &token);
+    let authorizers: std::vec::Vec<Address> = env.auths().into_iter().map(|(address, _)| address).collect();
+    assert_eq!(authorizers, std::vec![a, c]);
+    assert!(client.is_token_allowed(&token));
+
+    // Signer management is gated by the same threshold
+    assert_eq!(
+        client.try_set_admin_signers(&Vec::from_array(&env, [b.clone()]), &Vec::from_array(&env, [b]), &1),
+        Err(Ok(EscrowError::ThresholdNotMet))
+    );
+}
+
+// ===== SOLVENCY TESTS =====
+
+#[test]
+fn test_locked_totals_and_solvency() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+
+    env.as_contract(&contract_id, || {
+        let (secret, hashlock) = create_test_secret(&env);
+        let mut first = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+        first.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+        let mut second = first.clone();
+        second.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), first.clone(), None).unwrap();
+        EscrowFactory::create_src_escrow(env.clone(), Address::generate(&env), second.clone(), None).unwrap();
+
+        assert_eq!(EscrowFactory::get_order_locked(env.clone(), first.order_id()), 1100);
+        assert_eq!(EscrowFactory::get_total_locked(env.clone(), token_address.clone()), 2200);
+
+        // Only one of the two orders is actually backed
+        let report = EscrowFactory::check_solvency(env.clone(), token_address.clone());
+        assert_eq!(report, SolvencyReport { total_locked: 2200, balance: 1100, solvent: false });
+
+        // Settling an order releases it from the tracked total
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = 1150;
+        });
+        EscrowFactory::withdraw(env.clone(), first.order_id(), secret, None).unwrap();
+        assert_eq!(EscrowFactory::get_order_locked(env.clone(), first.order_id()), 0);
+        assert_eq!(EscrowFactory::get_order_locked(env.clone(), second.order_id()), 1100);
+        let report = EscrowFactory::check_solvency(env.clone(), token_address.clone());
+        assert_eq!(report, SolvencyReport { total_locked: 1100, balance: 0, solvent: false });
+    });
+
+    token_admin.mint(&contract_id, &1100);
+    let report = env.as_contract(&contract_id, || EscrowFactory::check_solvency(env.clone(), token_address.clone()));
+    assert!(report.solvent);
+}
+
+// ===== DEPOSIT SPLIT TESTS =====
+
+#[test]
+fn test_expire_bounty_split_with_treasury() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+    let treasury = Address::generate(&env);
+    let caller = Address::generate(&env);
+
+    assert_eq!(
+        client.try_announce_change(&Vec::new(&env), &ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 10_001 })),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    // 80/20 between caller and treasury
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 2000 }));
+    assert_eq!(client.get_deposit_split(), Some(DepositSplit { treasury: treasury.clone(), treasury_bps: 2000 }));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.expiration = 1250;
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1250;
+    });
+    let payout = client.expire(&immutables.order_id(), &caller);
+    assert_eq!(payout.safety_deposit_amount, 80);
+    assert_eq!(token_client.balance(&caller), 80);
+    assert_eq!(token_client.balance(&treasury), 20);
+    assert_eq!(token_client.balance(&payout.recipient), 1000);
+}
+
+#[test]
+fn test_public_withdraw_bounty_split_with_treasury() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &1100);
+    let treasury = Address::generate(&env);
+    let caller = Address::generate(&env);
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 2000 }));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    // Taker-only window
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1199;
+    });
+    assert_eq!(
+        client.try_public_withdraw(&immutables.order_id(), &caller, &secret),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1200;
+    });
+    let payout = client.public_withdraw(&immutables.order_id(), &caller, &secret);
+    let stellar_taker = env.as_contract(&contract_id, || immutables::get_stellar_addr(&env, &immutables.taker.evm).unwrap());
+    assert_eq!(payout.recipient, stellar_taker);
+    assert_eq!(payout.safety_deposit_recipient, caller);
+    assert_eq!(payout.safety_deposit_amount, 80);
+    assert_eq!(token_client.balance(&stellar_taker), 1000);
+    assert_eq!(token_client.balance(&caller), 80);
+    assert_eq!(token_client.balance(&treasury), 20);
+    assert_eq!(client.get_escrow_status(&immutables.order_id()).stage, EscrowStage::Withdrawn);
+}
+
+// ===== RELAYER ALLOW-LIST TESTS =====
+
+#[test]
+fn test_relayer_only_creation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let relayer = Address::generate(&env);
+    let attacker = Address::generate(&env);
+
+    client.set_relayer_only(&Vec::new(&env), &true);
+    client.add_relayer(&Vec::new(&env), &relayer);
+    assert!(client.is_relayer(&relayer));
+
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&relayer, &1100);
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+
+    // An unregistered caller can't squat the order_hash
+    assert_eq!(
+        client.try_create_dst_escrow(&attacker, &immutables, &SRC_CANCELLATION, &None),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+    client.create_dst_escrow(&relayer, &immutables, &SRC_CANCELLATION, &None);
+    let authorizers: std::vec::Vec<Address> = env.auths().into_iter().map(|(address, _)| address).collect();
+    assert_eq!(authorizers, std::vec![relayer.clone()]);
+
+    client.remove_relayer(&Vec::new(&env), &relayer);
+    assert!(!client.is_relayer(&relayer));
+}
+
+// ===== ESCROW REPLACEMENT TESTS =====
+
+#[test]
+fn test_replace_unfunded_escrow() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    // fund_escrow mints through the SAC, so the factory is the token admin here
+    let token_address = env.register_stellar_asset_contract_v2(contract_id.clone()).address();
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let maker = Vec::from_array(&env, [immutables.maker.stellar.clone()]);
+
+    let mut replacement = immutables.clone();
+    replacement.amount = 2000;
+    replacement.safety_deposit = 200;
+
+    // Neither the maker nor an admin signer
+    assert_eq!(
+        client.try_replace_unfunded_escrow(&Vec::from_array(&env, [Address::generate(&env)]), &immutables.order_id(), &replacement),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+    // The order_hash can't be moved
+    let mut moved = replacement.clone();
+    moved.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    assert_eq!(
+        client.try_replace_unfunded_escrow(&maker, &immutables.order_id(), &moved),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+
+    client.replace_unfunded_escrow(&maker, &immutables.order_id(), &replacement);
+    let (escrow_type, stored) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!(escrow_type, EscrowType::Source);
+    assert_eq!(stored.amount, 2000);
+    assert_eq!(stored.safety_deposit, 200);
+    assert_eq!(client.get_total_locked(&token_address), 2200);
+    assert_eq!(client.get_escrow_history(&immutables.order_id()).last().unwrap().action, EscrowAction::Replace);
+
+    // Once funded the escrow is no longer replaceable, not even by the admin
+    client.fund_escrow(&immutables.order_id(), &Address::generate(&env), &2200);
+    assert_eq!(client.get_order_funded(&immutables.order_id()), 2200);
+    assert_eq!(
+        client.try_replace_unfunded_escrow(&Vec::new(&env), &immutables.order_id(), &immutables),
+        Err(Ok(EscrowError::EscrowAlreadyFunded))
+    );
+}
+
+#[test]
+fn test_reassign_taker_before_funding() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    let token_address = env.register_stellar_asset_contract_v2(contract_id.clone()).address();
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    client.approve_operator(&immutables.order_id(), &Address::generate(&env));
+
+    // The maker can't be its own resolver
+    assert_eq!(
+        client.try_reassign_taker(&immutables.order_id(), &immutables.maker),
+        Err(Ok(EscrowError::SelfTransfer))
+    );
+
+    let backup = create_test_dual_address(&env);
+    client.reassign_taker(&immutables.order_id(), &backup);
+    assert_eq!(env.auths()[0].0, immutables.maker.stellar);
+    let (_, stored) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!((stored.taker.evm, stored.taker.stellar), (backup.evm.clone(), backup.stellar.clone()));
+    assert_eq!(client.get_operator(&immutables.order_id()), None);
+    assert_eq!(client.get_escrow_history(&immutables.order_id()).last().unwrap().action, EscrowAction::Reassign);
+
+    // The backup resolver withdraws in place of the original taker
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1100;
+    });
+    client.fund_escrow(&immutables.order_id(), &Address::generate(&env), &1100);
+    assert_eq!(
+        client.try_reassign_taker(&immutables.order_id(), &immutables.taker),
+        Err(Ok(EscrowError::EscrowAlreadyFunded))
+    );
+    let payout = client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(payout.recipient, backup.stellar);
+    assert_eq!(
+        client.try_reassign_taker(&immutables.order_id(), &immutables.taker),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+
+    // A destination escrow holds the taker's own tokens, the maker can't hand it over
+    let mut dst = immutables.clone();
+    dst.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1000;
+    });
+    let relayer = Address::generate(&env);
+    TokenAdmin::new(&env, &dst.token.stellar).mint(&relayer, &1100);
+    client.create_dst_escrow(&relayer, &dst, &SRC_CANCELLATION, &None);
+    assert_eq!(client.try_reassign_taker(&dst.order_id(), &backup), factory_error(FactoryError::NotSourceEscrow));
+    let (_, stored) = client.get_escrow_state(&dst.order_id());
+    assert_eq!(stored.taker.stellar, immutables.taker.stellar);
+}
+
+// ===== FILL ORDER TESTS =====
+
+#[test]
+fn test_fill_order_creates_and_funds_dst_escrow() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{Symbol, TryIntoVal, Val};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let resolver = Address::generate(&env);
+    token_admin.mint(&resolver, &1500);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock.clone());
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+    client.fill_order(&Address::generate(&env), &immutables, &SRC_CANCELLATION, &resolver);
+
+    let mut filled = None;
+    for (emitter, topics, data) in env.events().all().iter() {
+        let first: Result<Symbol, _> = topics.get(0).unwrap().try_into_val(&env);
+        if emitter == contract_id && first == Ok(symbol_short!("OrderFill")) {
+            let payload: OrderFilled = Val::try_into_val(&data, &env).unwrap();
+            filled = Some(payload);
+        }
+    }
+    let filled = filled.unwrap();
+    assert_eq!(filled.hashlock, hashlock);
+    assert_eq!(filled.maker, immutables.maker.evm);
+    assert_eq!(filled.amount, 1000);
+    assert_eq!(filled.deployed_at, 1000);
+
+    // Amount plus safety deposit were pulled from the resolver
+    assert_eq!(token_client.balance(&resolver), 400);
+    assert_eq!(token_client.balance(&contract_id), 1100);
+    assert_eq!(client.get_order_funded(&immutables.order_id()), 1100);
+    let (escrow_type, _) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!(escrow_type, EscrowType::Destination);
+
+    // The order_hash is taken now
+    assert_eq!(
+        client.try_fill_order(&Address::generate(&env), &immutables, &SRC_CANCELLATION, &resolver),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    assert_eq!(token_client.balance(&resolver), 400);
+}
+
+#[test]
+fn test_fill_order_rejects_dst_cancellation_after_src() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let resolver = Address::generate(&env);
+    token_admin.mint(&resolver, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+
+    // Dst cancellation opens at 1350, the src escrow would already be cancellable at 1349
+    assert_eq!(
+        client.try_fill_order(&Address::generate(&env), &immutables, &1349, &resolver),
+        Err(Ok(EscrowError::InvalidCreationTime))
+    );
+    assert_eq!(token_client.balance(&resolver), 1100);
+    assert!(client.try_get_escrow_state(&immutables.order_id()).is_err());
+
+    client.fill_order(&Address::generate(&env), &immutables, &1350, &resolver);
+    assert_eq!(token_client.balance(&resolver), 0);
+}
+
+#[test]
+fn test_top_up_funds_created_escrow_up_to_its_requirement() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let resolver = Address::generate(&env);
+    token_admin.mint(&resolver, &2000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let order_id = immutables.order_id();
+
+    assert_eq!(client.try_top_up(&order_id, &resolver, &0), Err(Ok(EscrowError::InvalidImmutables)));
+    assert_eq!(client.top_up(&order_id, &resolver, &600), 600);
+    assert_eq!(env.auths()[0].0, resolver);
+    // Amount plus safety deposit is all an escrow can take
+    assert_eq!(client.try_top_up(&order_id, &resolver, &600), Err(Ok(EscrowError::AmountAboveMaximum)));
+    assert_eq!(client.top_up(&order_id, &resolver, &500), 1100);
+    assert_eq!(client.get_order_funded(&order_id), 1100);
+    assert_eq!(token_client.balance(&resolver), 900);
+    assert_eq!(token_client.balance(&contract_id), 1100);
+    let history = client.get_escrow_history(&order_id);
+    assert_eq!(history.last().unwrap().action, EscrowAction::Fund);
+
+    // Settled escrows take no more funding
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    client.cancel(&order_id);
+    assert_eq!(client.try_top_up(&order_id, &resolver, &1), Err(Ok(EscrowError::InvalidStageTransition)));
+}
+
+// ===== ENCODING VIEW TESTS =====
+
+#[test]
+fn test_encoding_views_match_hash() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+
+    let encoded = client.encode_immutables(&immutables);
+    assert_eq!(encoded.len(), 0x100);
+    let digest: BytesN<32> = env.crypto().keccak256(&encoded).into();
+    assert_eq!(digest, immutables::hash(&env, &immutables).unwrap());
+
+    // The timelocks are the last word, deployed_at in its top four bytes
+    let packed = client.encode_timelocks(&immutables.timelocks);
+    assert_eq!(encoded.slice(0xe0..0x100), soroban_sdk::Bytes::from_array(&env, &packed.to_array()));
+    assert_eq!(packed.to_array()[..4], 1000u32.to_be_bytes());
+
+    let mut invalid = immutables.clone();
+    invalid.amount = -1;
+    assert_eq!(client.try_encode_immutables(&invalid), Err(Ok(EscrowError::InvalidImmutables)));
+}
+
+// ===== TIMING ERROR TESTS =====
+
+#[test]
+fn test_timing_errors_name_the_window() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1050;
+    });
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &secret, &None),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+    assert_eq!(client.try_cancel(&immutables.order_id()), Err(Ok(EscrowError::CancellationNotReachable)));
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1250;
+    });
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &secret, &None),
+        Err(Ok(EscrowError::WithdrawalWindowClosed))
+    );
+}
+
+// ===== RESCUE STAGE TESTS =====
+
+#[test]
+fn test_rescue_only_from_unsettled_escrow() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 100u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &2200);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut settled = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    settled.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut stuck = settled.clone();
+    stuck.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    client.create_src_escrow(&Address::generate(&env), &settled, &None);
+    client.create_src_escrow(&Address::generate(&env), &stuck, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    client.withdraw(&settled.order_id(), &secret, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1200;
+    });
+    // The withdrawn escrow's funds are gone, what's left belongs to the other order
+    assert_eq!(
+        client.try_rescue_funds(&settled.order_id(), &1100),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+
+    client.mark_stuck(&Vec::new(&env), &stuck.order_id());
+    assert_eq!(client.get_escrow_stage(&stuck.order_id()), EscrowStage::Stuck);
+    assert_eq!(client.get_total_locked(&token_address), 1100);
+    assert_eq!(client.try_cancel(&stuck.order_id()), Err(Ok(EscrowError::InvalidStageTransition)));
+
+    // No more than the escrow holds, and only once. The maker whose cancel it blocked gets it back
+    assert_eq!(client.try_rescue_funds(&stuck.order_id(), &1101), Err(Ok(EscrowError::InsufficientBalance)));
+    client.rescue_funds(&stuck.order_id(), &1100);
+    assert_eq!(env.auths()[0].0, stuck.maker.stellar);
+    assert_eq!(TokenClient::new(&env, &token_address).balance(&stuck.maker.stellar), 1100);
+    assert_eq!(client.get_escrow_stage(&stuck.order_id()), EscrowStage::Rescued);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+    assert_eq!(
+        client.try_rescue_funds(&stuck.order_id(), &1),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+}
+
+#[test]
+fn test_partial_rescue_keeps_the_rest_rescuable() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 100u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1200;
+    });
+    client.rescue_funds(&immutables.order_id(), &400);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Stuck);
+    assert_eq!(client.get_rescued_amount(&immutables.order_id()), 400);
+    assert_eq!(client.get_order_locked(&immutables.order_id()), 700);
+    assert_eq!(client.get_total_locked(&token_address), 700);
+    assert!(client.check_solvency(&token_address).solvent);
+
+    // Only rescues are left, for no more than what the first one left behind
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1450;
+    });
+    assert_eq!(client.try_cancel(&immutables.order_id()), Err(Ok(EscrowError::InvalidStageTransition)));
+    assert_eq!(client.try_rescue_funds(&immutables.order_id(), &701), Err(Ok(EscrowError::InsufficientBalance)));
+    client.rescue_funds(&immutables.order_id(), &700);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Rescued);
+    assert_eq!(client.get_rescued_amount(&immutables.order_id()), 0);
+    assert_eq!(token_client.balance(&immutables.taker.stellar), 1100);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+}
+
+// ===== PUBLIC CANCEL TESTS =====
+
+#[test]
+fn test_public_cancel_rewards_caller_on_source_escrow() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&contract_id, &1100);
+    token_admin.mint(&relayer, &1100);
+    let caller = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut src = create_test_immutables_with_secret(&env, secret, hashlock);
+    src.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut dst = src.clone();
+    dst.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    client.create_src_escrow(&Address::generate(&env), &src, &None);
+    client.create_dst_escrow(&relayer, &dst, &SRC_CANCELLATION, &None);
+
+    // Private cancellation window only
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1399;
+    });
+    assert_eq!(
+        client.try_public_cancel(&src.order_id(), &caller),
+        Err(Ok(EscrowError::CancellationNotReachable))
+    );
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+    assert_eq!(
+        client.try_public_cancel(&dst.order_id(), &caller),
+        Err(Ok(EscrowError::CancellationNotReachable))
+    );
+    let payout = client.public_cancel(&src.order_id(), &caller);
+    let stellar_maker = env.as_contract(&contract_id, || immutables::get_stellar_addr(&env, &src.maker.evm).unwrap());
+    assert_eq!(payout.recipient, stellar_maker);
+    assert_eq!(payout.safety_deposit_recipient, caller);
+    assert_eq!(token_client.balance(&stellar_maker), 1000);
+    assert_eq!(token_client.balance(&caller), 100);
+    assert_eq!(client.get_escrow_stage(&src.order_id()), EscrowStage::Cancelled);
+    assert_eq!(client.get_settlement_receipt(&src.order_id()).unwrap().payout, payout);
+    assert_eq!(
+        client.try_public_cancel(&src.order_id(), &caller),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+}
+
+#[test]
+fn test_watchdog_has_priority_and_keeps_full_bounty() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &1100);
+    let watchdog = Address::generate(&env);
+    let other = Address::generate(&env);
+    token_admin.mint(&watchdog, &100);
+    let treasury = Address::generate(&env);
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 2000 }));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let order_id = immutables.order_id();
+
+    // Registrations are closed until the admin sets the terms
+    assert_eq!(
+        client.try_register_watchdog(&order_id, &watchdog, &50),
+        Err(Ok(EscrowError::WatchdogsDisabled))
+    );
+    client.set_watchdog_config(&Vec::new(&env), &WatchdogConfig { min_bond: 50, priority_window: 60 });
+    assert_eq!(
+        client.try_register_watchdog(&order_id, &watchdog, &10),
+        Err(Ok(EscrowError::AmountBelowMinimum))
+    );
+    client.register_watchdog(&order_id, &watchdog, &50);
+    assert_eq!(
+        client.try_register_watchdog(&order_id, &other, &50),
+        Err(Ok(EscrowError::WatchdogRegistered))
+    );
+    assert_eq!(client.get_watchdog(&order_id), Some(WatchdogBond { watchdog: watchdog.clone(), bond: 50 }));
+    assert_eq!(client.get_total_locked(&token_address), 1150);
+
+    // Only the watchdog during its priority window
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+    assert_eq!(
+        client.try_public_cancel(&order_id, &other),
+        Err(Ok(EscrowError::WatchdogPriority))
+    );
+
+    // Bond back plus the whole safety deposit, the treasury's cut included
+    let payout = client.public_cancel(&order_id, &watchdog);
+    assert_eq!(payout.safety_deposit_amount, 100);
+    assert_eq!(token_client.balance(&watchdog), 200);
+    assert_eq!(token_client.balance(&treasury), 0);
+    assert_eq!(client.get_watchdog(&order_id), None);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+}
+
+#[test]
+fn test_watchdog_forfeits_bond_after_priority_window() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &2200);
+    let watchdog = Address::generate(&env);
+    let other = Address::generate(&env);
+    token_admin.mint(&watchdog, &100);
+    client.set_watchdog_config(&Vec::new(&env), &WatchdogConfig { min_bond: 50, priority_window: 60 });
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut missed = create_test_immutables_with_secret(&env, secret, hashlock);
+    missed.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut settled = missed.clone();
+    settled.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    client.create_src_escrow(&Address::generate(&env), &missed, &None);
+    client.create_src_escrow(&Address::generate(&env), &settled, &None);
+    client.register_watchdog(&missed.order_id(), &watchdog, &50);
+    client.register_watchdog(&settled.order_id(), &watchdog, &50);
+
+    // The bond stays locked while the escrow is open
+    assert_eq!(
+        client.try_release_watchdog_bond(&settled.order_id()),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+
+    // The maker cancelling privately releases the bond
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    client.cancel(&settled.order_id());
+    assert_eq!(client.release_watchdog_bond(&settled.order_id()), 50);
+    assert_eq!(client.release_watchdog_bond(&settled.order_id()), 0);
+    assert_eq!(token_client.balance(&watchdog), 50);
+
+    // Past the priority window anyone executes and collects the forfeited bond
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1460;
+    });
+    let payout = client.public_cancel(&missed.order_id(), &other);
+    assert_eq!(payout.safety_deposit_amount, 100);
+    assert_eq!(token_client.balance(&other), 150);
+    assert_eq!(token_client.balance(&watchdog), 50);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+}
+
+#[test]
+fn test_cancel_reasons_are_recorded_and_emitted() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{IntoVal, Symbol, TryIntoVal, Val};
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &3300);
+    let caller = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut timed_out = create_test_immutables_with_secret(&env, secret, hashlock);
+    timed_out.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut emergency = timed_out.clone();
+    emergency.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    let mut defaulted = timed_out.clone();
+    defaulted.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    for immutables in [&timed_out, &emergency, &defaulted] {
+        client.create_src_escrow(&Address::generate(&env), immutables, &None);
+    }
+    assert_eq!(client.get_cancel_reason(&timed_out.order_id()), None);
+
+    // The single Cancelled event of the last call, read before any other invocation
+    let cancelled_event = || {
+        let mut found = std::vec::Vec::new();
+        for (emitter, topics, data) in env.events().all().iter() {
+            let first: Result<Symbol, _> = topics.get(0).unwrap().try_into_val(&env);
+            if emitter == contract_id && first == Ok(CANCELLED_TOPIC) {
+                let order_hash: BytesN<32> = topics.get(1).unwrap().into_val(&env);
+                let payload: EscrowCancelled = Val::into_val(&data, &env);
+                found.push((order_hash, payload));
+            }
+        }
+        assert_eq!(found.len(), 1);
+        found.pop().unwrap()
+    };
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    client.cancel(&timed_out.order_id());
+    let (order_hash, payload) = cancelled_event();
+    assert_eq!(order_hash, timed_out.order_hash);
+    assert_eq!(payload.reason, CancelReason::Timeout);
+    assert_eq!(payload.amount, 1000);
+    assert_eq!(payload.safety_deposit_amount, 100);
+    assert_eq!(client.get_cancel_reason(&timed_out.order_id()), Some(CancelReason::Timeout));
+
+    // Reasons of the public and cascade paths can't be claimed by the maker
+    for reason in [CancelReason::ResolverDefault, CancelReason::ParentCancelled] {
+        assert_eq!(
+            client.try_cancel_with_reason(&emergency.order_id(), &reason),
+            Err(Ok(EscrowError::InvalidCaller))
+        );
+    }
+    client.cancel_with_reason(&emergency.order_id(), &CancelReason::Emergency);
+    assert_eq!(cancelled_event().1.reason, CancelReason::Emergency);
+    assert_eq!(env.auths()[0].0, emergency.maker.stellar);
+    assert_eq!(client.get_cancel_reason(&emergency.order_id()), Some(CancelReason::Emergency));
+
+    // Cancelling in the public phase means the resolver defaulted
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+    client.public_cancel(&defaulted.order_id(), &caller);
+    let (order_hash, payload) = cancelled_event();
+    assert_eq!(order_hash, defaulted.order_hash);
+    assert_eq!(payload.safety_deposit_recipient, caller);
+    assert_eq!(client.get_cancel_reason(&defaulted.order_id()), Some(CancelReason::ResolverDefault));
+}
+
+#[test]
+fn test_stats_follow_creations_and_transitions() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&contract_id, &2200);
+    token_admin.mint(&relayer, &1100);
+    assert_eq!(client.get_stats(), EscrowStats::default());
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut withdrawn = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    withdrawn.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut stuck = withdrawn.clone();
+    stuck.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    let mut cancelled = withdrawn.clone();
+    cancelled.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    client.create_src_escrow(&Address::generate(&env), &withdrawn, &None);
+    client.create_src_escrow(&Address::generate(&env), &stuck, &None);
+    client.create_dst_escrow(&relayer, &cancelled, &SRC_CANCELLATION, &None);
+    assert_eq!(
+        client.get_stats(),
+        EscrowStats { source: 2, destination: 1, created: 3, ..Default::default() }
+    );
+    assert_eq!(client.get_token_stats(&token_address), TokenStats { volume: 3000, safety_deposits_held: 300 });
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    client.withdraw(&withdrawn.order_id(), &secret, &None);
+    client.mark_stuck(&Vec::new(&env), &stuck.order_id());
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1350;
+    });
+    client.cancel(&cancelled.order_id());
+
+    // Volume keeps counting settled escrows, only the stuck escrow's deposit is still held
+    assert_eq!(
+        client.get_stats(),
+        EscrowStats { source: 2, destination: 1, withdrawn: 1, cancelled: 1, stuck: 1, ..Default::default() }
+    );
+    assert_eq!(client.get_token_stats(&token_address), TokenStats { volume: 3000, safety_deposits_held: 100 });
+}
+
+#[test]
+fn test_timelock_horizon_is_capped() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+    assert_eq!(client.get_max_timelock_horizon(), DEFAULT_MAX_TIMELOCK_HORIZON);
+
+    // Public cancellation 31 days out
+    let day = 24 * 60 * 60;
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    immutables.timelocks = Timelocks::new(&env, 1000, 100, 200, 300, 31 * day, 150, 250, 350);
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &immutables, &None),
+        Err(Ok(EscrowError::TimelockHorizonTooLong))
+    );
+
+    assert_eq!(
+        client.try_set_max_timelock_horizon(&Vec::new(&env), &0),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    client.set_max_timelock_horizon(&Vec::new(&env), &(60 * day as u64));
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+}
+
+#[test]
+fn test_create_with_default_timelocks_deploys_template_now() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 5000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    let relayer = Address::generate(&env);
+    assert_eq!(client.get_default_timelocks(), None);
+    assert_eq!(
+        client.try_create_src_escrow_with_defaults(&relayer, &immutables, &None),
+        Err(Ok(EscrowError::NoDefaultTimelocks))
+    );
+
+    // Cancellation before withdrawal is rejected like on any escrow
+    let mut template = TimelockTemplate {
+        src_withdrawal: 100,
+        src_public_withdrawal: 200,
+        src_cancellation: 50,
+        src_public_cancellation: 400,
+        dst_withdrawal: 150,
+        dst_public_withdrawal: 250,
+        dst_cancellation: 350,
+    };
+    assert_eq!(
+        client.try_set_default_timelocks(&Vec::new(&env), &template),
+        Err(Ok(EscrowError::TimeLockError))
+    );
+    template.src_public_cancellation = 31 * 24 * 60 * 60;
+    template.src_cancellation = 300;
+    assert_eq!(
+        client.try_set_default_timelocks(&Vec::new(&env), &template),
+        Err(Ok(EscrowError::TimelockHorizonTooLong))
+    );
+    template.src_public_cancellation = 400;
+    client.set_default_timelocks(&Vec::new(&env), &template);
+    assert_eq!(client.get_default_timelocks(), Some(template));
+
+    // Whatever timelocks the relayer passed, the template is deployed at the ledger time
+    client.create_src_escrow_with_defaults(&relayer, &immutables, &None);
+    let (_, stored) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!(stored.timelocks, Timelocks::new(&env, 5000, 100, 200, 300, 400, 150, 250, 350));
+}
+
+#[test]
+fn test_sweep_stale_escrows_after_announcement() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let order_ids = Vec::from_array(&env, [immutables.order_id()]);
+    let recovery = Address::generate(&env);
+
+    // Rescue opens at 87400, the escrow turns stale one rescue delay later
+    env.ledger().with_mut(|ledger| ledger.timestamp = 173_799);
+    assert_eq!(
+        client.try_announce_sweep(&Vec::new(&env), &order_ids, &recovery),
+        Err(Ok(EscrowError::RescueNotStarted))
+    );
+    env.ledger().with_mut(|ledger| ledger.timestamp = 173_800);
+    assert_eq!(
+        client.try_sweep_stale(&Vec::new(&env), &order_ids, &recovery),
+        Err(Ok(EscrowError::SweepNotAnnounced))
+    );
+    let executable_at = client.announce_sweep(&Vec::new(&env), &order_ids, &recovery);
+    assert_eq!(executable_at, 173_800 + SWEEP_ANNOUNCEMENT_DELAY);
+
+    // Only the announced sweep, and only after the announcement delay
+    assert_eq!(
+        client.try_sweep_stale(&Vec::new(&env), &order_ids, &recovery),
+        Err(Ok(EscrowError::RescueNotStarted))
+    );
+    env.ledger().with_mut(|ledger| ledger.timestamp = executable_at);
+    assert_eq!(
+        client.try_sweep_stale(&Vec::new(&env), &order_ids, &Address::generate(&env)),
+        Err(Ok(EscrowError::SweepNotAnnounced))
+    );
+    assert_eq!(client.sweep_stale(&Vec::new(&env), &order_ids, &recovery), 1);
+    assert_eq!(TokenClient::new(&env, &token_address).balance(&recovery), 1100);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Rescued);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+    assert_eq!(client.get_sweep_announcement(), None);
+}
+
+#[test]
+fn test_escrow_records_dispatch_on_schema_version() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let order_id = immutables.order_id();
+    assert_eq!(client.get_escrow_schema_version(&order_id), ESCROW_SCHEMA_VERSION);
+    let (_, stored) = client.get_escrow_state(&order_id);
+
+    // A record from before versioning is still read, as version 0
+    let key = EscrowDataKey::EscrowState(immutables.order_hash.clone());
+    env.as_contract(&contract_id, || {
+        env.storage().persistent().set(&key, &(EscrowType::Source, stored.clone()));
+    });
+    assert_eq!(client.get_escrow_schema_version(&order_id), 0);
+    let (escrow_type, legacy) = client.get_escrow_state(&order_id);
+    assert_eq!(escrow_type, EscrowType::Source);
+    assert_eq!(legacy.taker.stellar, stored.taker.stellar);
+
+    // A layout from a later factory version isn't guessed at
+    env.as_contract(&contract_id, || {
+        let record = EscrowRecord { schema_version: ESCROW_SCHEMA_VERSION + 1, escrow_type: EscrowType::Source, immutables: stored };
+        env.storage().persistent().set(&key, &record);
+    });
+    assert_eq!(client.try_get_escrow_schema_version(&order_id), Err(Ok(EscrowError::UnknownSchemaVersion)));
+    assert!(client.try_get_escrow_state(&order_id).is_err());
+}
+
+#[test]
+fn test_reserved_order_is_left_to_its_relayer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 500);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    let order_id = immutables.order_id();
+    let (relayer, rival) = (Address::generate(&env), Address::generate(&env));
+    assert!(client.is_order_available(&order_id));
+
+    assert_eq!(
+        client.try_reserve_order(&order_id, &relayer, &(MAX_ORDER_RESERVATION + 1)),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    assert_eq!(client.reserve_order(&order_id, &relayer, &60), 560);
+    assert!(!client.is_order_available(&order_id));
+    assert_eq!(client.try_reserve_order(&order_id, &rival, &60), Err(Ok(EscrowError::OrderReserved)));
+    assert_eq!(
+        client.try_create_src_escrow(&rival, &immutables, &None),
+        Err(Ok(EscrowError::OrderReserved))
+    );
+
+    // An expired reservation frees the order id again
+    env.ledger().with_mut(|ledger| ledger.timestamp = 560);
+    assert!(client.is_order_available(&order_id));
+    client.reserve_order(&order_id, &rival, &60);
+    client.create_src_escrow(&rival, &immutables, &None);
+    assert!(!client.is_order_available(&order_id));
+    assert_eq!(client.try_reserve_order(&order_id, &relayer, &60), factory_error(FactoryError::EscrowExists));
+}
+
+#[test]
+fn test_export_escrow_round_trips() {
+    use soroban_sdk::xdr::FromXdr;
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+    assert_eq!(
+        client.try_export_escrow(&OrderId::EvmKeccak(BytesN::from_array(&env, &[0xff; 32]))),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    client.cancel(&immutables.order_id());
+
+    let bytes = client.export_escrow(&immutables.order_id());
+    let export = EscrowExport::from_xdr(&env, &bytes).unwrap();
+    assert_eq!(export.version, ESCROW_EXPORT_VERSION);
+    assert_eq!(export.escrow_type, EscrowType::Source);
+    assert_eq!(export.immutables.maker.stellar, immutables.maker.stellar);
+    assert_eq!(export.status, client.get_escrow_status(&immutables.order_id()));
+    assert_eq!(export.locked, 0);
+    assert_eq!(export.cancel_reason, CancelReason::Timeout as u32);
+    assert_eq!(export.history, client.get_escrow_history(&immutables.order_id()));
+    assert_eq!(export.exported_at, 1300);
+
+    // Canonical: the same state exports to the same bytes
+    assert_eq!(client.export_escrow(&immutables.order_id()), bytes);
+}
+
+#[test]
+fn test_import_escrow_from_previous_factory() {
+    use ed25519_dalek::Signer;
+    use soroban_sdk::xdr::FromXdr;
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let old_id = env.register(EscrowFactory, (admin.clone(), 86400u64));
+    let new_id = env.register(EscrowFactory, (admin, 86400u64));
+    let old = EscrowFactoryClient::new(&env, &old_id);
+    let new = EscrowFactoryClient::new(&env, &new_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&old_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    old.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let export = old.export_escrow(&immutables.order_id());
+
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&[0x07; 32]);
+    let sign = |bytes: &Bytes| {
+        let message: std::vec::Vec<u8> = new.get_import_message(bytes).iter().collect();
+        BytesN::from_array(&env, &signing_key.sign(&message).to_bytes())
+    };
+    let approvers = Vec::new(&env);
+    assert_eq!(
+        new.try_import_escrow(&approvers, &export, &sign(&export)),
+        Err(Ok(EscrowError::ImportsDisabled))
+    );
+    new.set_migration_key(&approvers, &BytesN::from_array(&env, &signing_key.verifying_key().to_bytes()));
+
+    // Anything but the signed bytes is rejected, as are other format versions
+    let mut tampered = EscrowExport::from_xdr(&env, &export).unwrap();
+    tampered.locked = 0;
+    let tampered = tampered.to_xdr(&env);
+    assert!(new.try_import_escrow(&approvers, &tampered, &sign(&export)).is_err());
+    let mut future = EscrowExport::from_xdr(&env, &export).unwrap();
+    future.version += 1;
+    let future = future.to_xdr(&env);
+    assert_eq!(
+        new.try_import_escrow(&approvers, &future, &sign(&future)),
+        Err(Ok(EscrowError::InvalidExport))
+    );
+
+    assert_eq!(new.import_escrow(&approvers, &export, &sign(&export)), immutables.order_hash);
+    assert_eq!(
+        new.try_import_escrow(&approvers, &export, &sign(&export)),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    assert_eq!(new.get_escrow_stage(&immutables.order_id()), EscrowStage::Created);
+    assert_eq!(new.get_total_locked(&token_address), 1100);
+    assert_eq!(new.get_stats(), EscrowStats { source: 1, created: 1, ..Default::default() });
+    assert_eq!(new.get_escrow_history(&immutables.order_id()).len(), 2);
+
+    // Not solvent until the funds follow, then the escrow settles on the new factory
+    assert!(!new.check_solvency(&token_address).solvent);
+    token_admin.mint(&new_id, &1100);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    let payout = new.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(payout.recipient, immutables.taker.stellar);
+    assert_eq!(new.get_total_locked(&token_address), 0);
+}
+
+// ===== ORDER HASH TESTS =====
+
+#[test]
+fn test_compute_order_hash_is_deterministic() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let fields = OrderFields {
+        maker: BytesN::from_array(&env, &[0x11; 20]),
+        taker: BytesN::from_array(&env, &[0x22; 20]),
+        maker_asset: BytesN::from_array(&env, &[0x33; 20]),
+        taker_asset: BytesN::from_array(&env, &[0x44; 20]),
+        making_amount: 1000,
+        taking_amount: 2500,
+        salt: BytesN::from_array(&env, &[0x55; 32]),
+        src_chain_id: 1,
+        dst_chain_id: 0x5354454c4c4152,
+    };
+    let order_hash = client.compute_order_hash(&fields);
+    assert_eq!(order_hash, client.compute_order_hash(&fields));
+
+    // Any field change, e.g. a fresh salt, gives a new identifier
+    let mut resalted = fields.clone();
+    resalted.salt = BytesN::from_array(&env, &[0x56; 32]);
+    assert_ne!(order_hash, client.compute_order_hash(&resalted));
+
+    // The derived hash is what escrows are keyed by
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.order_hash = order_hash.clone();
+    env.mock_all_auths();
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    assert_eq!(client.get_escrow_state(&OrderId::EvmKeccak(order_hash.clone())).1.order_hash, order_hash);
+}
+
+// ===== CHAIN ID TESTS =====
+
+#[test]
+fn test_chain_ids_must_be_supported() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let relayer = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.src_chain_id = 1;
+    immutables.dst_chain_id = 0x5354454c4c4152;
+
+    client.add_supported_chain(&Vec::new(&env), &1, &ChainConfig::default());
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &immutables, &None),
+        Err(Ok(EscrowError::UnsupportedChain))
+    );
+
+    client.add_supported_chain(&Vec::new(&env), &0x5354454c4c4152, &ChainConfig::default());
+    assert!(client.is_supported_chain(&0x5354454c4c4152));
+
+    // Source and destination can't be the same chain
+    let mut same_chain = immutables.clone();
+    same_chain.dst_chain_id = 1;
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &same_chain, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+
+    client.create_src_escrow(&relayer, &immutables, &None);
+
+    client.remove_supported_chain(&Vec::new(&env), &1);
+    let mut next = immutables.clone();
+    next.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &next, &None),
+        Err(Ok(EscrowError::UnsupportedChain))
+    );
+}
+
+#[test]
+fn test_chain_config_applies_stricter_parameters() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &2200);
+    let relayer = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    let ethereum = ChainConfig { finality_lag: 60, min_safety_deposit: 100, fee_bps: 2500 };
+    let stellar = ChainConfig { finality_lag: 10, min_safety_deposit: 0, fee_bps: 0 };
+    assert_eq!(
+        client.try_add_supported_chain(&Vec::new(&env), &1, &ChainConfig { fee_bps: 10_001, ..ethereum.clone() }),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    client.add_supported_chain(&Vec::new(&env), &1, &ethereum);
+    client.add_supported_chain(&Vec::new(&env), &0x5354454c4c4152, &stellar);
+    assert_eq!(client.get_chain_config(&1), Some(ethereum));
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 1000 }));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.src_chain_id = 0x5354454c4c4152;
+    immutables.dst_chain_id = 1;
+
+    let mut low_deposit = immutables.clone();
+    low_deposit.safety_deposit = 99;
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &low_deposit, &None),
+        Err(Ok(EscrowError::SafetyDepositTooLow))
+    );
+    let mut short_lag = immutables.clone();
+    short_lag.finality_lag = 30;
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &short_lag, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+
+    // Ethereum's lag applies on top of the withdrawal stage
+    client.create_src_escrow(&relayer, &immutables, &None);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1159;
+    });
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &secret, &None),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1160;
+    });
+    client.withdraw(&immutables.order_id(), &secret, &None);
+
+    // Ethereum's fee replaces the factory-wide treasury share
+    let mut cancelled = immutables.clone();
+    cancelled.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1000;
+    });
+    client.create_src_escrow(&relayer, &cancelled, &None);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1400;
+    });
+    let payout = client.public_cancel(&cancelled.order_id(), &keeper);
+    assert_eq!(payout.safety_deposit_amount, 75);
+    assert_eq!(token_client.balance(&keeper), 75);
+    assert_eq!(token_client.balance(&treasury), 25);
+}
+
+// ===== CLAIMABLE PAYOUT TESTS =====
+
+#[test]
+fn test_failed_payout_becomes_claimable() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    // Revocable, so the issuer can freeze a trustline
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env));
+    token.issuer().set_flag(soroban_sdk::testutils::IssuerFlags::RevocableFlag);
+    let token_address = token.address();
+    let token_admin = TokenAdmin::new(&env, &token_address);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let taker = env.as_contract(&contract_id, || immutables::get_stellar_addr(&env, &immutables.taker.evm).unwrap());
+
+    // The taker's trustline is frozen, the withdraw still settles the escrow
+    token_admin.set_authorized(&taker, &false);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Withdrawn);
+    assert_eq!(token_client.balance(&taker), 0);
+    assert_eq!(client.get_claimable(&taker, &token_address), 1100);
+    assert_eq!(client.get_total_locked(&token_address), 1100);
+    assert!(client.check_solvency(&token_address).solvent);
+
+    token_admin.set_authorized(&taker, &true);
+    assert_eq!(client.claim(&taker, &token_address), 1100);
+    assert_eq!(token_client.balance(&taker), 1100);
+    assert_eq!(client.get_claimable(&taker, &token_address), 0);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+    assert_eq!(client.claim(&taker, &token_address), 0);
+}
+
+// ===== ADDRESS MAPPING EVENT TESTS =====
+
+#[test]
+fn test_address_mapping_events_and_order_view() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{TryIntoVal, Val};
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let relayer = Address::generate(&env);
+
+    let mapping_events = || {
+        let mut events = std::vec::Vec::new();
+        for (_, topics, data) in env.events().all().iter() {
+            let first: Result<Symbol, _> = topics.get(0).unwrap().try_into_val(&env);
+            if first == Ok(shared::immutables::ADDRESS_MAPPED_TOPIC) {
+                let evm: BytesN<20> = topics.get(1).unwrap().try_into_val(&env).unwrap();
+                let payload: shared::AddressMapped = Val::try_into_val(&data, &env).unwrap();
+                events.push((evm, payload));
+            }
+        }
+        events
+    };
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut first = create_test_immutables_with_secret(&env, secret, hashlock);
+    first.maker = create_test_dual_address_with_stellar(&env, Address::generate(&env), 0x0a);
+    first.taker = create_test_dual_address_with_stellar(&env, Address::generate(&env), 0x0b);
+    client.create_src_escrow(&relayer, &first, &None);
+
+    let events = mapping_events();
+    assert_eq!(events.len(), 3);
+    let (maker_evm, maker_event) = &events[0];
+    assert_eq!(maker_evm, &first.maker.evm);
+    assert_eq!(maker_event.stellar, first.maker.stellar);
+    assert_eq!(maker_event.previous, None);
+    assert_eq!(maker_event.caller, relayer);
+    assert_eq!(maker_event.order_hash, Some(first.order_hash.clone()));
+    assert_eq!(client.get_order_mappings(&first.order_id()).len(), 3);
+
+    // Same parties again: nothing changes, nothing is logged
+    let mut second = first.clone();
+    second.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    client.create_src_escrow(&relayer, &second, &None);
+    assert!(mapping_events().is_empty());
+    assert!(client.get_order_mappings(&second.order_id()).is_empty());
+
+    // A new Stellar address for the maker would redirect the first escrow's payout
+    let mut third = first.clone();
+    third.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    third.maker.stellar = Address::generate(&env);
+    assert_eq!(
+        client.try_create_src_escrow(&relayer, &third, &None),
+        Err(Ok(EscrowError::MappingConflict))
+    );
+    env.as_contract(&contract_id, || {
+        assert_eq!(immutables::get_stellar_addr(&env, &first.maker.evm), Some(first.maker.stellar.clone()));
+    });
+}
+
+#[test]
+fn test_remap_address_is_admin_only_and_logged() {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::{IntoVal, TryIntoVal, Val};
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin.clone(), 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    let replacement = Address::generate(&env);
+    let outsider = Vec::from_array(&env, [Address::generate(&env)]);
+    assert_eq!(
+        client.try_remap_address(&outsider, &immutables.maker.evm, &replacement),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+
+    client.remap_address(&Vec::new(&env), &immutables.maker.evm, &replacement);
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (shared::immutables::ADDRESS_REMAPPED_TOPIC, immutables.maker.evm.clone()).into_val(&env)
+    );
+    let event: shared::AddressMapped = Val::try_into_val(&data, &env).unwrap();
+    assert_eq!(event.stellar, replacement);
+    assert_eq!(event.previous, Some(immutables.maker.stellar.clone()));
+    assert_eq!(event.caller, admin);
+
+    env.as_contract(&contract_id, || {
+        assert_eq!(immutables::get_stellar_addr(&env, &immutables.maker.evm), Some(replacement.clone()));
+    });
+}
+
+#[test]
+fn test_list_address_mappings_pages_through_the_map() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    assert_eq!(client.get_address_mapping_count(), 0);
+    assert_eq!(client.list_address_mappings(&0, &10).len(), 0);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    // Creation maps maker, taker and token
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let last_evm = BytesN::from_array(&env, &[0xff; 20]);
+    let last_stellar = Address::generate(&env);
+    client.remap_address(&Vec::new(&env), &last_evm, &last_stellar);
+    assert_eq!(client.get_address_mapping_count(), 4);
+
+    // Pages follow EVM address order and stop at the end of the map
+    let first = client.list_address_mappings(&0, &3);
+    assert_eq!(first.len(), 3);
+    let rest = client.list_address_mappings(&3, &3);
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest.get(0).unwrap().evm, last_evm);
+    assert_eq!(rest.get(0).unwrap().stellar, last_stellar);
+    assert_eq!(client.list_address_mappings(&4, &3).len(), 0);
+    env.as_contract(&contract_id, || {
+        for mapping in first.iter() {
+            assert!(mapping.evm < last_evm);
+            assert_eq!(immutables::get_stellar_addr(&env, &mapping.evm), Some(mapping.stellar));
+        }
+    });
+}
+
+#[test]
+fn test_payouts_use_addresses_resolved_at_creation() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    let (_, stored) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!(stored.taker.stellar, immutables.taker.stellar);
+
+    // Pointing the taker's EVM address elsewhere doesn't move this escrow's payout
+    let redirected = Address::generate(&env);
+    client.remap_address(&Vec::new(&env), &immutables.taker.evm, &redirected);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    let payout = client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(payout.recipient, immutables.taker.stellar);
+    assert_eq!(token_client.balance(&immutables.taker.stellar), 1100);
+    assert_eq!(token_client.balance(&redirected), 0);
+}
+
+#[test]
+fn test_withdraw_parts_of_schedule() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    let secrets: std::vec::Vec<BytesN<32>> = [0x01u8, 0x02, 0x03].iter()
+        .map(|byte| BytesN::from_array(&env, &[*byte; 32]))
+        .collect();
+    let part = |secret: &BytesN<32>, amount: i128| shared::WithdrawalPart {
+        hashlock: env.crypto().keccak256(&Bytes::from_array(&env, &secret.to_array())).into(),
+        amount,
+    };
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+    // The parts have to add up to the amount
+    immutables.parts = Vec::from_array(&env, [part(&secrets[0], 300), part(&secrets[1], 300)]);
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &immutables, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    immutables.parts.push_back(part(&secrets[2], 400));
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    let order_id = immutables.order_id();
+    let taker = immutables.taker.stellar.clone();
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    assert_eq!(client.try_withdraw(&order_id, &secret, &None), Err(Ok(EscrowError::PartsScheduled)));
+
+    // Each secret unlocks its part and a pro-rata share of the safety deposit
+    let payout = client.withdraw_part(&order_id, &secrets[1]);
+    assert_eq!((payout.amount, payout.safety_deposit_amount), (300, 30));
+    assert_eq!(client.try_withdraw_part(&order_id, &secrets[1]), factory_error(FactoryError::AlreadySettled));
+    assert_eq!(client.try_withdraw_part(&order_id, &secret), Err(Ok(EscrowError::UnknownPart)));
+    assert_eq!(client.get_settlement_receipt(&order_id), None);
+    assert_eq!(client.get_escrow_stage(&order_id), EscrowStage::PartiallyWithdrawn);
+    assert_eq!(client.get_stats().partially_withdrawn, 1);
+    assert_eq!(client.get_withdrawn_parts(&order_id), 0b010);
+    assert_eq!(client.get_order_locked(&order_id), 770);
+    assert_eq!(client.get_total_locked(&token_address), 770);
+
+    client.withdraw_part(&order_id, &secrets[0]);
+    assert_eq!(client.get_escrow_stage(&order_id), EscrowStage::PartiallyWithdrawn);
+    let payout = client.withdraw_part(&order_id, &secrets[2]);
+    assert_eq!((payout.amount, payout.safety_deposit_amount), (400, 40));
+    assert_eq!(client.get_escrow_stage(&order_id), EscrowStage::Withdrawn);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+    assert_eq!(token_client.balance(&taker), 1100);
+
+    // The last part settled the escrow, retries of any part are told so
+    assert_eq!(client.get_settlement_receipt(&order_id).unwrap().payout, payout);
+    assert_eq!(client.try_withdraw_part(&order_id, &secrets[0]), factory_error(FactoryError::AlreadySettled));
+}
+
+#[test]
+fn test_cancel_after_partial_withdrawal_refunds_the_rest() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+
+    let first = BytesN::from_array(&env, &[0x01; 32]);
+    let second = BytesN::from_array(&env, &[0x02; 32]);
+    let part = |secret: &BytesN<32>, amount: i128| shared::WithdrawalPart {
+        hashlock: env.crypto().keccak256(&Bytes::from_array(&env, &secret.to_array())).into(),
+        amount,
+    };
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.parts = Vec::from_array(&env, [part(&first, 250), part(&second, 750)]);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    client.withdraw_part(&immutables.order_id(), &first);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1350;
+    });
+    let refund = client.cancel(&immutables.order_id());
+    assert_eq!((refund.amount, refund.safety_deposit_amount), (750, 75));
+    assert_eq!(token_client.balance(&immutables.taker.stellar), 275);
+    assert_eq!(token_client.balance(&immutables.maker.stellar), 825);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+}
+
+#[test]
+fn test_remaining_amount_tracks_fills() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let first = BytesN::from_array(&env, &[0x01; 32]);
+    let second = BytesN::from_array(&env, &[0x02; 32]);
+    let part = |secret: &BytesN<32>, amount: i128| shared::WithdrawalPart {
+        hashlock: env.crypto().keccak256(&Bytes::from_array(&env, &secret.to_array())).into(),
+        amount,
+    };
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock.clone());
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    immutables.parts = Vec::from_array(&env, [part(&first, 250), part(&second, 750)]);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    assert_eq!(client.get_remaining_amount(&immutables.order_id()), 1000);
+    assert_eq!(client.get_remaining_amount(&OrderId::EvmKeccak(BytesN::from_array(&env, &[0xff; 32]))), 0);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    client.withdraw_part(&immutables.order_id(), &first);
+    assert_eq!(client.get_remaining_amount(&immutables.order_id()), 750);
+    client.withdraw_part(&immutables.order_id(), &second);
+    assert_eq!(client.get_remaining_amount(&immutables.order_id()), 0);
+
+    // A plain escrow whose status already counts a fill can't pay out its full amount again
+    let mut plain = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    plain.order_hash = BytesN::from_array(&env, &[0x07; 32]);
+    plain.token = immutables.token.clone();
+    client.create_src_escrow(&Address::generate(&env), &plain, &None);
+    env.as_contract(&contract_id, || {
+        EscrowFactory::save_status(&env, &plain.order_hash, EscrowStage::Created, 1);
+    });
+    assert_eq!(client.get_remaining_amount(&plain.order_id()), 999);
+    assert_eq!(
+        client.try_withdraw(&plain.order_id(), &secret, &None),
+        Err(Ok(EscrowError::FillExceedsAmount))
+    );
+}
+
+// Balance entry layout of the Stellar Asset Contract, native XLM can't be minted in tests
+#[soroban_sdk::contracttype]
+enum SacDataKey {
+    Balance(Address),
+}
+
+#[soroban_sdk::contracttype]
+struct SacBalance {
+    amount: i128,
+    authorized: bool,
+    clawback: bool,
+}
+
+// Deploy the XLM SAC and credit holder with amount
+fn setup_native(env: &Env, holder: &Address, amount: i128) -> TokenClient<'static> {
+    let native = env.deployer().with_stellar_asset(soroban_sdk::Bytes::from_array(env, &[0u8; 4])).deploy();
+    env.as_contract(&native, || {
+        let balance = SacBalance { amount, authorized: true, clawback: false };
+        env.storage().persistent().set(&SacDataKey::Balance(holder.clone()), &balance);
+    });
+    TokenClient::new(env, &native)
+}
+
+#[test]
+fn test_native_deposit_is_held_and_paid_in_xlm() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    let resolver = Address::generate(&env);
+    token_admin.mint(&resolver, &1000);
+    let xlm = setup_native(&env, &resolver, 500);
+    assert_eq!(xlm.address, Asset::native_address(&env));
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let solidity_hash = immutables::hash(&env, &immutables).unwrap();
+    immutables.native_deposit = true;
+    assert_ne!(immutables::hash(&env, &immutables).unwrap(), solidity_hash);
+
+    // The amount is pulled in the token, the safety deposit in XLM
+    client.fill_order(&Address::generate(&env), &immutables, &SRC_CANCELLATION, &resolver);
+    assert_eq!(token_client.balance(&resolver), 0);
+    assert_eq!(xlm.balance(&resolver), 400);
+    assert_eq!(client.get_total_locked(&token_address), 1000);
+    assert_eq!(client.get_total_locked(&xlm.address), 100);
+    assert_eq!(client.get_order_locked(&immutables.order_id()), 1000);
+    assert_eq!(client.get_token_stats(&xlm.address).safety_deposits_held, 100);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+    let payout = client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(payout.token, token_address);
+    assert_eq!(payout.safety_deposit_token, xlm.address);
+    // A destination escrow pays the amount to the maker, the deposit stays with the taker
+    assert_eq!(token_client.balance(&immutables.maker.stellar), 1000);
+    assert_eq!(xlm.balance(&immutables.taker.stellar), 100);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+    assert_eq!(client.get_total_locked(&xlm.address), 0);
+    assert_eq!(client.get_token_stats(&xlm.address).safety_deposits_held, 0);
+}
+
+#[test]
+fn test_zero_evm_addresses_are_rejected() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+    let zero = BytesN::from_array(&env, &[0u8; 20]);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+
+    for party in 0..2 {
+        let mut zeroed = immutables.clone();
+        if party == 0 {
+            zeroed.maker.evm = zero.clone();
+        } else {
+            zeroed.taker.evm = zero.clone();
+        }
+        assert_eq!(
+            client.try_create_src_escrow(&Address::generate(&env), &zeroed, &None),
+            Err(Ok(EscrowError::ZeroAddress))
+        );
+    }
+
+    // address(0) as token is the EVM native token, only accepted once allowed
+    immutables.token.evm = zero;
+    assert_eq!(
+        client.try_create_src_escrow(&Address::generate(&env), &immutables, &None),
+        Err(Ok(EscrowError::ZeroAddress))
+    );
+    assert!(!client.get_native_token_allowed());
+    client.set_native_token_allowed(&Vec::new(&env), &true);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+}
+
+#[test]
+fn test_operator_withdraws_on_behalf_of_taker() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+    let operator = Address::generate(&env);
+    let settlement = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    let order_id = immutables.order_id();
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+
+    // Not approved yet, then approved and revoked again
+    let target = Some(settlement.clone());
+    assert_eq!(
+        client.try_operator_withdraw(&order_id, &operator, &secret, &target),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+    client.approve_operator(&order_id, &operator);
+    assert_eq!(env.auths()[0].0, immutables.taker.stellar);
+    assert_eq!(client.get_operator(&order_id), Some(operator.clone()));
+    client.revoke_operator(&order_id);
+    assert_eq!(client.get_operator(&order_id), None);
+    assert_eq!(
+        client.try_operator_withdraw(&order_id, &operator, &secret, &target),
+        Err(Ok(EscrowError::InvalidCaller))
+    );
+
+    // Nobody but the taker withdraws without an operator approval, even to the default recipients
+    env.set_auths(&[]);
+    assert!(client.try_withdraw(&order_id, &secret, &None).is_err());
+    env.mock_all_auths();
+
+    client.approve_operator(&order_id, &operator);
+    let payout = client.operator_withdraw(&order_id, &operator, &secret, &target);
+    assert_eq!(env.auths().len(), 1);
+    assert_eq!(env.auths()[0].0, operator);
+    assert_eq!(payout.recipient, settlement);
+    assert_eq!(token_client.balance(&settlement), 1000);
+    assert_eq!(token_client.balance(&immutables.taker.stellar), 100);
+    assert_eq!(client.get_escrow_stage(&order_id), EscrowStage::Withdrawn);
+}
+
+#[test]
+fn test_safety_deposit_refund_decays_over_withdraw_window() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &10000);
+    let treasury = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    let mut own_curve = immutables.clone();
+    own_curve.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    own_curve.taker = create_test_dual_address(&env);
+    own_curve.deposit_decay_bps = 10000;
+    let mut whale = immutables.clone();
+    whale.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    whale.taker = create_test_dual_address(&env);
+    whale.safety_deposit = 10i128.pow(36);
+    token_admin.mint(&contract_id, &(whale.amount + whale.safety_deposit));
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    client.create_src_escrow(&Address::generate(&env), &own_curve, &None);
+    client.create_src_escrow(&Address::generate(&env), &whale, &None);
+
+    // Halfway through the window [1100, 1200)
+    apply_change(&env, &client, ParamChange::DepositDecay(5000));
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1150;
+    });
+
+    // Nothing decays without a treasury to collect it
+    let simulated = client.simulate_withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(simulated.safety_deposit_amount, 100);
+
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 0 }));
+    let simulated = client.simulate_withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(simulated.safety_deposit_amount, 75);
+    let payout = client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(payout.safety_deposit_amount, 75);
+    assert_eq!(token_client.balance(&immutables.taker.stellar), 1075);
+    assert_eq!(token_client.balance(&treasury), 25);
+
+    // The order's own curve overrides the factory's
+    client.withdraw(&own_curve.order_id(), &secret, &None);
+    assert_eq!(token_client.balance(&own_curve.taker.stellar), 1050);
+    assert_eq!(token_client.balance(&treasury), 75);
+
+    // Deposit times decay times elapsed seconds would overflow an i128 here
+    let payout = client.withdraw(&whale.order_id(), &secret, &None);
+    assert_eq!(payout.safety_deposit_amount, 75 * 10i128.pow(34));
+    assert_eq!(token_client.balance(&treasury), 75 + 25 * 10i128.pow(34));
+    assert_eq!(client.get_total_locked(&token_address), 0);
+
+    assert_eq!(
+        client.try_announce_change(&Vec::new(&env), &ParamChange::DepositDecay(10001)),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+}
+
+#[test]
+fn test_escrow_info_records_creation_ledger() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let relayer = Address::generate(&env);
+    token_admin.mint(&relayer, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    assert!(matches!(client.try_get_escrow_info(&immutables.order_id()), Err(Ok(EscrowError::InvalidImmutables))));
+
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number = 500;
+        ledger.timestamp = 1010;
+    });
+    client.create_dst_escrow(&relayer, &immutables, &SRC_CANCELLATION, &None);
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number = 512;
+        ledger.timestamp = 1070;
+    });
+
+    let info = client.get_escrow_info(&immutables.order_id());
+    assert_eq!(info.escrow_type, EscrowType::Destination);
+    assert_eq!(info.status.stage, EscrowStage::Created);
+    assert_eq!(info.created_ledger, 500);
+    assert_eq!(info.created_at, 1010);
+    assert_eq!(info.created_by, Some(relayer));
+    assert_eq!(info.confirmations, 12);
+    // The token's metadata was read once at creation
+    let token_client = TokenClient::new(&env, &token_address);
+    assert_eq!(info.token_decimals, Some(7));
+    assert_eq!(info.token_symbol, Some(token_client.symbol()));
+
+    // A token contract without metadata leaves it unset instead of failing creation
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut plain = create_test_immutables_with_secret(&env, secret, hashlock);
+    plain.order_hash = BytesN::from_array(&env, &[0x7a; 32]);
+    client.create_src_escrow(&Address::generate(&env), &plain, &None);
+    let info = client.get_escrow_info(&plain.order_id());
+    assert_eq!((info.token_decimals, info.token_symbol), (None, None));
+}
+
+#[test]
+fn test_colliding_parties_are_rejected() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, _) = setup_token(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+
+    let mut same_party = immutables.clone();
+    same_party.taker = same_party.maker.clone();
+    let mut same_stellar = immutables.clone();
+    same_stellar.taker = create_test_dual_address_with_stellar(&env, immutables.maker.stellar.clone(), 0x02);
+    let mut token_is_taker = immutables.clone();
+    token_is_taker.taker = create_test_dual_address_with_stellar(&env, token_address, 0x03);
+    let mut factory_sponsor = immutables.clone();
+    factory_sponsor.sponsor = Some(contract_id.clone());
+    for colliding in [same_party, same_stellar, token_is_taker, factory_sponsor] {
+        assert_eq!(
+            client.try_create_src_escrow(&Address::generate(&env), &colliding, &None),
+            Err(Ok(EscrowError::SelfTransfer))
+        );
+    }
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+}
+
+#[test]
+fn test_stellar_origin_src_escrow() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &1100);
+    let maker = Address::generate(&env);
+
+    // A Stellar account without any EVM address swaps into an EVM token
+    let order = StellarOrderFields {
+        maker: maker.clone(),
+        maker_evm: None,
+        maker_asset: token_address.clone(),
+        taker_asset: BytesN::from_array(&env, &[0x44; 20]),
+        receiver: BytesN::from_array(&env, &[0x55; 20]),
+        making_amount: 1000,
+        taking_amount: 2500,
+        salt: BytesN::from_array(&env, &[0x66; 32]),
+        src_chain_id: 0,
+        dst_chain_id: 0,
+    };
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.order_hash = client.compute_stellar_order_hash(&order);
+    immutables.maker = DualAddress { evm: BytesN::from_array(&env, &[0; 20]), stellar: maker.clone() };
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    assert_eq!(
+        client.try_create_stellar_src_escrow(&Address::generate(&env), &order, &immutables, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    immutables.stellar_origin = true;
+
+    // Neither the EVM paths nor an order with other fields accept it
+    let relayer = Address::generate(&env);
+    assert_eq!(client.try_create_src_escrow(&relayer, &immutables, &None), Err(Ok(EscrowError::InvalidImmutables)));
+    assert_eq!(
+        client.try_create_dst_escrow(&relayer, &immutables, &SRC_CANCELLATION, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    let mut other = order.clone();
+    other.making_amount = 999;
+    assert_eq!(
+        client.try_create_stellar_src_escrow(&relayer, &other, &immutables, &None),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+
+    client.create_stellar_src_escrow(&relayer, &order, &immutables, &None);
+    assert_eq!(env.auths()[0].0, maker);
+    let (_, stored) = client.get_escrow_state(&immutables.order_id());
+    assert_eq!(stored.maker.stellar, maker);
+    assert!(client.get_order_mappings(&immutables.order_id()).iter().all(|party| party.stellar != maker));
+
+    // The maker gets its tokens (and, on a private cancel, the deposit) back without any mapping
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1300;
+    });
+    let payout = client.cancel(&immutables.order_id());
+    assert_eq!(payout.recipient, maker);
+    assert_eq!(token_client.balance(&maker), 1100);
+}
+
+#[test]
+fn test_order_id_must_match_escrow_origin() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    assert_eq!(immutables.order_id(), OrderId::EvmKeccak(immutables.order_hash.clone()));
+
+    // The same hash tagged as a Stellar order names no escrow here
+    let stellar_id = OrderId::StellarSha256(immutables.order_hash.clone());
+    assert!(matches!(client.try_get_escrow_state(&stellar_id), Err(Ok(EscrowError::InvalidOrderId))));
+    assert_eq!(client.try_withdraw(&stellar_id, &secret, &None), Err(Ok(EscrowError::InvalidOrderId)));
+    assert_eq!(client.get_order_locked(&stellar_id), 0);
+    assert_eq!(client.get_escrow_history(&stellar_id).len(), 0);
+    assert_eq!(client.get_order_locked(&immutables.order_id()), 1100);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.timestamp = 1100;
+    });
+    client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(client.get_escrow_stage(&immutables.order_id()), EscrowStage::Withdrawn);
+}
+
+#[test]
+fn test_actions_extend_escrow_ttl() {
+    use soroban_sdk::testutils::storage::Persistent as _;
+
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &1100);
+
+    assert_eq!(client.try_set_escrow_ttl(&Vec::new(&env), &0), Err(Ok(EscrowError::InvalidImmutables)));
+    assert_eq!(client.try_set_escrow_ttl(&Vec::new(&env), &u32::MAX), Err(Ok(EscrowError::InvalidImmutables)));
+    client.set_escrow_ttl(&Vec::new(&env), &2_000);
+    assert_eq!(client.get_escrow_ttl(), 2_000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    client.approve_operator(&immutables.order_id(), &Address::generate(&env));
+    let ttl = |key: EscrowDataKey| env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&key));
+    let state = EscrowDataKey::EscrowState(immutables.order_hash.clone());
+    let operator = EscrowDataKey::Operator(immutables.order_hash.clone());
+    // New entries start at the network minimum (4096 ledgers in tests), above the configured TTL
+    assert_eq!(ttl(state.clone()), 4_095);
+
+    // Even a read bumps every record of the escrow back to the full TTL
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += 3_000;
+    });
+    assert_eq!(ttl(state.clone()), 1_095);
+    client.get_escrow_stage(&immutables.order_id());
+    assert_eq!(ttl(state.clone()), 2_000);
+    assert_eq!(ttl(operator.clone()), 2_000);
+
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += 500;
+        ledger.timestamp = 1100;
+    });
+    client.withdraw(&immutables.order_id(), &secret, &None);
+    assert_eq!(ttl(state), 2_000);
+    assert_eq!(ttl(operator), 2_000);
+    let status = client.get_escrow_status(&immutables.order_id());
+    assert_eq!(status.live_until_ledger, env.ledger().sequence() + 2_000);
+}
+
+#[test]
+fn test_repeated_withdraw_returns_already_settled_and_receipt() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    let order_id = immutables.order_id();
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    assert_eq!(client.get_settlement_receipt(&order_id), None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    let payout = client.withdraw(&order_id, &secret, &None);
+
+    // The retry is told the escrow settled, not that the window is wrong
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1160);
+    assert_eq!(client.try_withdraw(&order_id, &secret, &None), factory_error(FactoryError::AlreadySettled));
+    let receipt = client.get_settlement_receipt(&order_id).unwrap();
+    assert_eq!(receipt.stage, EscrowStage::Withdrawn);
+    assert_eq!(receipt.payout, payout);
+    assert_eq!(receipt.timestamp, 1150);
+
+    // Only the settling secret identifies a retry
+    let other_secret = BytesN::from_array(&env, &[0x07; 32]);
+    assert_eq!(client.try_withdraw(&order_id, &other_secret, &None), Err(Ok(EscrowError::InvalidStageTransition)));
+}
+
+#[test]
+fn test_attestation_required_gates_withdraw_to_attested_claimants() {
+    use ed25519_dalek::Signer;
+
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    immutables.attestation_required = true;
+    let order_id = immutables.order_id();
+    let claimant = immutables.taker.stellar.clone();
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    assert_eq!(client.try_withdraw(&order_id, &secret, &None), factory_error(FactoryError::AttestationRequired));
+
+    let attestor = ed25519_dalek::SigningKey::from_bytes(&[0x07; 32]);
+    let attest = |key: &ed25519_dalek::SigningKey| {
+        let message: std::vec::Vec<u8> = client.get_attestation_message(&claimant).iter().collect();
+        BytesN::from_array(&env, &key.sign(&message).to_bytes())
+    };
+    assert_eq!(client.try_submit_attestation(&claimant, &attest(&attestor)), Err(Ok(EscrowError::InvalidSignature)));
+    let approvers = Vec::new(&env);
+    client.set_attestor(&approvers, &BytesN::from_array(&env, &attestor.verifying_key().to_bytes()));
+    // Another key's signature traps
+    assert!(client.try_submit_attestation(&claimant, &attest(&ed25519_dalek::SigningKey::from_bytes(&[0x08; 32]))).is_err());
+    assert!(!client.is_attested(&claimant));
+
+    client.submit_attestation(&claimant, &attest(&attestor));
+    assert!(client.is_attested(&claimant));
+    assert_eq!(client.withdraw(&order_id, &secret, &None).recipient, claimant);
+
+    // Rotating the attestor voids what the previous key attested
+    let rotated = ed25519_dalek::SigningKey::from_bytes(&[0x09; 32]);
+    client.set_attestor(&approvers, &BytesN::from_array(&env, &rotated.verifying_key().to_bytes()));
+    assert!(!client.is_attested(&claimant));
+}
+
+#[test]
+fn test_parameter_changes_wait_for_their_delay() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(EscrowFactory, (Address::generate(&env), 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let approvers = Vec::new(&env);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1000);
+
+    let change = ParamChange::RescueDelay(3600);
+    assert_eq!(client.announce_change(&approvers, &change), 1000 + PARAM_CHANGE_DELAY);
+    assert_eq!(
+        client.get_pending_change(&Param::RescueDelay),
+        Some(PendingChange { change: change.clone(), executable_at: 1000 + PARAM_CHANGE_DELAY })
+    );
+    assert_eq!(client.try_execute_change(&approvers, &Param::RescueDelay), Err(Ok(EscrowError::InvalidTime)));
+    assert_eq!(client.try_execute_change(&approvers, &Param::DepositDecay), factory_error(FactoryError::ChangeNotAnnounced));
+    assert_eq!(client.get_rescue_delay(), 86400);
+
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1000 + PARAM_CHANGE_DELAY);
+    assert_eq!(client.execute_change(&approvers, &Param::RescueDelay), change);
+    assert_eq!(client.get_rescue_delay(), 3600);
+    assert_eq!(client.get_pending_change(&Param::RescueDelay), None);
+
+    // Cancelled changes never apply
+    client.announce_change(&approvers, &ParamChange::DepositDecay(5000));
+    assert_eq!(client.get_pending_changes().len(), 1);
+    client.cancel_change(&approvers, &Param::DepositDecay);
+    assert_eq!(client.try_cancel_change(&approvers, &Param::DepositDecay), factory_error(FactoryError::ChangeNotAnnounced));
+    env.ledger().with_mut(|ledger| ledger.timestamp += PARAM_CHANGE_DELAY);
+    assert_eq!(client.try_execute_change(&approvers, &Param::DepositDecay), factory_error(FactoryError::ChangeNotAnnounced));
+    assert_eq!(client.get_deposit_decay(), 0);
+
+    // Freezing also stops changes already announced
+    client.announce_change(&approvers, &ParamChange::RescueDelay(7200));
+    client.freeze_config(&approvers);
+    env.ledger().with_mut(|ledger| ledger.timestamp += PARAM_CHANGE_DELAY);
+    assert_eq!(client.try_execute_change(&approvers, &Param::RescueDelay), Err(Ok(EscrowError::ConfigFrozen)));
+}
+
+#[test]
+fn test_public_actions_pay_executor_rebate_from_pool() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    let token_client = TokenClient::new(&env, &token_address);
+    token_admin.mint(&contract_id, &3300);
+    let funder = Address::generate(&env);
+    token_admin.mint(&funder, &13);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut src = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    src.token = create_test_dual_address_with_stellar(&env, token_address.clone(), 0x01);
+    src.expiration = 1300;
+    let mut other = src.clone();
+    other.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    other.taker = create_test_dual_address(&env);
+    let mut third = src.clone();
+    third.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    client.create_src_escrow(&Address::generate(&env), &src, &None);
+    client.create_src_escrow(&Address::generate(&env), &other, &None);
+    client.create_src_escrow(&Address::generate(&env), &third, &None);
+
+    assert_eq!(
+        client.try_set_executor_rebate(&Vec::new(&env), &token_address, &-1),
+        Err(Ok(EscrowError::AmountBelowMinimum))
+    );
+    client.set_executor_rebate(&Vec::new(&env), &token_address, &5);
+    assert_eq!(client.fund_rebates(&funder, &token_address, &13), 13);
+    assert_eq!(client.get_total_locked(&token_address), 3313);
+
+    // Full rebate on top of the deposit, then what's left in the pool
+    let caller = Address::generate(&env);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1200);
+    client.public_withdraw(&third.order_id(), &caller, &secret);
+    assert_eq!(token_client.balance(&caller), 105);
+    assert_eq!(client.get_rebate_pool(&token_address), 8);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1400);
+    client.public_cancel(&src.order_id(), &caller);
+    assert_eq!(token_client.balance(&caller), 210);
+    assert_eq!(client.get_rebate_pool(&token_address), 3);
+    client.expire(&other.order_id(), &caller);
+    assert_eq!(token_client.balance(&caller), 313);
+    assert_eq!(client.get_rebate_pool(&token_address), 0);
+    assert_eq!(client.get_total_locked(&token_address), 0);
+}
+
+#[test]
+fn test_child_leg_waits_for_parent_and_cancels_with_it() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &4400);
+    let relayer = Address::generate(&env);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut parent = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    parent.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    let leg = |seed: u8, parent_hash: &BytesN<32>| {
+        let mut child = parent.clone();
+        child.order_hash = BytesN::from_array(&env, &[seed; 32]);
+        child.parent = Some(parent_hash.clone());
+        child
+    };
+    let mut child = leg(0x02, &parent.order_hash);
+    child.maker = create_test_dual_address(&env);
+
+    // The parent must be another escrow of this factory
+    assert_eq!(client.try_create_src_escrow(&relayer, &child, &None), Err(Ok(EscrowError::InvalidImmutables)));
+    let own_parent = leg(0x02, &child.order_hash);
+    assert_eq!(client.try_create_src_escrow(&relayer, &own_parent, &None), Err(Ok(EscrowError::InvalidImmutables)));
+    client.create_src_escrow(&relayer, &parent, &None);
+
+    // Hanging a leg off the parent takes its maker's consent
+    env.set_auths(&[]);
+    assert!(client.try_create_src_escrow(&relayer, &child, &None).is_err());
+    env.mock_all_auths();
+    client.create_src_escrow(&relayer, &child, &None);
+    let authorizers: std::vec::Vec<Address> = env.auths().into_iter().map(|(address, _)| address).collect();
+    assert!(authorizers.contains(&parent.maker.stellar));
+    assert_eq!(client.get_children(&parent.order_id()), Vec::from_array(&env, [child.order_hash.clone()]));
+
+    // The child pays out only after its parent
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1150);
+    assert_eq!(client.try_withdraw(&child.order_id(), &secret, &None), Err(Ok(EscrowError::InvalidStageTransition)));
+    assert_eq!(client.try_cancel_orphan(&child.order_id()), Err(Ok(EscrowError::InvalidStageTransition)));
+    client.withdraw(&parent.order_id(), &secret, &None);
+    client.withdraw(&child.order_id(), &secret, &None);
+
+    // An expired parent lets anyone unwind its children before their own cancellation
+    let mut expiring = parent.clone();
+    expiring.order_hash = BytesN::from_array(&env, &[0x03; 32]);
+    expiring.expiration = 1200;
+    let orphan = leg(0x04, &expiring.order_hash);
+    client.create_src_escrow(&relayer, &expiring, &None);
+    client.create_src_escrow(&relayer, &orphan, &None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1200);
+    client.expire(&expiring.order_id(), &Address::generate(&env));
+    assert_eq!(client.try_create_src_escrow(&relayer, &leg(0x05, &expiring.order_hash), &None), Err(Ok(EscrowError::InvalidImmutables)));
+    let payout = client.cancel_orphan(&orphan.order_id());
+    assert_eq!(payout.amount, 1000);
+    assert_eq!(client.get_escrow_stage(&orphan.order_id()), EscrowStage::Cancelled);
+    assert_eq!(client.get_cancel_reason(&orphan.order_id()), Some(CancelReason::ParentCancelled));
+}
+
+#[test]
+fn test_timestamp_tolerance_extends_withdrawal_deadline() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin, 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+
+    // Private window [1100, 1200), the withdrawal lands a little late
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1210);
+    assert_eq!(
+        client.try_withdraw(&immutables.order_id(), &secret, &None),
+        Err(Ok(EscrowError::WithdrawalWindowClosed))
+    );
+    assert_eq!(
+        client.try_set_timestamp_tolerance(&Vec::new(&env), &(MAX_TIMESTAMP_TOLERANCE + 1)),
+        Err(Ok(EscrowError::InvalidImmutables))
+    );
+    client.set_timestamp_tolerance(&Vec::new(&env), &30);
+    assert_eq!(client.get_timestamp_tolerance(), 30);
+    client.withdraw(&immutables.order_id(), &secret, &None);
+
+    // Windows don't open early
+    let mut early = immutables.clone();
+    early.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1000);
+    client.create_src_escrow(&Address::generate(&env), &early, &None);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1090);
+    assert_eq!(
+        client.try_withdraw(&early.order_id(), &secret, &None),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+}
+
+#[test]
+fn test_get_config_snapshots_factory_settings() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(EscrowFactory, (admin.clone(), 86400u64));
+    let client = EscrowFactoryClient::new(&env, &contract_id);
+    let (token_address, token_admin) = setup_token(&env);
+    token_admin.mint(&contract_id, &10000);
+
+    let config = client.get_config();
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.rescue_delay, 86400);
+    assert_eq!(config.treasury, None);
+    assert!(config.paused_tokens.is_empty() && config.supported_chains.is_empty());
+    assert_eq!((config.finality_lag, config.timestamp_tolerance), (0, 0));
+    assert_eq!(config.max_timelock_horizon, DEFAULT_MAX_TIMELOCK_HORIZON);
+    assert_eq!(config.escrow_ttl, ESCROW_TTL_LEDGERS);
+    assert_eq!(config.token_policy, TokenPolicy::Open);
+    assert!(!config.relayer_only);
+    assert_eq!((config.watchdog_min_bond, config.watchdog_priority_window), (None, 0));
+
+    let treasury = Address::generate(&env);
+    let ethereum = ChainConfig { finality_lag: 60, min_safety_deposit: 100, fee_bps: 2500 };
+    client.add_supported_chain(&Vec::new(&env), &1, &ethereum);
+    client.add_supported_chain(&Vec::new(&env), &0x5354454c4c4152, &ChainConfig::default());
+    client.remove_supported_chain(&Vec::new(&env), &0x5354454c4c4152);
+    client.pause_token(&Vec::new(&env), &token_address);
+    apply_change(&env, &client, ParamChange::DepositSplit(DepositSplit { treasury: treasury.clone(), treasury_bps: 1000 }));
+    client.set_finality_lag(&Vec::new(&env), &30);
+    client.set_timestamp_tolerance(&Vec::new(&env), &5);
+    client.set_escrow_ttl(&Vec::new(&env), &100_000);
+    client.set_token_policy(&Vec::new(&env), &TokenPolicy::DenyList);
+    client.set_watchdog_config(&Vec::new(&env), &WatchdogConfig { min_bond: 10, priority_window: 60 });
+
+    let config = client.get_config();
+    assert_eq!((config.finality_lag, config.timestamp_tolerance, config.escrow_ttl), (30, 5, 100_000));
+    assert_eq!(config.token_policy, TokenPolicy::DenyList);
+    assert_eq!((config.watchdog_min_bond, config.watchdog_priority_window), (Some(10), 60));
+    assert_eq!(config.supported_chains.len(), 1);
+    assert_eq!(config.supported_chains.get(1), Some(ethereum));
+    assert_eq!(config.paused_tokens, Vec::from_array(&env, [token_address.clone()]));
+    assert_eq!((config.treasury, config.treasury_bps), (Some(treasury), 1000));
+    client.unpause_token(&Vec::new(&env), &token_address);
+    assert!(client.get_config().paused_tokens.is_empty());
+
+    // Rescue opens the rescue delay after creation, the escrow's own delay taking precedence
+    let (secret, hashlock) = create_test_secret(&env);
+    let mut immutables = create_test_immutables_with_secret(&env, secret, hashlock);
+    immutables.token = create_test_dual_address_with_stellar(&env, token_address, 0x01);
+    client.remove_supported_chain(&Vec::new(&env), &1);
+    env.ledger().with_mut(|ledger| ledger.timestamp = 1000);
+    client.create_src_escrow(&Address::generate(&env), &immutables, &None);
+    assert_eq!(client.get_rescue_start(&immutables.order_id()), 1000 + 86400);
+
+    let mut own_delay = immutables.clone();
+    own_delay.order_hash = BytesN::from_array(&env, &[0x02; 32]);
+    own_delay.rescue_delay = 172_800;
+    client.create_src_escrow(&Address::generate(&env), &own_delay, &None);
+    assert_eq!(client.get_rescue_start(&own_delay.order_id()), 1000 + 172_800);
+}
@@ -0,0 +1,163 @@
+//! End-to-end harness for cross-chain swaps.
+//!
+//! Two factory deployments stand in for the source and destination chains, each
+//! with its own token. The maker locks tokens on the source side, the resolver
+//! fills on the destination side, and the same order_hash and hashlock tie both
+//! legs together like they would across Stellar and an EVM chain.
+
+use factory::{EscrowFactory, EscrowFactoryClient};
+use shared::{timelocks, DualAddress, Immutables, LopOrder, Stage, Timelocks};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::token::{StellarAssetClient, TokenClient};
+use soroban_sdk::{Address, Bytes, BytesN, Env, Vec};
+
+pub const AMOUNT: i128 = 1000;
+pub const SAFETY_DEPOSIT: i128 = 100;
+pub const DEPLOYED_AT: u32 = 1000;
+
+/// One side of the swap: a factory and the token it escrows
+pub struct Chain<'a> {
+    pub factory: EscrowFactoryClient<'a>,
+    pub token: TokenClient<'a>,
+    pub token_admin: StellarAssetClient<'a>,
+}
+
+impl<'a> Chain<'a> {
+    fn new(env: &Env) -> Self {
+        let admin = Address::generate(env);
+        let factory_id = env.register(EscrowFactory, (admin, 86400u64));
+        let token_id = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+        Chain {
+            factory: EscrowFactoryClient::new(env, &factory_id),
+            token: TokenClient::new(env, &token_id),
+            token_admin: StellarAssetClient::new(env, &token_id),
+        }
+    }
+
+    /// Tokens the factory holds for its escrows
+    pub fn escrowed(&self) -> i128 {
+        self.token.balance(&self.factory.address)
+    }
+}
+
+/// A single order with both of its legs
+pub struct Swap<'a> {
+    pub env: Env,
+    pub src: Chain<'a>,
+    pub dst: Chain<'a>,
+    pub maker: Address,
+    pub resolver: Address,
+    pub secret: BytesN<32>,
+    pub src_immutables: Immutables,
+    pub dst_immutables: Immutables,
+}
+
+impl<'a> Swap<'a> {
+    /// Set up both chains and fund the maker and the resolver, nothing is escrowed yet
+    pub fn new(env: &Env) -> Self {
+        env.mock_all_auths();
+        env.ledger().with_mut(|ledger| {
+            ledger.timestamp = DEPLOYED_AT as u64;
+        });
+
+        let src = Chain::new(env);
+        let dst = Chain::new(env);
+        let maker = Address::generate(env);
+        let resolver = Address::generate(env);
+        src.token_admin.mint(&maker, &AMOUNT);
+        src.token_admin.mint(&resolver, &SAFETY_DEPOSIT);
+        dst.token_admin.mint(&resolver, &(AMOUNT + SAFETY_DEPOSIT));
+
+        let secret = BytesN::from_array(env, &[0x5e; 32]);
+        let hashlock: BytesN<32> = env.crypto().keccak256(&Bytes::from_array(env, &secret.to_array())).into();
+        let order_hash = BytesN::from_array(env, &[0x0d; 32]);
+
+        let maker_evm = BytesN::from_array(env, &[0x11; 20]);
+        let resolver_evm = BytesN::from_array(env, &[0x22; 20]);
+        let dual = |evm: &BytesN<20>, stellar: &Address| DualAddress {
+            evm: evm.clone(),
+            stellar: stellar.clone(),
+        };
+
+        // Source leg: the maker's tokens, withdrawn by the resolver. The resolver put
+        // up the safety deposit, so it gets it back on cancel as the sponsor
+        let src_immutables = Immutables {
+            order_hash: order_hash.clone(),
+            hashlock: hashlock.clone(),
+            hashlocks: Vec::new(env),
+            cancel_hashlock: None,
+            sponsor: Some(resolver.clone()),
+            maker: dual(&maker_evm, &maker),
+            taker: dual(&resolver_evm, &resolver),
+            token: dual(&BytesN::from_array(env, &[0x33; 20]), &src.token.address),
+            amount: AMOUNT,
+            safety_deposit: SAFETY_DEPOSIT,
+            timelocks: Self::timelocks(env),
+            expiration: 0,
+            rescue_delay: 0,
+            finality_lag: 0,
+            src_chain_id: 0,
+            dst_chain_id: 0,
+            parts: Vec::new(env),
+            native_deposit: false,
+            deposit_decay_bps: 0,
+            stellar_origin: false,
+            lop_order: LopOrder::Unrecorded,
+            attestation_required: false,
+            parent: None,
+        };
+
+        // Destination leg: the resolver's tokens, which it releases to the maker as taker
+        let dst_immutables = Immutables {
+            token: dual(&BytesN::from_array(env, &[0x44; 20]), &dst.token.address),
+            sponsor: None,
+            ..src_immutables.clone()
+        };
+
+        Swap {
+            env: env.clone(),
+            src,
+            dst,
+            maker,
+            resolver,
+            secret,
+            src_immutables,
+            dst_immutables,
+        }
+    }
+
+    // Same schedule on both legs, destination windows close before the source ones
+    fn timelocks(env: &Env) -> Timelocks {
+        Timelocks::new(
+            env,
+            DEPLOYED_AT,
+            100, // src_withdrawal
+            200, // src_public_withdrawal
+            300, // src_cancellation
+            400, // src_public_cancellation
+            150, // dst_withdrawal
+            250, // dst_public_withdrawal
+            280, // dst_cancellation
+        )
+    }
+
+    /// Lock the maker's tokens and the resolver's deposit in the source escrow
+    pub fn lock_src(&self) {
+        self.src.token.transfer(&self.maker, &self.src.factory.address, &AMOUNT);
+        self.src.token.transfer(&self.resolver, &self.src.factory.address, &SAFETY_DEPOSIT);
+        self.src.factory.create_src_escrow(&self.resolver, &self.src_immutables, &None);
+    }
+
+    /// Fill the order on the destination side in one call
+    pub fn fill_dst(&self) {
+        let src_cancellation = timelocks::get(&self.src_immutables.timelocks, &self.env, Stage::SrcCancellation).unwrap();
+        self.dst.factory.fill_order(&self.resolver, &self.dst_immutables, &src_cancellation, &self.resolver);
+    }
+
+    /// Move the ledger to `offset` seconds after deployment
+    pub fn advance_to(&self, offset: u64) {
+        self.env.ledger().with_mut(|ledger| {
+            ledger.timestamp = DEPLOYED_AT as u64 + offset;
+        });
+    }
+}
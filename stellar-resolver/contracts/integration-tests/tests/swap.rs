@@ -0,0 +1,101 @@
+use factory::EscrowStage;
+use integration_tests::{Swap, AMOUNT, SAFETY_DEPOSIT};
+use shared::EscrowError;
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{Address, Env};
+
+fn locked_swap(env: &Env) -> Swap<'_> {
+    let swap = Swap::new(env);
+    swap.lock_src();
+    swap.fill_dst();
+
+    assert_eq!(swap.src.escrowed(), AMOUNT + SAFETY_DEPOSIT);
+    assert_eq!(swap.dst.escrowed(), AMOUNT + SAFETY_DEPOSIT);
+    assert_eq!(swap.src.token.balance(&swap.maker), 0);
+    assert_eq!(swap.dst.token.balance(&swap.resolver), 0);
+    swap
+}
+
+#[test]
+fn test_happy_path_swap() {
+    let env = Env::default();
+    let swap = locked_swap(&env);
+    let order_id = swap.src_immutables.order_id();
+
+    // Nothing can be withdrawn before the destination window opens
+    swap.advance_to(100);
+    assert_eq!(
+        swap.dst.factory.try_withdraw(&order_id, &swap.secret, &None),
+        Err(Ok(EscrowError::TooEarlyForWithdrawal))
+    );
+
+    // The resolver releases the destination leg to the maker and reveals the secret,
+    // keeping its safety deposit
+    swap.advance_to(150);
+    swap.dst.factory.withdraw(&order_id, &swap.secret, &None);
+    assert_eq!(swap.dst.token.balance(&swap.maker), AMOUNT);
+    assert_eq!(swap.dst.token.balance(&swap.resolver), SAFETY_DEPOSIT);
+    assert_eq!(swap.dst.escrowed(), 0);
+    assert_eq!(swap.dst.factory.get_escrow_stage(&order_id), EscrowStage::Withdrawn);
+
+    // The resolver picks the secret up from the destination chain and claims the source leg
+    let revealed = swap.dst.factory.get_revealed_secret(&swap.src_immutables.hashlock).unwrap();
+    swap.src.factory.withdraw(&order_id, &revealed, &None);
+    assert_eq!(swap.src.token.balance(&swap.resolver), AMOUNT + SAFETY_DEPOSIT);
+    assert_eq!(swap.src.escrowed(), 0);
+    assert_eq!(swap.src.factory.get_escrow_stage(&order_id), EscrowStage::Withdrawn);
+
+    assert!(swap.src.factory.check_solvency(&swap.src.token.address).solvent);
+    assert!(swap.dst.factory.check_solvency(&swap.dst.token.address).solvent);
+}
+
+#[test]
+fn test_timeout_refunds_both_legs() {
+    let env = Env::default();
+    let swap = locked_swap(&env);
+    let order_id = swap.src_immutables.order_id();
+
+    // The secret is never revealed, so both legs run into cancellation, the destination first
+    swap.advance_to(280);
+    assert_eq!(
+        swap.src.factory.try_cancel(&order_id),
+        Err(Ok(EscrowError::CancellationNotReachable))
+    );
+    swap.dst.factory.cancel(&order_id);
+    assert_eq!(swap.dst.token.balance(&swap.resolver), AMOUNT + SAFETY_DEPOSIT);
+    assert_eq!(swap.dst.escrowed(), 0);
+
+    swap.advance_to(300);
+    swap.src.factory.cancel(&order_id);
+    assert_eq!(swap.src.token.balance(&swap.maker), AMOUNT);
+    assert_eq!(swap.src.token.balance(&swap.resolver), SAFETY_DEPOSIT);
+    assert_eq!(swap.src.escrowed(), 0);
+
+    // A late secret no longer unlocks anything
+    assert_eq!(
+        swap.dst.factory.try_withdraw(&order_id, &swap.secret, &None),
+        Err(Ok(EscrowError::InvalidStageTransition))
+    );
+    assert_eq!(swap.src.factory.get_total_locked(&swap.src.token.address), 0);
+    assert_eq!(swap.dst.factory.get_total_locked(&swap.dst.token.address), 0);
+}
+
+#[test]
+fn test_public_cancel_after_resolver_disappears() {
+    let env = Env::default();
+    let swap = locked_swap(&env);
+    let order_id = swap.src_immutables.order_id();
+    let keeper = Address::generate(&env);
+
+    swap.advance_to(280);
+    swap.dst.factory.cancel(&order_id);
+    assert_eq!(swap.dst.token.balance(&swap.resolver), AMOUNT + SAFETY_DEPOSIT);
+
+    // Nobody cancelled the source leg privately, so a keeper does it for the deposit
+    swap.advance_to(400);
+    swap.src.factory.public_cancel(&order_id, &keeper);
+    assert_eq!(swap.src.token.balance(&swap.maker), AMOUNT);
+    assert_eq!(swap.src.token.balance(&keeper), SAFETY_DEPOSIT);
+    assert_eq!(swap.src.escrowed(), 0);
+    assert_eq!(swap.src.factory.get_escrow_stage(&order_id), EscrowStage::Cancelled);
+}
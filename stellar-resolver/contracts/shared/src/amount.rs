@@ -0,0 +1,60 @@
+//! Token amounts and their conversion to the unsigned EVM encoding.
+//!
+//! Soroban tokens count in i128 while `abi.encode` writes uint256 words, so every
+//! amount crossing into a hash goes through `to_u128` here instead of an `as` cast.
+//! `EscrowAmount` is an amount that was checked positive once, at construction.
+//! Contract types keep plain i128 fields, since values arriving from outside can't
+//! be trusted to hold the invariant, and are turned into `EscrowAmount` at the edge.
+
+use crate::baseescrow::Error;
+
+/// Strictly positive token amount: an order's amount, safety deposit or a part of it
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct EscrowAmount(i128);
+
+impl EscrowAmount {
+    pub fn new(value: i128) -> Result<Self, Error> {
+        if value <= 0 {
+            return Err(Error::InvalidImmutables);
+        }
+        Ok(EscrowAmount(value))
+    }
+
+    /// Amount decoded from a uint256-sized EVM value, which may not fit an i128
+    pub fn from_u128(value: u128) -> Result<Self, Error> {
+        Self::new(i128::try_from(value).map_err(|_| Error::InvalidImmutables)?)
+    }
+
+    pub fn get(self) -> i128 {
+        self.0
+    }
+
+    /// Positive by construction, so the conversion is lossless
+    pub fn to_u128(self) -> u128 {
+        self.0 as u128
+    }
+
+    pub fn checked_add(self, other: EscrowAmount) -> Result<Self, Error> {
+        self.0.checked_add(other.0).map(EscrowAmount).ok_or(Error::InvalidImmutables)
+    }
+}
+
+impl TryFrom<i128> for EscrowAmount {
+    type Error = Error;
+
+    fn try_from(value: i128) -> Result<Self, Error> {
+        Self::new(value)
+    }
+}
+
+impl From<EscrowAmount> for i128 {
+    fn from(amount: EscrowAmount) -> i128 {
+        amount.0
+    }
+}
+
+/// Unsigned view of an amount that may be zero (order amounts, zero safety deposits
+/// on the EVM side), for hashing. Negative amounts have no EVM encoding
+pub fn to_u128(value: i128) -> Result<u128, Error> {
+    u128::try_from(value).map_err(|_| Error::InvalidImmutables)
+}
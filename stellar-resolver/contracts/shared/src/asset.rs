@@ -0,0 +1,90 @@
+use soroban_sdk::{token, Address, Bytes, Env, String};
+
+use crate::baseescrow::Error;
+
+/// Serialized XDR of the native asset (AssetType::Native), used to derive the XLM SAC address
+const NATIVE_ASSET_XDR: [u8; 4] = [0, 0, 0, 0];
+
+/// Uniform handle over the token flavours an escrow can hold: Soroban-native tokens,
+/// classic assets wrapped by their Stellar Asset Contract, and XLM
+pub enum Asset {
+    /// Any SEP-41 token contract
+    Token(token::TokenClient<'static>),
+    /// SAC wrapping a classic asset, the admin interface is usable when this contract is the SAC admin
+    Classic(token::TokenClient<'static>, token::StellarAssetClient<'static>),
+    /// The XLM SAC
+    Native(token::TokenClient<'static>),
+}
+
+impl Asset {
+    /// Address of the XLM Stellar Asset Contract on the current network
+    pub fn native_address(env: &Env) -> Address {
+        env.deployer()
+            .with_stellar_asset(Bytes::from_array(env, &NATIVE_ASSET_XDR))
+            .deployed_address()
+    }
+
+    pub fn native(env: &Env) -> Self {
+        Asset::Native(token::TokenClient::new(env, &Self::native_address(env)))
+    }
+
+    /// Wrap a token address, recognizing XLM; other addresses are treated as plain tokens
+    pub fn from_address(env: &Env, address: &Address) -> Self {
+        if *address == Self::native_address(env) {
+            return Self::native(env);
+        }
+        Asset::Token(token::TokenClient::new(env, address))
+    }
+
+    /// Wrap the SAC of a classic asset to also get its admin interface
+    pub fn classic(env: &Env, address: &Address) -> Self {
+        Asset::Classic(
+            token::TokenClient::new(env, address),
+            token::StellarAssetClient::new(env, address),
+        )
+    }
+
+    fn client(&self) -> &token::TokenClient<'static> {
+        match self {
+            Asset::Token(client) | Asset::Classic(client, _) | Asset::Native(client) => client,
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.client().address.clone()
+    }
+
+    pub fn balance(&self, id: &Address) -> i128 {
+        self.client().balance(id)
+    }
+
+    pub fn transfer(&self, from: &Address, to: &Address, amount: i128) {
+        self.client().transfer(from, to, &amount);
+    }
+
+    /// Transfer without trapping, false when the token rejected it (e.g. a deauthorized
+    /// or missing trustline) so the caller can fall back instead of reverting
+    pub fn try_transfer(&self, from: &Address, to: &Address, amount: i128) -> bool {
+        matches!(self.client().try_transfer(from, to, &amount), Ok(Ok(())))
+    }
+
+    /// Decimals and symbol of the token, None when it doesn't implement them instead of trapping
+    pub fn metadata(&self) -> Option<(u32, String)> {
+        let client = self.client();
+        match (client.try_decimals(), client.try_symbol()) {
+            (Ok(Ok(decimals)), Ok(Ok(symbol))) => Some((decimals, symbol)),
+            _ => None,
+        }
+    }
+
+    /// Mint through the SAC admin interface, only classic assets support it
+    pub fn mint(&self, to: &Address, amount: i128) -> Result<(), Error> {
+        match self {
+            Asset::Classic(_, admin) => {
+                admin.mint(to, &amount);
+                Ok(())
+            }
+            Asset::Token(_) | Asset::Native(_) => Err(Error::UnsupportedAssetOperation),
+        }
+    }
+}
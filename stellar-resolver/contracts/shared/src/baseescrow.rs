@@ -1,8 +1,18 @@
 use soroban_sdk::{
-    contract, contractimpl, contracttype, contracterror, Address, BytesN, Env, symbol_short, token,
+    contracttype, contracterror, xdr::ToXdr, Address, Bytes, BytesN, Env,
 };
-use crate::immutables::{DualAddress, Immutables, immutables};
+use crate::immutables::{DualAddress, Immutables};
+use crate::asset::Asset;
+#[cfg(feature = "storage")]
+use soroban_sdk::symbol_short;
+#[cfg(feature = "storage")]
+use crate::immutables::immutables;
+#[cfg(feature = "storage")]
 use crate::timelock::timelocks;
+#[cfg(feature = "storage")]
+use crate::statemachine::{self, EscrowStage};
+#[cfg(feature = "storage")]
+use crate::events;
 
 // Storage keys
 #[contracttype]
@@ -10,8 +20,16 @@ pub enum DataKey {
     RescueDelay,
     Factory,
     Immutables,
+    Initialized,                // Set by the first init, no later init can replace the escrow's state
+    ImmutablesHash,             // Binding hash of the immutables the escrow was initialized with
+    RevealedSecret(BytesN<32>), // Secret revealed by a withdraw, keyed by its hashlock
+    RevealedPreimage(BytesN<32>), // Preimage that isn't 32 bytes long revealed by a withdraw, keyed by its hashlock
+    Stage,                      // Stage of a standalone escrow, Created from init until a withdraw or cancel settles it
 }
 
+/// Longest preimage accepted for counterpart HTLCs with variable-length secrets
+pub const MAX_PREIMAGE_BYTES: u32 = 128;
+
 // Errors
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -23,40 +41,197 @@ pub enum Error {
     NativeTokenSendingFailure = 5,
     AddressMappingMissing = 6,
     TimeLockError = 7,
+    InvalidStageTransition = 8,
+    InvalidPermit = 9,
+    PermitExpired = 10,
+    PermitKeyMissing = 11,
+    InvalidSignature = 12,
+    MappingNotAttested = 13,
+    TokenNotAllowed = 14,
+    AmountBelowMinimum = 15,
+    AmountAboveMaximum = 16,
+    SafetyDepositTooLow = 17,
+    InsufficientBalance = 18,
+    CreationDeadlinePassed = 19,
+    ThresholdNotMet = 20,
+    UnsupportedAssetOperation = 21,
+    EscrowAlreadyFunded = 22,
+    TooEarlyForWithdrawal = 23,    // Withdrawal window (plus any finality lag) hasn't opened yet
+    WithdrawalWindowClosed = 24,   // Withdrawal window is over, only cancellation is left
+    CancellationNotReachable = 25, // Cancellation timelock hasn't been reached yet
+    RescueNotStarted = 26,         // Rescue delay hasn't elapsed yet
+    UnsupportedChain = 27,
+    MappingConflict = 28,
+    PartsScheduled = 29,    // Escrow with a withdrawal schedule, use withdraw_part
+    UnknownPart = 30,       // Secret doesn't unlock any pending part of the schedule
+    FillExceedsAmount = 31, // Payout would bring the cumulative fill above the order amount
+    ImmutablesMismatch = 32, // Supplied immutables differ from the ones the escrow was initialized with
+    AlreadyInitialized = 33,
+    InvalidPreimageLength = 34, // Empty preimage or longer than MAX_PREIMAGE_BYTES
+    WatchdogsDisabled = 35,     // No watchdog config set, registrations are closed
+    WatchdogPriority = 36,      // A registered watchdog still has priority on this public action
+    WatchdogRegistered = 37,    // Order already has a watchdog
+    TimelockHorizonTooLong = 38, // Last stage opens later than the factory's max timelock horizon
+    ImportsDisabled = 39,        // No migration key set, escrows can't be imported
+    InvalidExport = 40,          // Export is malformed, of another format version or inconsistent
+    ZeroAddress = 41,            // Maker or taker (or, unless allowed, token) EVM address is all zeros
+    SelfTransfer = 42,           // Maker, taker, token, sponsor or the factory coincide, funds would flow back to their payer
+    InsufficientDeposit = 43,    // Factory received less than amount plus safety deposit from the dst escrow's funder
+    InvalidCreationTime = 44,    // Dst escrow's cancellation would open after the src escrow's cancellation start
+    InvalidOrderId = 45,         // Order id's origin differs from the escrow's (EVM vs Stellar order)
+    ConfigFrozen = 46,           // Factory config was frozen, the rescue delay and fee parameters can't change
+    NoDefaultTimelocks = 47,     // No timelock template was set, create_*_escrow_with_defaults can't fill in the stages
+    SweepNotAnnounced = 48,      // Sweep doesn't match the announced one, or none was announced
+    UnknownSchemaVersion = 49,   // Stored escrow record has a layout this factory version can't read
+    OrderReserved = 50,          // Another relayer holds a live reservation of the order id
+}
+
+// Where the funds of a withdraw or cancel ended up, returned to callers
+// so cross-contract integrations don't have to parse events
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutSummary {
+    pub recipient: Address,                 // Receiver of the swap amount
+    pub token: Address,                     // Stellar token contract paid out
+    pub amount: i128,                       // Swap amount transferred
+    pub safety_deposit_recipient: Address,  // Receiver of the safety deposit
+    pub safety_deposit_token: Address,      // Token the safety deposit was paid in, XLM for native deposits
+    pub safety_deposit_amount: i128,        // Safety deposit transferred
 }
 
 // BaseEscrow trait
 pub trait BaseEscrowTrait {
     fn rescue_delay(env: Env) -> u64;
     fn factory(env: Env) -> Address;
-    fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables) -> Result<(), Error>;
-    fn cancel(env: Env, immutables: Immutables) -> Result<(), Error>;
+    fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables) -> Result<PayoutSummary, Error>;
+    fn cancel(env: Env, immutables: Immutables) -> Result<PayoutSummary, Error>;
     fn rescue_funds(env: Env, token: DualAddress, amount: i128, immutables: Immutables) -> Result<(), Error>;
-    fn initialize(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables);
+    fn initialize(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Result<(), Error>;
 }
 
 // Modifier helpers
+/// Requires the auth of the order's taker, resolved through the address map
+#[cfg(feature = "storage")]
 pub fn only_taker(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-    let _stellar_taker = immutables::get_stellar_addr(env, &immutables.taker.evm)
+    let stellar_taker = immutables::get_stellar_addr(env, &immutables.taker.evm)
         .ok_or(Error::AddressMappingMissing)?;
-    // TODO: Add proper caller authentication
-    // if caller != stellar_taker {
-        // return Err(Error::InvalidCaller);
-        Ok(())
-    }
+    stellar_taker.require_auth();
+    Ok(())
+}
 
 
 pub fn only_valid_secret(env: &Env, secret: &BytesN<32>, immutables: &Immutables) -> Result<(), Error> {
     use soroban_sdk::Bytes;
     let secret_bytes = Bytes::from_array(env, &secret.to_array());
-    let computed_hash = env.crypto().keccak256(&secret_bytes);
-    let computed_hash_bytes: BytesN<32> = computed_hash.into();
-    if computed_hash_bytes != immutables.hashlock {
+    only_unlocking_hash(env.crypto().keccak256(&secret_bytes).into(), immutables)
+}
+
+/// Same check for a preimage of any length up to MAX_PREIMAGE_BYTES, for counterpart
+/// chains whose HTLC secrets aren't exactly 32 bytes
+pub fn only_valid_preimage(env: &Env, preimage: &Bytes, immutables: &Immutables) -> Result<(), Error> {
+    if preimage.is_empty() || preimage.len() > MAX_PREIMAGE_BYTES {
+        return Err(Error::InvalidPreimageLength);
+    }
+    only_unlocking_hash(env.crypto().keccak256(preimage).into(), immutables)
+}
+
+fn only_unlocking_hash(computed_hash: BytesN<32>, immutables: &Immutables) -> Result<(), Error> {
+    if computed_hash == immutables.hashlock {
+        return Ok(());
+    }
+    // Any of the pre-committed backup hashlocks is accepted as well
+    if immutables.hashlocks.contains(&computed_hash) {
+        return Ok(());
+    }
+    Err(Error::InvalidSecret)
+}
+
+/// Check the pre-image of the cooperative cancel hashlock, orders without one can't be cancelled early
+pub fn only_valid_cancel_secret(env: &Env, secret: &BytesN<32>, immutables: &Immutables) -> Result<(), Error> {
+    use soroban_sdk::Bytes;
+    let cancel_hashlock = immutables.cancel_hashlock.as_ref().ok_or(Error::InvalidSecret)?;
+    let computed_hash: BytesN<32> = env.crypto().keccak256(&Bytes::from_array(env, &secret.to_array())).into();
+    if &computed_hash != cancel_hashlock {
         return Err(Error::InvalidSecret);
     }
     Ok(())
 }
 
+/// Store a secret revealed by a successful withdraw under its hashlock, so the counterpart
+/// leg can be completed without parsing events
+#[cfg(feature = "storage")]
+pub fn record_revealed_secret(env: &Env, secret: &BytesN<32>) {
+    use soroban_sdk::Bytes;
+    let hashlock: BytesN<32> = env.crypto().keccak256(&Bytes::from_array(env, &secret.to_array())).into();
+    env.storage().persistent().set(&DataKey::RevealedSecret(hashlock), secret);
+}
+
+#[cfg(feature = "storage")]
+pub fn get_revealed_secret(env: &Env, hashlock: &BytesN<32>) -> Option<BytesN<32>> {
+    env.storage().persistent().get(&DataKey::RevealedSecret(hashlock.clone()))
+}
+
+/// record_revealed_secret for a preimage of any length, 32-byte ones stay readable
+/// through get_revealed_secret
+#[cfg(feature = "storage")]
+pub fn record_revealed_preimage(env: &Env, preimage: &Bytes) {
+    match BytesN::<32>::try_from(preimage.clone()) {
+        Ok(secret) => record_revealed_secret(env, &secret),
+        Err(_) => {
+            let hashlock: BytesN<32> = env.crypto().keccak256(preimage).into();
+            env.storage().persistent().set(&DataKey::RevealedPreimage(hashlock), preimage);
+        }
+    }
+}
+
+/// Preimage revealed for a hashlock, whatever its length
+#[cfg(feature = "storage")]
+pub fn get_revealed_preimage(env: &Env, hashlock: &BytesN<32>) -> Option<Bytes> {
+    match get_revealed_secret(env, hashlock) {
+        Some(secret) => Some(secret.into()),
+        None => env.storage().persistent().get(&DataKey::RevealedPreimage(hashlock.clone())),
+    }
+}
+
+/// Stage of a standalone escrow, what the factory keeps in its status record
+#[cfg(feature = "storage")]
+pub fn get_stage(env: &Env) -> EscrowStage {
+    env.storage().persistent().get(&DataKey::Stage).unwrap_or(EscrowStage::Created)
+}
+
+/// Reject an action the escrow's stage doesn't allow, checked before any funds move so a
+/// settled escrow can't pay out again from whatever balance it still holds
+#[cfg(feature = "storage")]
+pub fn only_stage_allows(env: &Env, to: &EscrowStage) -> Result<(), Error> {
+    statemachine::check_transition(&get_stage(env), to)
+}
+
+/// Move a standalone escrow to its next stage and emit the lifecycle event
+#[cfg(feature = "storage")]
+pub fn advance_stage(env: &Env, order_hash: &BytesN<32>, to: EscrowStage, amount: i128) -> Result<(), Error> {
+    statemachine::transition(env, order_hash, get_stage(env), to.clone(), amount)?;
+    env.storage().persistent().set(&DataKey::Stage, &to);
+    Ok(())
+}
+
+/// keccak256 of the whole immutables record. Unlike the Solidity-compatible hash it also
+/// covers the Stellar addresses and the Stellar-only parameters
+pub fn binding_hash(env: &Env, immutables: &Immutables) -> BytesN<32> {
+    env.crypto().keccak256(&immutables.clone().to_xdr(env)).into()
+}
+
+/// Reject immutables other than the ones the escrow was initialized with
+#[cfg(feature = "storage")]
+pub fn only_bound_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+    let bound: BytesN<32> = env.storage().persistent()
+        .get(&DataKey::ImmutablesHash)
+        .ok_or(Error::ImmutablesMismatch)?;
+    if binding_hash(env, immutables) != bound {
+        return Err(Error::ImmutablesMismatch);
+    }
+    Ok(())
+}
+
 pub fn only_after(env: &Env, start: u64) -> Result<(), Error> {
     if env.ledger().timestamp() < start {
         return Err(Error::InvalidTime);
@@ -71,10 +246,23 @@ pub fn only_before(env: &Env, stop: u64) -> Result<(), Error> {
     Ok(())
 }
 
+/// only_after for a start derived from another chain's clock, passing up to `tolerance`
+/// seconds early to absorb close-time skew. Only for call sites that opt in explicitly
+pub fn only_after_within(env: &Env, start: u64, tolerance: u64) -> Result<(), Error> {
+    only_after(env, start.saturating_sub(tolerance))
+}
+
+/// only_before that keeps passing up to `tolerance` seconds after the stop, see only_after_within
+pub fn only_before_within(env: &Env, stop: u64, tolerance: u64) -> Result<(), Error> {
+    only_before(env, stop.saturating_add(tolerance))
+}
+
 // BaseEscrow shared implementation
+#[cfg(feature = "storage")]
 pub struct BaseEscrow;
 
 // Partial implementation
+#[cfg(feature = "storage")]
 impl BaseEscrowTrait for BaseEscrow {
     fn rescue_delay(env: Env) -> u64 {
         env.storage()
@@ -93,8 +281,9 @@ impl BaseEscrowTrait for BaseEscrow {
     fn rescue_funds(env: Env, token: DualAddress, amount: i128, immutables: Immutables) -> Result<(), Error> {
         only_taker(&env, &immutables)?;
         validate_immutables(&env, &immutables)?;
+        only_bound_immutables(&env, &immutables)?;
         let rescue_start = timelocks::rescue_start(&immutables.timelocks, &env, Self::rescue_delay(env.clone())).map_err(|_| Error::TimeLockError)?;
-        only_after(&env, rescue_start)?;
+        only_after(&env, rescue_start).map_err(|_| Error::RescueNotStarted)?;
         let stellar_token = immutables::get_stellar_addr(&env, &token.evm)
             .ok_or(Error::AddressMappingMissing)?;
         if stellar_token != immutables.token.stellar {
@@ -103,43 +292,56 @@ impl BaseEscrowTrait for BaseEscrow {
         let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm)
             .ok_or(Error::AddressMappingMissing)?;
         uni_transfer(&env, &stellar_token, &stellar_taker, amount)?;
-        env.events().publish((symbol_short!("FundsSave"), stellar_token), amount);
+        events::publish(&env, (symbol_short!("FundsSave"), stellar_token), amount);
         Ok(())
     }
 
-    fn initialize(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) {
-        validate_immutables(&env, &immutables).expect("Invalid immutables");
+    fn initialize(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Result<(), Error> {
+        // Re-running init would let anyone replace the factory, delay and immutables of a funded escrow
+        if env.storage().persistent().has(&DataKey::Initialized) {
+            return Err(Error::AlreadyInitialized);
+        }
+        validate_immutables(&env, &immutables)?;
         // Map EVM addresses to Stellar addresses (provided by bridge or relayer)
-        immutables::map_evm_to_stellar(&env, immutables.maker.evm.clone(), immutables.maker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.taker.evm.clone(), immutables.taker.stellar.clone());
-        immutables::map_evm_to_stellar(&env, immutables.token.evm.clone(), immutables.token.stellar.clone());
+        for party in [&immutables.maker, &immutables.taker, &immutables.token] {
+            immutables::map_and_log(&env, party.evm.clone(), party.stellar.clone(), &factory, Some(&immutables.order_hash))?;
+        }
 
         env.storage().persistent().set(&DataKey::Factory, &factory);
         env.storage().persistent().set(&DataKey::RescueDelay, &rescue_delay);
         env.storage().persistent().set(&DataKey::Immutables, &immutables);
+        // Later calls pass the immutables again and must pass exactly these
+        env.storage().persistent().set(&DataKey::ImmutablesHash, &binding_hash(&env, &immutables));
+        env.storage().persistent().set(&DataKey::Initialized, &true);
+        env.storage().persistent().set(&DataKey::Stage, &EscrowStage::Created);
         timelocks::store_timelocks(&env, &immutables.timelocks);
+        statemachine::emit_stage(&env, &immutables.order_hash, EscrowStage::Created, immutables.amount);
+        Ok(())
     }
 
-    fn withdraw(_env: Env, _secret: BytesN<32>, _immutables: Immutables) -> Result<(), Error> {
+    fn withdraw(_env: Env, _secret: BytesN<32>, _immutables: Immutables) -> Result<PayoutSummary, Error> {
         panic!("withdraw must be implemented by derived contract")
     }
 
-    fn cancel(_env: Env, _immutables: Immutables) -> Result<(), Error> {
+    fn cancel(_env: Env, _immutables: Immutables) -> Result<PayoutSummary, Error> {
         panic!("cancel must be implemented by derived contract")
     }
 }
 
 // Abstract validate_immutables
+#[cfg(feature = "storage")]
 fn validate_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
     // Basic validation
-    if immutables.amount <= 0 || immutables.safety_deposit <= 0 {
+    immutables.checked_amount()?;
+    immutables.checked_safety_deposit()?;
+    immutables.check_evm_addresses(false)?;
+    // Every party is resolved through its EVM mapping, Stellar-origin orders go through the factory
+    if immutables.stellar_origin {
         return Err(Error::InvalidImmutables);
     }
     timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
-    // Hash validation (to be extended by derived contracts)
-    if immutables::hash(env, immutables).map_err(|_| Error::TimeLockError)? != immutables.order_hash {
-        return Err(Error::InvalidImmutables);
-    }
+    // The immutables must encode, later calls are checked against their binding hash
+    immutables::hash(env, immutables).map_err(|_| Error::TimeLockError)?;
     Ok(())
 }
 
@@ -148,10 +350,7 @@ pub fn uni_transfer(env: &Env, token: &Address, to: &Address, amount: i128) -> R
     if amount <= 0 {
         return Ok(());
     }
-    // For token contracts (not native tokens)
-    {
-        let token_client = token::Client::new(env, token);
-        token_client.transfer(&env.current_contract_address(), to, &amount);
-    }
+    // Same call for Soroban tokens, wrapped classic assets and XLM
+    Asset::from_address(env, token).transfer(&env.current_contract_address(), to, amount);
     Ok(())
 }
\ No newline at end of file
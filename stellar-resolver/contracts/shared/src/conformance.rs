@@ -0,0 +1,262 @@
+#![cfg(test)]
+
+//! Cross-chain conformance vectors for `immutables::hash` and `order::hash`.
+//!
+//! Each expected hash is `ImmutablesLib.hashMem` from the 1inch cross-chain-swap
+//! contracts, i.e. keccak256 over the 0x100 byte `abi.encode` of
+//! `IBaseEscrow.Immutables` (orderHash, hashlock, maker, taker, token, amount,
+//! safetyDeposit, timelocks), with the timelocks built by `TimelocksSettersLib.init`.
+//! They come from Solidity, never from this crate; regenerate them with
+//!
+//! ```text
+//! cd evm-cross-chain-resolver && forge script contracts/script/ImmutablesVectors.s.sol
+//! ```
+//!
+//! which prints the hashes of the same vectors in the same order. A mismatch means the
+//! Stellar packing drifted from EVM.
+
+use super::*;
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{Address, Bytes, BytesN, Env, Vec};
+
+extern crate std;
+
+struct Vector {
+    order_hash: &'static str,
+    hashlock: &'static str,
+    maker: &'static str,
+    taker: &'static str,
+    token: &'static str,
+    amount: i128,
+    safety_deposit: i128,
+    deployed_at: u32,
+    offsets: [u32; 7],
+    expected: &'static str,
+}
+
+const VECTORS: [Vector; 3] = [
+    // Small round numbers
+    Vector {
+        order_hash: "1111111111111111111111111111111111111111111111111111111111111111",
+        hashlock: "2222222222222222222222222222222222222222222222222222222222222222",
+        maker: "3333333333333333333333333333333333333333",
+        taker: "4444444444444444444444444444444444444444",
+        token: "5555555555555555555555555555555555555555",
+        amount: 1000,
+        safety_deposit: 100,
+        deployed_at: 1000,
+        offsets: [100, 200, 300, 400, 150, 250, 350],
+        expected: "2b88c5ab1580f593f913792c2de87d3199198483fca3253aa6ee676b526e0ce1",
+    },
+    // Mainnet-like order: USDC-sized addresses, 18 decimal amounts, 2025 deployment
+    Vector {
+        order_hash: "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        hashlock: "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f",
+        maker: "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        taker: "d8da6bf26964af9d7eed9e03e53415d37aa96045",
+        token: "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+        amount: 1_000_000_000_000_000_000_000_000,
+        safety_deposit: 100_000_000_000_000_000,
+        deployed_at: 1_735_689_600,
+        offsets: [300, 600, 1800, 3600, 240, 540, 1500],
+        expected: "780c7df03aa58925e16dd9790b81a84302de38ba1aeb807dc9c6447cb4a1ff6f",
+    },
+    // Every field at its widest
+    Vector {
+        order_hash: "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        hashlock: "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee",
+        maker: "ffffffffffffffffffffffffffffffffffffffff",
+        taker: "ffffffffffffffffffffffffffffffffffffffff",
+        token: "ffffffffffffffffffffffffffffffffffffffff",
+        amount: i128::MAX,
+        safety_deposit: 1,
+        deployed_at: u32::MAX,
+        offsets: [u32::MAX; 7],
+        expected: "e021982b38a32cc860420fa11b7fabb9d568f8e34af7653a85cd0b366ede2751",
+    },
+];
+
+fn decode<const N: usize>(hex: &str) -> [u8; N] {
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    out
+}
+
+fn dual(env: &Env, hex: &str) -> DualAddress {
+    DualAddress {
+        evm: BytesN::from_array(env, &decode::<20>(hex)),
+        stellar: Address::generate(env),
+    }
+}
+
+fn build(env: &Env, vector: &Vector) -> Immutables {
+    let o = vector.offsets;
+    Immutables {
+        order_hash: BytesN::from_array(env, &decode::<32>(vector.order_hash)),
+        hashlock: BytesN::from_array(env, &decode::<32>(vector.hashlock)),
+        hashlocks: Vec::new(env),
+        cancel_hashlock: None,
+        sponsor: None,
+        maker: dual(env, vector.maker),
+        taker: dual(env, vector.taker),
+        token: dual(env, vector.token),
+        amount: vector.amount,
+        safety_deposit: vector.safety_deposit,
+        timelocks: Timelocks::new(env, vector.deployed_at, o[0], o[1], o[2], o[3], o[4], o[5], o[6]),
+        expiration: 0,
+        rescue_delay: 0,
+        finality_lag: 0,
+        src_chain_id: 0,
+        dst_chain_id: 0,
+        parts: Vec::new(env),
+        native_deposit: false,
+        deposit_decay_bps: 0,
+        stellar_origin: false,
+        lop_order: LopOrder::Unrecorded,
+        attestation_required: false,
+        parent: None,
+    }
+}
+
+#[test]
+fn test_hash_matches_solidity_vectors() {
+    let env = Env::default();
+    for (i, vector) in VECTORS.iter().enumerate() {
+        let immutables = build(&env, vector);
+        let hash = other_immutables::hash(&env, &immutables).unwrap();
+        assert_eq!(hash.to_array(), decode::<32>(vector.expected), "vector {} drifted from Solidity", i);
+    }
+}
+
+#[test]
+fn test_stellar_only_fields_keep_solidity_hash() {
+    let env = Env::default();
+    let vector = &VECTORS[0];
+    let mut immutables = build(&env, vector);
+
+    // Stellar addresses, expiration and rescue delay never reach the EVM hash
+    immutables.maker.stellar = Address::generate(&env);
+    immutables.expiration = 5000;
+    immutables.rescue_delay = 172_800;
+
+    let hash = other_immutables::hash(&env, &immutables).unwrap();
+    assert_eq!(hash.to_array(), decode::<32>(vector.expected));
+
+    // A cancel hashlock is committed to, so it moves the order off the Solidity hash
+    immutables.cancel_hashlock = Some(BytesN::from_array(&env, &[0x42; 32]));
+    let hash = other_immutables::hash(&env, &immutables).unwrap();
+    assert_ne!(hash.to_array(), decode::<32>(vector.expected));
+}
+
+#[test]
+fn test_encoding_is_abi_encode_layout() {
+    let env = Env::default();
+    let vector = &VECTORS[0];
+    let immutables = build(&env, vector);
+
+    // Eight 32-byte words, addresses left-padded to a full word
+    let encoded = other_immutables::encode(&env, &immutables).unwrap();
+    assert_eq!(encoded.len(), 0x100);
+    let word = |i: u32| encoded.slice(i * 32..(i + 1) * 32);
+    assert_eq!(word(0), Bytes::from_array(&env, &decode::<32>(vector.order_hash)));
+    let mut maker = [0u8; 32];
+    maker[12..].copy_from_slice(&decode::<20>(vector.maker));
+    assert_eq!(word(2), Bytes::from_array(&env, &maker));
+    assert_eq!(word(7), Bytes::from_array(&env, &immutables.timelocks.to_bytes(&env).unwrap()));
+
+    let digest: BytesN<32> = env.crypto().keccak256(&encoded).into();
+    assert_eq!(digest.to_array(), decode::<32>(vector.expected));
+}
+
+#[test]
+fn test_order_hash_matches_abi_encode() {
+    let env = Env::default();
+    let fields = crate::OrderFields {
+        maker: BytesN::from_array(&env, &[0x11; 20]),
+        taker: BytesN::from_array(&env, &[0x22; 20]),
+        maker_asset: BytesN::from_array(&env, &[0x33; 20]),
+        taker_asset: BytesN::from_array(&env, &[0x44; 20]),
+        making_amount: 1000,
+        taking_amount: 2500,
+        salt: BytesN::from_array(&env, &[0x55; 32]),
+        src_chain_id: 1,
+        dst_chain_id: 0x5354454c4c4152,
+    };
+    // keccak256(abi.encode(maker, taker, makerAsset, takerAsset, makingAmount, takingAmount, salt, srcChainId, dstChainId))
+    let expected = "9b2ce197d1a89a61c16a86dd2de7a1046460e9a301bfc9a29addb15463277557";
+    let hash = crate::order::hash(&env, &fields).unwrap();
+    assert_eq!(hash.to_array(), decode::<32>(expected));
+
+    let mut negative = fields.clone();
+    negative.taking_amount = -1;
+    assert_eq!(crate::order::hash(&env, &negative), Err(EscrowError::InvalidImmutables));
+}
+
+#[test]
+fn test_chain_ids_bind_the_hash() {
+    let env = Env::default();
+    let vector = &VECTORS[0];
+    let mut immutables = build(&env, vector);
+    immutables.src_chain_id = 1;
+    immutables.dst_chain_id = 0x5354454c4c4152;
+
+    // The presence word and two chain id words after the Solidity layout
+    let encoded = other_immutables::encode(&env, &immutables).unwrap();
+    assert_eq!(encoded.len(), 0x160);
+    let hash = other_immutables::hash(&env, &immutables).unwrap();
+    assert_ne!(hash.to_array(), decode::<32>(vector.expected));
+
+    // The same order on another chain pair hashes differently
+    let mut replayed = immutables.clone();
+    replayed.src_chain_id = 56;
+    assert_ne!(other_immutables::hash(&env, &replayed).unwrap(), hash);
+}
+
+#[test]
+fn test_withdrawal_parts_bind_the_hash() {
+    let env = Env::default();
+    let vector = &VECTORS[0];
+    let mut immutables = build(&env, vector);
+    let part = |byte: u8, amount: i128| crate::WithdrawalPart {
+        hashlock: BytesN::from_array(&env, &[byte; 32]),
+        amount,
+    };
+    immutables.parts = Vec::from_array(&env, [part(0x01, 40), part(0x02, 60)]);
+
+    // The presence word and one word committing to the schedule
+    let encoded = other_immutables::encode(&env, &immutables).unwrap();
+    assert_eq!(encoded.len(), 0x140);
+    let hash = other_immutables::hash(&env, &immutables).unwrap();
+    assert_ne!(hash.to_array(), decode::<32>(vector.expected));
+
+    // Moving amount between parts changes the hash
+    let mut reshuffled = immutables.clone();
+    reshuffled.parts = Vec::from_array(&env, [part(0x01, 60), part(0x02, 40)]);
+    assert_ne!(other_immutables::hash(&env, &reshuffled).unwrap(), hash);
+}
+
+#[test]
+fn test_extensions_cant_pass_for_one_another() {
+    let env = Env::default();
+    let vector = &VECTORS[0];
+    let backup = BytesN::from_array(&env, &[0x42; 32]);
+
+    // Without the presence word both would append the same keccak256(backup) word
+    let mut with_backup = build(&env, vector);
+    with_backup.hashlocks = Vec::from_array(&env, [backup.clone()]);
+    let mut with_cancel = build(&env, vector);
+    with_cancel.cancel_hashlock = Some(env.crypto().keccak256(&Bytes::from_array(&env, &backup.to_array())).into());
+    assert_ne!(
+        other_immutables::hash(&env, &with_backup).unwrap(),
+        other_immutables::hash(&env, &with_cancel).unwrap()
+    );
+
+    // The presence word flags which extensions follow
+    let encoded = other_immutables::encode(&env, &with_cancel).unwrap();
+    assert_eq!(encoded.len(), 0x140);
+    let mut flags = [0u8; 32];
+    flags[31] = crate::layout::EXT_CANCEL_HASHLOCK as u8;
+    assert_eq!(encoded.slice(0x100..0x120), Bytes::from_array(&env, &flags));
+}
@@ -0,0 +1,71 @@
+use soroban_sdk::{crypto::Hash, Bytes, BytesN, Env};
+
+use crate::baseescrow::Error;
+
+/// Prefix EVM wallets prepend before signing with `personal_sign` (EIP-191)
+const EIP191_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+
+/// keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)
+pub fn eth_signed_message_hash(env: &Env, message: &Bytes) -> Hash<32> {
+    let mut bytes = Bytes::from_slice(env, EIP191_PREFIX);
+
+    // Message length as ASCII decimal, as personal_sign does
+    let mut len = message.len();
+    let mut digits = [0u8; 10];
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (len % 10) as u8;
+        len /= 10;
+        if len == 0 {
+            break;
+        }
+    }
+    bytes.extend_from_slice(&digits[start..]);
+    bytes.append(message);
+
+    env.crypto().keccak256(&bytes)
+}
+
+/// Recover the 20-byte EVM address that produced a 65-byte (r || s || v) signature over `digest`
+pub fn recover_evm_address(env: &Env, digest: &Hash<32>, signature: &BytesN<65>) -> Result<BytesN<20>, Error> {
+    let sig = signature.to_array();
+
+    // Accept both the legacy 27/28 and the raw 0/1 recovery ids
+    let recovery_id = match sig[64] {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        _ => return Err(Error::InvalidSignature),
+    };
+    let mut rs = [0u8; 64];
+    rs.copy_from_slice(&sig[..64]);
+
+    // Uncompressed public key: 0x04 || X (32) || Y (32)
+    let public_key = env
+        .crypto()
+        .secp256k1_recover(digest, &BytesN::from_array(env, &rs), recovery_id)
+        .to_array();
+    let key_hash: BytesN<32> = env
+        .crypto()
+        .keccak256(&Bytes::from_slice(env, &public_key[1..]))
+        .into();
+
+    // Address is the last 20 bytes of keccak256(X || Y)
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&key_hash.to_array()[12..]);
+    Ok(BytesN::from_array(env, &address))
+}
+
+/// Check that `signature` is an EIP-191 signature of `message` by `expected`
+pub fn verify_evm_signature(
+    env: &Env,
+    message: &Bytes,
+    signature: &BytesN<65>,
+    expected: &BytesN<20>,
+) -> Result<(), Error> {
+    let digest = eth_signed_message_hash(env, message);
+    if recover_evm_address(env, &digest, signature)? != *expected {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(())
+}
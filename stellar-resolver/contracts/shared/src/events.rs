@@ -0,0 +1,29 @@
+//! Event publishing with an optional deployment tag.
+//!
+//! Several factories on one network emit the same topics. A contract can store a tag
+//! symbol under EVENT_TAG_KEY in its instance storage; every event published through
+//! `publish` then carries it as an extra first topic, so shared indexers can filter per
+//! deployment. Without a tag the topics are exactly the ones given.
+
+use soroban_sdk::{symbol_short, Env, IntoVal, Symbol, Val, Vec};
+
+/// Instance storage key of the deployment tag
+pub const EVENT_TAG_KEY: Symbol = symbol_short!("evt_tag");
+
+/// The deployment tag, None when the contract has none
+pub fn tag(env: &Env) -> Option<Symbol> {
+    env.storage().instance().get(&EVENT_TAG_KEY)
+}
+
+/// Publish an event, with the deployment tag prepended to its topics when one is set
+pub fn publish<T, D>(env: &Env, topics: T, data: D)
+where
+    T: IntoVal<Env, Vec<Val>>,
+    D: IntoVal<Env, Val>,
+{
+    let mut topics: Vec<Val> = topics.into_val(env);
+    if let Some(tag) = tag(env) {
+        topics.push_front(tag.into_val(env));
+    }
+    env.events().publish(topics, data);
+}
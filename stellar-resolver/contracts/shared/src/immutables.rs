@@ -1,7 +1,16 @@
-use soroban_sdk::{contracttype, Address, Bytes, BytesN, Env, Map};
+use soroban_sdk::{contracttype, symbol_short, xdr::ToXdr, Address, Bytes, BytesN, Env, Symbol, Vec, U256};
+#[cfg(feature = "storage")]
+use soroban_sdk::Map;
 
-use crate::timelock::Timelocks;
+use crate::amount::{self, EscrowAmount};
+use crate::asset::Asset;
+use crate::layout;
+use crate::order::OrderId;
+use crate::timelock::{Timelocks, TimelocksRepr};
+use crate::baseescrow::Error;
 use crate::types::TimeLockError;
+#[cfg(feature = "storage")]
+use crate::events;
 
 // Dual-address struct for cross-chain compatibility
 #[contracttype]
@@ -11,18 +20,126 @@ pub struct DualAddress {
     pub stellar: Address,   // Stellar 32-byte address
 }
 
+impl DualAddress {
+    /// All-zero EVM side, address(0) in Solidity
+    pub fn is_zero_evm(&self) -> bool {
+        self.evm.to_array() == [0u8; 20]
+    }
+}
+
+// One part of a partial withdrawal schedule: the secret behind hashlock unlocks exactly amount
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawalPart {
+    pub hashlock: BytesN<32>,
+    pub amount: i128,
+}
+
+// Fields of the 1inch Limit Order Protocol order an EVM-originated escrow was filled from,
+// kept so the order can be reconstructed losslessly for audits and disputes
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LopOrderFields {
+    pub salt: U256,          // Order salt
+    pub maker_traits: U256,  // MakerTraits bit field
+    pub receiver: BytesN<20>, // Receiver of the taker asset, address(0) = the maker
+}
+
+// LOP order an escrow records, an enum rather than an Option as contracttype fields can't
+// hold an Option of a struct
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LopOrder {
+    Unrecorded,
+    Recorded(LopOrderFields),
+}
+
 // Updated Immutables struct
 #[contracttype]
 #[derive(Clone)]
 pub struct Immutables {
     pub order_hash: BytesN<32>,     // keccak256 hash of fields or contract address
     pub hashlock: BytesN<32>,       // keccak256 hash of secret
+    pub hashlocks: Vec<BytesN<32>>, // Backup hashlocks, any one of them also unlocks the escrow
+    pub cancel_hashlock: Option<BytesN<32>>, // keccak256 of a cancel secret allowing early cooperative cancel
+    pub sponsor: Option<Address>,   // Third party providing the safety deposit, refunded to it on cancel
     pub maker: DualAddress,         // Maker addresses (EVM and Stellar)
     pub taker: DualAddress,         // Taker addresses
     pub token: DualAddress,         // Token contract addresses
-    pub amount: i128,               // Token amount (signed, validated positive)
-    pub safety_deposit: i128,       // Safety deposit (signed, validated positive)
+    pub amount: i128,               // Token amount, see checked_amount
+    pub safety_deposit: i128,       // Safety deposit, see checked_safety_deposit
     pub timelocks: Timelocks,       // Time constraints
+    pub expiration: u64,            // Absolute expiry timestamp, 0 = never (Stellar-only, not hashed)
+    pub rescue_delay: u64,          // Rescue delay override, 0 = factory default (Stellar-only, not hashed)
+    pub finality_lag: u64,          // Extra wait after the withdrawal stage opens, 0 = factory default (Stellar-only, not hashed)
+    pub src_chain_id: u64,          // Chain holding the source escrow, 0 = unbound (hashed when either id is set)
+    pub dst_chain_id: u64,          // Chain holding the destination escrow, 0 = unbound
+    pub parts: Vec<WithdrawalPart>, // Per-part withdrawal schedule summing to amount, empty = one withdrawal (hashed when set)
+    pub native_deposit: bool,       // Safety deposit held and paid in XLM instead of the swap token (hashed when set)
+    pub deposit_decay_bps: u32,     // Safety deposit share forfeited by withdrawing at the end of the window, 0 = factory default (Stellar-only, not hashed)
+    pub stellar_origin: bool,       // Order placed on Stellar, see order::StellarOrderFields: the maker's EVM address is optional (hashed when set)
+    pub lop_order: LopOrder,        // 1inch LOP order fields of an EVM-originated order (hashed when recorded)
+    pub attestation_required: bool, // Withdrawal recipient must hold an attestation from the factory's attestor (Stellar-only, not hashed)
+    pub parent: Option<BytesN<32>>, // order_hash of the leg a multi-leg swap escrow depends on, withdrawable once it is (Stellar-only, not hashed)
+}
+
+impl Immutables {
+    /// The order amount, which must be positive
+    pub fn checked_amount(&self) -> Result<EscrowAmount, Error> {
+        EscrowAmount::new(self.amount)
+    }
+
+    /// The safety deposit, which escrows created on Stellar require to be positive
+    pub fn checked_safety_deposit(&self) -> Result<EscrowAmount, Error> {
+        EscrowAmount::new(self.safety_deposit)
+    }
+
+    /// Rejects zeroed EVM addresses: address(0) is no maker or taker, and as token it is
+    /// the EVM native token sentinel, only accepted when allow_native_token is set.
+    /// The maker of a Stellar-origin order may have no EVM address
+    pub fn check_evm_addresses(&self, allow_native_token: bool) -> Result<(), Error> {
+        if (self.maker.is_zero_evm() && !self.stellar_origin) || self.taker.is_zero_evm() {
+            return Err(Error::ZeroAddress);
+        }
+        if self.token.is_zero_evm() && !allow_native_token {
+            return Err(Error::ZeroAddress);
+        }
+        Ok(())
+    }
+
+    /// Id of the escrow's order, its origin given by stellar_origin
+    pub fn order_id(&self) -> OrderId {
+        match self.stellar_origin {
+            true => OrderId::StellarSha256(self.order_hash.clone()),
+            false => OrderId::EvmKeccak(self.order_hash.clone()),
+        }
+    }
+
+    /// Token the safety deposit is held and paid in: the native XLM SAC, which needs no
+    /// trustline, for native_deposit orders, the swap token otherwise
+    pub fn deposit_token(&self, env: &Env) -> Address {
+        if self.native_deposit {
+            Asset::native_address(env)
+        } else {
+            self.token.stellar.clone()
+        }
+    }
+}
+
+/// First topic of the event emitted when a mapping is created: ("AddrMap", evm)
+pub const ADDRESS_MAPPED_TOPIC: Symbol = symbol_short!("AddrMap");
+
+/// First topic of the event emitted when the admin points a mapping elsewhere: ("AddrRemap", evm)
+pub const ADDRESS_REMAPPED_TOPIC: Symbol = symbol_short!("AddrRemap");
+
+/// Payload of the address mapping event
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AddressMapped {
+    pub stellar: Address,
+    pub previous: Option<Address>,      // Stellar address the EVM address mapped to before (remaps only)
+    pub caller: Address,                // Party whose call created the mapping
+    pub order_hash: Option<BytesN<32>>, // Order whose creation set the mapping, None for direct registrations
 }
 
 // Storage for address mappings
@@ -33,22 +150,30 @@ pub enum DataKey {
 }
 
 // Immutables module
+#[allow(clippy::module_inception)]
 pub mod immutables {
     use super::*;
 
-    /// Validates that amounts are positive before processing
+    /// Validates that the amounts have an EVM encoding: a positive amount and a
+    /// non-negative safety deposit (EVM orders may carry none)
     pub fn validate_amounts(immutables: &Immutables) -> Result<(), TimeLockError> {
-        if immutables.amount <= 0 {
-            return Err(TimeLockError::DeploymentTimestampNotSet); // Using closest available error
-        }
-        if immutables.safety_deposit < 0 {
-            return Err(TimeLockError::DeploymentTimestampNotSet); // Using closest available error
-        }
+        // Using closest available error
+        immutables.checked_amount().map_err(|_| TimeLockError::DeploymentTimestampNotSet)?;
+        amount::to_u128(immutables.safety_deposit).map_err(|_| TimeLockError::DeploymentTimestampNotSet)?;
         Ok(())
     }
 
     /// Computes keccak256 hash of Immutables to match Solidity
     pub fn hash(env: &Env, immutables: &Immutables) -> Result<BytesN<32>, TimeLockError> {
+        let bytes = encode(env, immutables)?;
+        Ok(env.crypto().keccak256(&bytes).into())
+    }
+
+    /// ABI-style packing of Immutables that hash() commits to: eight 32-byte words
+    /// like Solidity's abi.encode in layout::IMMUTABLES_WORDS order, followed, when any
+    /// Stellar-only extension is present, by a word flagging which (layout::EXT_*) and
+    /// the extensions themselves
+    pub fn encode(env: &Env, immutables: &Immutables) -> Result<Bytes, TimeLockError> {
         // Validate amounts before processing
         validate_amounts(immutables)?;
 
@@ -63,42 +188,109 @@ pub mod immutables {
         // CRITICAL: Only include EVM addresses in hash for cross-chain compatibility
         // Solidity contracts only know about EVM addresses, not Stellar addresses
         
-        // maker: 20-byte EVM address, padded to 32 bytes
-        let mut maker_padded = [0u8; 32];
-        maker_padded[12..32].copy_from_slice(&immutables.maker.evm.to_array());
-        bytes.extend_from_array(&maker_padded);
-        
-        // taker: 20-byte EVM address, padded to 32 bytes
-        let mut taker_padded = [0u8; 32];
-        taker_padded[12..32].copy_from_slice(&immutables.taker.evm.to_array());
-        bytes.extend_from_array(&taker_padded);
-        
-        // token: 20-byte EVM address, padded to 32 bytes
-        let mut token_padded = [0u8; 32];
-        token_padded[12..32].copy_from_slice(&immutables.token.evm.to_array());
-        bytes.extend_from_array(&token_padded);
-        
-        // amount: i128 as 16-byte big-endian (validated positive)
-        let amount_bytes = (immutables.amount as u128).to_be_bytes();
-        let mut amount_padded = [0u8; 32];
-        amount_padded[16..32].copy_from_slice(&amount_bytes);
-        bytes.extend_from_array(&amount_padded);
+        // maker, taker, token: 20-byte EVM addresses, left-padded to a word
+        for party in [&immutables.maker, &immutables.taker, &immutables.token] {
+            bytes.extend_from_array(&layout::address_word(&party.evm.to_array()));
+        }
         
-        // safety_deposit: i128 as 16-byte big-endian (validated non-negative)
-        let deposit_bytes = (immutables.safety_deposit as u128).to_be_bytes();
-        let mut deposit_padded = [0u8; 32];
-        deposit_padded[16..32].copy_from_slice(&deposit_bytes);
-        bytes.extend_from_array(&deposit_padded);
+        // amount, safety_deposit: big-endian u128 words
+        for value in [immutables.amount, immutables.safety_deposit] {
+            let word = amount::to_u128(value).map_err(|_| TimeLockError::DeploymentTimestampNotSet)?;
+            bytes.extend_from_array(&layout::amount_word(word));
+        }
         
         // timelocks: 32 bytes packed (exactly matching Solidity uint256)
-        let timelocks_bytes = immutables.timelocks.to_bytes(env);
+        let timelocks_bytes = immutables.timelocks.to_packed_bytes(env)?;
         bytes.extend_from_array(&timelocks_bytes);
 
-        Ok(env.crypto().keccak256(&bytes).into())
+        // Stellar-only extensions are committed to only when present, so plain orders keep
+        // the exact Solidity hash. A presence word with one bit per extension leads them:
+        // without it a value of one extension could pass for another's and distinct
+        // immutables would hash the same
+        let mut flags = 0u32;
+        let mut extensions = Bytes::new(env);
+
+        // Backup hashlocks
+        if !immutables.hashlocks.is_empty() {
+            flags |= layout::EXT_HASHLOCKS;
+            let mut hashlocks_bytes = Bytes::new(env);
+            for hashlock in immutables.hashlocks.iter() {
+                hashlocks_bytes.extend_from_array(&hashlock.to_array());
+            }
+            let hashlocks_hash: BytesN<32> = env.crypto().keccak256(&hashlocks_bytes).into();
+            extensions.extend_from_array(&hashlocks_hash.to_array());
+        }
+
+        // The cooperative cancel hashlock
+        if let Some(cancel_hashlock) = &immutables.cancel_hashlock {
+            flags |= layout::EXT_CANCEL_HASHLOCK;
+            extensions.extend_from_array(&cancel_hashlock.to_array());
+        }
+
+        // The sponsor, which receives the safety deposit back on cancel
+        if let Some(sponsor) = &immutables.sponsor {
+            flags |= layout::EXT_SPONSOR;
+            extensions.append(&sponsor.clone().to_xdr(env));
+        }
+
+        // Chain ids bind the order to one pair of chains so it can't be replayed on another,
+        // as two 32-byte words
+        if immutables.src_chain_id != 0 || immutables.dst_chain_id != 0 {
+            flags |= layout::EXT_CHAIN_IDS;
+            for chain_id in [immutables.src_chain_id, immutables.dst_chain_id] {
+                extensions.extend_from_array(&layout::chain_id_word(chain_id));
+            }
+        }
+
+        // Partial withdrawal schedule, committed as keccak256 of (hashlock, amount word) pairs
+        if !immutables.parts.is_empty() {
+            flags |= layout::EXT_PARTS;
+            let mut parts_bytes = Bytes::new(env);
+            for part in immutables.parts.iter() {
+                parts_bytes.extend_from_array(&part.hashlock.to_array());
+                let part_amount = EscrowAmount::new(part.amount).map_err(|_| TimeLockError::DeploymentTimestampNotSet)?;
+                parts_bytes.extend_from_array(&layout::amount_word(part_amount.to_u128()));
+            }
+            let parts_hash: BytesN<32> = env.crypto().keccak256(&parts_bytes).into();
+            extensions.extend_from_array(&parts_hash.to_array());
+        }
+
+        // An XLM safety deposit changes what the deposit word means, so it's committed to
+        if immutables.native_deposit {
+            flags |= layout::EXT_NATIVE_DEPOSIT;
+            extensions.extend_from_array(&layout::bool_word(true));
+        }
+
+        // A Stellar-origin maker may have no EVM address, its Stellar address stands in
+        // for it as keccak256 of its XDR
+        if immutables.stellar_origin {
+            flags |= layout::EXT_STELLAR_ORIGIN;
+            let maker: BytesN<32> = env.crypto().keccak256(&immutables.maker.stellar.clone().to_xdr(env)).into();
+            extensions.extend_from_array(&maker.to_array());
+        }
+
+        // LOP order fields, committed as keccak256 of their abi.encode (salt, makerTraits, receiver)
+        if let LopOrder::Recorded(lop_order) = &immutables.lop_order {
+            flags |= layout::EXT_LOP_ORDER;
+            let mut lop_bytes = Bytes::new(env);
+            lop_bytes.append(&lop_order.salt.to_be_bytes());
+            lop_bytes.append(&lop_order.maker_traits.to_be_bytes());
+            lop_bytes.extend_from_array(&layout::address_word(&lop_order.receiver.to_array()));
+            let lop_hash: BytesN<32> = env.crypto().keccak256(&lop_bytes).into();
+            extensions.extend_from_array(&lop_hash.to_array());
+        }
+
+        if flags != 0 {
+            bytes.extend_from_array(&layout::extensions_word(flags));
+            bytes.append(&extensions);
+        }
+
+        Ok(bytes)
     }
 
 
     /// Maps EVM address to Stellar address
+    #[cfg(feature = "storage")]
     pub fn map_evm_to_stellar(env: &Env, evm_addr: BytesN<20>, stellar_addr: Address) {
         let mut map: Map<BytesN<20>, Address> = env
             .storage()
@@ -109,7 +301,51 @@ pub mod immutables {
         env.storage().persistent().set(&DataKey::AddressMap, &map);
     }
 
+    /// Maps EVM address to Stellar address and emits an AddressMapped event so indexers can
+    /// audit the association. Returns Ok(false), without writing or emitting, when it already
+    /// held. A different existing mapping is a MappingConflict, only an explicit remap may
+    /// point an EVM address elsewhere
+    #[cfg(feature = "storage")]
+    pub fn map_and_log(
+        env: &Env,
+        evm_addr: BytesN<20>,
+        stellar_addr: Address,
+        caller: &Address,
+        order_hash: Option<&BytesN<32>>,
+    ) -> Result<bool, Error> {
+        match get_stellar_addr(env, &evm_addr) {
+            Some(existing) if existing == stellar_addr => return Ok(false),
+            Some(_) => return Err(Error::MappingConflict),
+            None => {}
+        }
+        map_evm_to_stellar(env, evm_addr.clone(), stellar_addr.clone());
+        let event = AddressMapped {
+            stellar: stellar_addr,
+            previous: None,
+            caller: caller.clone(),
+            order_hash: order_hash.cloned(),
+        };
+        events::publish(env, (ADDRESS_MAPPED_TOPIC, evm_addr), event);
+        Ok(true)
+    }
+
+    /// Deliberately point an EVM address at another Stellar address, emitting an
+    /// AddressRemapped event. Callers must restrict this to the admin
+    #[cfg(feature = "storage")]
+    pub fn remap_and_log(env: &Env, evm_addr: BytesN<20>, stellar_addr: Address, caller: &Address) {
+        let previous = get_stellar_addr(env, &evm_addr);
+        map_evm_to_stellar(env, evm_addr.clone(), stellar_addr.clone());
+        let event = AddressMapped {
+            stellar: stellar_addr,
+            previous,
+            caller: caller.clone(),
+            order_hash: None,
+        };
+        events::publish(env, (ADDRESS_REMAPPED_TOPIC, evm_addr), event);
+    }
+
     /// Gets Stellar address for EVM address
+    #[cfg(feature = "storage")]
     pub fn get_stellar_addr(env: &Env, evm_addr: &BytesN<20>) -> Option<Address> {
         let map: Map<BytesN<20>, Address> = env
             .storage()
@@ -119,7 +355,30 @@ pub mod immutables {
         map.get(evm_addr.clone())
     }
 
+    /// Up to limit mappings in EVM address order, starting at the start-th one
+    #[cfg(feature = "storage")]
+    pub fn list_mappings(env: &Env, start: u32, limit: u32) -> Vec<DualAddress> {
+        let map: Map<BytesN<20>, Address> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::AddressMap)
+            .unwrap_or(Map::new(env));
+        let mut page = Vec::new(env);
+        for (evm, stellar) in map.iter().skip(start as usize).take(limit as usize) {
+            page.push_back(DualAddress { evm, stellar });
+        }
+        page
+    }
+
+    /// Number of EVM addresses with a mapping
+    #[cfg(feature = "storage")]
+    pub fn mapping_count(env: &Env) -> u32 {
+        let map: Option<Map<BytesN<20>, Address>> = env.storage().persistent().get(&DataKey::AddressMap);
+        map.map_or(0, |map| map.len())
+    }
+
     /// Stores Immutables in persistent storage
+    #[cfg(feature = "storage")]
     pub fn store_immutables(env: &Env, immutables: &Immutables) -> Result<(), TimeLockError> {
         validate_amounts(immutables)?;
         env.storage().persistent().set(&DataKey::ImmutablesData, immutables);
@@ -127,6 +386,7 @@ pub mod immutables {
     }
 
     /// Retrieves Immutables from persistent storage
+    #[cfg(feature = "storage")]
     pub fn get_immutables(env: &Env) -> Option<Immutables> {
         env.storage().persistent().get(&DataKey::ImmutablesData)
     }
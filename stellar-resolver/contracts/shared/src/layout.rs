@@ -0,0 +1,127 @@
+//! EVM parity layout: every constant the Solidity-compatible packing depends on.
+//!
+//! `timelock.rs` packs `Timelocks` into one uint256, `immutables.rs` and `order.rs`
+//! lay fields out like `abi.encode`. Both sides read their offsets, padding and field
+//! order from here, and the assertions at the bottom fail the build if the numbers stop
+//! adding up. Changing anything in this file changes hashes shared with the EVM side,
+//! the vectors in `conformance.rs` must keep passing.
+
+use crate::timelock::Stage;
+
+/// Size of an ABI word
+pub const WORD_BYTES: usize = 32;
+pub const WORD_BITS: u32 = 256;
+
+// ===== Timelocks (TimelocksLib.sol) =====
+
+/// Each timelock field is a uint32: deployed_at or a stage offset in seconds
+pub const TIMELOCK_FIELD_BITS: u32 = 32;
+pub const TIMELOCK_FIELD_BYTES: usize = 4;
+pub const STAGE_COUNT: u32 = 7;
+
+/// deployed_at occupies the most significant field, bits 224-255
+pub const DEPLOYED_AT_OFFSET: u32 = 224;
+/// First byte of deployed_at in the big-endian word
+pub const DEPLOYED_AT_BYTE_INDEX: usize = 0;
+
+/// Bit offset of a stage's field, `uint256(stage) * 32` like `TimelocksLib.get`:
+/// SrcWithdrawal at bits 0-31 up to DstCancellation at bits 192-223
+pub const fn stage_bit_offset(stage: Stage) -> u32 {
+    stage as u32 * TIMELOCK_FIELD_BITS
+}
+
+/// First byte of a stage's field in the big-endian word
+pub const fn stage_byte_index(stage: Stage) -> usize {
+    ((WORD_BITS - TIMELOCK_FIELD_BITS - stage_bit_offset(stage)) / 8) as usize
+}
+
+// ===== abi.encode words =====
+
+/// Addresses are the 20 raw bytes in their 0x-string order, left-padded with zeros
+pub const ADDRESS_BYTES: usize = 20;
+pub const ADDRESS_PADDING: usize = WORD_BYTES - ADDRESS_BYTES;
+
+/// Amounts are encoded as a big-endian u128, left-padded. `amount.rs` does the i128 conversion
+pub const AMOUNT_BYTES: usize = 16;
+pub const AMOUNT_PADDING: usize = WORD_BYTES - AMOUNT_BYTES;
+
+/// Chain ids are a big-endian u64, left-padded
+pub const CHAIN_ID_BYTES: usize = 8;
+pub const CHAIN_ID_PADDING: usize = WORD_BYTES - CHAIN_ID_BYTES;
+
+/// Field order of IBaseEscrow.Immutables, one word each. Stellar-only extensions follow
+/// them only when set, see `immutables::encode`
+pub const IMMUTABLES_WORDS: [&str; 8] = [
+    "orderHash", "hashlock", "maker", "taker", "token", "amount", "safetyDeposit", "timelocks",
+];
+pub const IMMUTABLES_ENCODED_BYTES: usize = 0x100;
+
+/// Stellar-only extensions of the immutables encoding, one bit each in the presence word
+/// that precedes them when any is set. The extensions follow in the order of their bits
+pub const EXT_HASHLOCKS: u32 = 1 << 0;
+pub const EXT_CANCEL_HASHLOCK: u32 = 1 << 1;
+pub const EXT_SPONSOR: u32 = 1 << 2;
+pub const EXT_CHAIN_IDS: u32 = 1 << 3;
+pub const EXT_PARTS: u32 = 1 << 4;
+pub const EXT_NATIVE_DEPOSIT: u32 = 1 << 5;
+pub const EXT_STELLAR_ORIGIN: u32 = 1 << 6;
+pub const EXT_LOP_ORDER: u32 = 1 << 7;
+
+/// Field order of the canonical order hash, one word each
+pub const ORDER_WORDS: [&str; 9] = [
+    "maker", "taker", "makerAsset", "takerAsset", "makingAmount", "takingAmount", "salt", "srcChainId", "dstChainId",
+];
+pub const ORDER_ENCODED_BYTES: usize = 0x120;
+
+pub fn address_word(address: &[u8; ADDRESS_BYTES]) -> [u8; WORD_BYTES] {
+    let mut word = [0u8; WORD_BYTES];
+    word[ADDRESS_PADDING..].copy_from_slice(address);
+    word
+}
+
+pub fn amount_word(amount: u128) -> [u8; WORD_BYTES] {
+    let mut word = [0u8; WORD_BYTES];
+    word[AMOUNT_PADDING..].copy_from_slice(&amount.to_be_bytes());
+    word
+}
+
+/// abi.encode of a bool: 0 or 1 in the last byte
+pub fn bool_word(flag: bool) -> [u8; WORD_BYTES] {
+    let mut word = [0u8; WORD_BYTES];
+    word[WORD_BYTES - 1] = flag as u8;
+    word
+}
+
+pub fn chain_id_word(chain_id: u64) -> [u8; WORD_BYTES] {
+    let mut word = [0u8; WORD_BYTES];
+    word[CHAIN_ID_PADDING..].copy_from_slice(&chain_id.to_be_bytes());
+    word
+}
+
+/// Presence word of the extensions: the flag bits as a big-endian u32, left-padded
+pub fn extensions_word(flags: u32) -> [u8; WORD_BYTES] {
+    let mut word = [0u8; WORD_BYTES];
+    word[WORD_BYTES - 4..].copy_from_slice(&flags.to_be_bytes());
+    word
+}
+
+// Layout checks, evaluated at compile time
+const _: () = {
+    assert!(WORD_BITS as usize == WORD_BYTES * 8);
+    assert!(TIMELOCK_FIELD_BITS as usize == TIMELOCK_FIELD_BYTES * 8);
+    // deployed_at plus the seven stages fill the word exactly, without overlap
+    assert!(DEPLOYED_AT_OFFSET + TIMELOCK_FIELD_BITS == WORD_BITS);
+    assert!((STAGE_COUNT + 1) * TIMELOCK_FIELD_BITS == WORD_BITS);
+    assert!(DEPLOYED_AT_BYTE_INDEX == ((WORD_BITS - TIMELOCK_FIELD_BITS - DEPLOYED_AT_OFFSET) / 8) as usize);
+    assert!(stage_bit_offset(Stage::SrcWithdrawal) == 0);
+    assert!(stage_bit_offset(Stage::DstCancellation) + TIMELOCK_FIELD_BITS == DEPLOYED_AT_OFFSET);
+    assert!(Stage::DstCancellation as u32 == STAGE_COUNT - 1);
+    assert!(stage_byte_index(Stage::SrcWithdrawal) == WORD_BYTES - TIMELOCK_FIELD_BYTES);
+    assert!(stage_byte_index(Stage::DstCancellation) == DEPLOYED_AT_BYTE_INDEX + TIMELOCK_FIELD_BYTES);
+    // Every value fits its word
+    assert!(ADDRESS_PADDING + ADDRESS_BYTES == WORD_BYTES);
+    assert!(AMOUNT_BYTES == core::mem::size_of::<u128>());
+    assert!(CHAIN_ID_BYTES == core::mem::size_of::<u64>());
+    assert!(IMMUTABLES_WORDS.len() * WORD_BYTES == IMMUTABLES_ENCODED_BYTES);
+    assert!(ORDER_WORDS.len() * WORD_BYTES == ORDER_ENCODED_BYTES);
+};
@@ -1,18 +1,46 @@
 #![no_std]
 
 // Shared library for cross-chain atomic swap contracts
-// Contains common types, timelock logic, immutables, and base escrow functionality
+// Contains common types, timelock logic and the action windows built on it, immutables, base escrow functionality, the escrow stage state machine, order hashing
+// the EVM parity layout both hashes follow and checked token amounts
+//
+// The packing, hashing and validation primitives (timelocks, hashlocks, dual addresses) only need an
+// Env and can be reused by other Soroban protocols. Helpers that read or write contract storage
+// (address map, stored timelocks and immutables, revealed secrets, BaseEscrow, TTL bumps) sit behind the
+// default "storage" feature, depend on shared with default-features = false to leave them out
 
 pub mod timelock;
+pub mod window;
 pub mod types;
 pub mod immutables;
 pub mod baseescrow;
+pub mod statemachine;
+pub mod permit;
+pub mod crypto;
+pub mod asset;
+pub mod order;
+pub mod layout;
+pub mod amount;
+pub mod events;
+#[cfg(feature = "storage")]
+pub mod ttl;
 
 // Re-export commonly used types for easier imports
 pub use types::*;
-pub use timelock::{timelocks, Stage, Timelocks};
-pub use immutables::{immutables as other_immutables, DualAddress, Immutables};
-pub use baseescrow::{BaseEscrowTrait, Error as EscrowError, only_taker, only_valid_secret, only_before, only_after, uni_transfer};
+pub use timelock::{timelocks, Stage, Timelocks, TimelocksRepr};
+pub use window::{EscrowType, in_private_withdraw_window, in_private_withdraw_window_within, in_withdraw_window, in_public_withdraw_window, in_cancel_window, in_public_cancel_window};
+pub use immutables::{immutables as other_immutables, AddressMapped, DualAddress, Immutables, LopOrder, LopOrderFields, WithdrawalPart};
+pub use statemachine::{EscrowStage, StageEvent};
+pub use permit::{Permit, PermitAction};
+pub use asset::Asset;
+pub use amount::EscrowAmount;
+pub use order::{OrderFields, OrderId, StellarOrderFields};
+pub use baseescrow::{BaseEscrowTrait, Error as EscrowError, PayoutSummary, only_valid_secret, only_valid_preimage, only_valid_cancel_secret, only_before, only_after, only_before_within, only_after_within, uni_transfer};
+#[cfg(feature = "storage")]
+pub use baseescrow::{only_taker, only_bound_immutables, record_revealed_secret, get_revealed_secret, record_revealed_preimage, get_revealed_preimage, get_stage, only_stage_allows, advance_stage};
 
 #[cfg(test)]
 mod test;
+
+#[cfg(test)]
+mod conformance;
@@ -0,0 +1,96 @@
+use soroban_sdk::{contracttype, xdr::ToXdr, Address, Bytes, BytesN, Env};
+
+use crate::amount;
+use crate::baseescrow::Error;
+use crate::layout;
+
+/// Fields that identify an order on both chains, addresses in their EVM form
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderFields {
+    pub maker: BytesN<20>,
+    pub taker: BytesN<20>,
+    pub maker_asset: BytesN<20>, // Token the maker gives up on the source chain
+    pub taker_asset: BytesN<20>, // Token the maker receives on the destination chain
+    pub making_amount: i128,
+    pub taking_amount: i128,
+    pub salt: BytesN<32>,        // Lets a maker place otherwise identical orders
+    pub src_chain_id: u64,
+    pub dst_chain_id: u64,
+}
+
+/// Fields of an order placed on Stellar, for swaps with Stellar as the source chain. The
+/// maker is a Stellar account authorizing its escrow itself, its EVM address is optional
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StellarOrderFields {
+    pub maker: Address,
+    pub maker_evm: Option<BytesN<20>>, // Maker's EVM address, if it has one
+    pub maker_asset: Address,          // Stellar token the maker gives up
+    pub taker_asset: BytesN<20>,       // EVM token the maker receives on the destination chain
+    pub receiver: BytesN<20>,          // EVM address receiving taker_asset
+    pub making_amount: i128,
+    pub taking_amount: i128,
+    pub salt: BytesN<32>,
+    pub src_chain_id: u64,
+    pub dst_chain_id: u64,
+}
+
+/// Identifier of an escrow's order: its order hash, tagged with how the order was hashed.
+/// Entry points keyed by order reject an id whose origin differs from the escrow's
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OrderId {
+    EvmKeccak(BytesN<32>),     // Order placed on EVM, see hash
+    StellarSha256(BytesN<32>), // Order placed on Stellar, see stellar_hash
+}
+
+impl OrderId {
+    /// The order hash escrows are stored under
+    pub fn hash(&self) -> BytesN<32> {
+        match self {
+            OrderId::EvmKeccak(hash) | OrderId::StellarSha256(hash) => hash.clone(),
+        }
+    }
+}
+
+/// Canonical order hash: keccak256 over nine 32-byte words, matching Solidity's
+/// keccak256(abi.encode(maker, taker, makerAsset, takerAsset, makingAmount,
+/// takingAmount, salt, srcChainId, dstChainId)), see layout::ORDER_WORDS
+pub fn hash(env: &Env, fields: &OrderFields) -> Result<BytesN<32>, Error> {
+    let mut bytes = Bytes::new(env);
+    for address in [&fields.maker, &fields.taker, &fields.maker_asset, &fields.taker_asset] {
+        bytes.extend_from_array(&layout::address_word(&address.to_array()));
+    }
+    for value in [fields.making_amount, fields.taking_amount] {
+        bytes.extend_from_array(&layout::amount_word(amount::to_u128(value)?));
+    }
+    bytes.extend_from_array(&fields.salt.to_array());
+    for chain_id in [fields.src_chain_id, fields.dst_chain_id] {
+        bytes.extend_from_array(&layout::chain_id_word(chain_id));
+    }
+
+    Ok(env.crypto().keccak256(&bytes).into())
+}
+
+/// Order hash of a Stellar-origin order: sha256 of the fields' XDR. Nothing on EVM has
+/// to recompute it, the destination escrow only carries it
+pub fn stellar_hash(env: &Env, fields: &StellarOrderFields) -> BytesN<32> {
+    env.crypto().sha256(&fields.clone().to_xdr(env)).into()
+}
+
+/// Domain separator of one factory instance: keccak256(factory address XDR || network id),
+/// the network id being sha256 of the network passphrase
+pub fn domain_separator(env: &Env, factory: &Address, network_id: &BytesN<32>) -> BytesN<32> {
+    let mut bytes = factory.clone().to_xdr(env);
+    bytes.extend_from_array(&network_id.to_array());
+    env.crypto().keccak256(&bytes).into()
+}
+
+/// Escrow key: keccak256(domain separator || order_hash). The same order_hash seen by two
+/// factories (or networks) gives two different keys
+pub fn escrow_key(env: &Env, domain_separator: &BytesN<32>, order_hash: &BytesN<32>) -> BytesN<32> {
+    let mut bytes = Bytes::from_array(env, &domain_separator.to_array());
+    bytes.extend_from_array(&order_hash.to_array());
+    env.crypto().keccak256(&bytes).into()
+}
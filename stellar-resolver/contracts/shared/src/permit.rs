@@ -0,0 +1,73 @@
+use soroban_sdk::{contracttype, xdr::ToXdr, Address, Bytes, BytesN, Env};
+
+use crate::baseescrow::Error;
+
+/// Actions a maker can authorize off-chain for a relayer to submit
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum PermitAction {
+    Cancel = 0,
+}
+
+/// Off-chain signed authorization for a single action on a single order
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Permit {
+    pub action: PermitAction,
+    pub nonce: u64,             // Must equal the signer's next expected nonce
+    pub expiry: u64,            // Ledger timestamp after which the permit is void
+    pub signature: BytesN<64>,  // ed25519 signature over the canonical payload
+}
+
+/// Canonical payload signed by the maker:
+/// contract address XDR || order_hash (32) || action (4, BE) || nonce (8, BE) || expiry (8, BE)
+///
+/// The contract address binds the permit to one deployment so it can't be replayed elsewhere.
+pub fn payload(
+    env: &Env,
+    contract: &Address,
+    order_hash: &BytesN<32>,
+    action: PermitAction,
+    nonce: u64,
+    expiry: u64,
+) -> Bytes {
+    let mut bytes = contract.clone().to_xdr(env);
+    bytes.extend_from_array(&order_hash.to_array());
+    bytes.extend_from_array(&(action as u32).to_be_bytes());
+    bytes.extend_from_array(&nonce.to_be_bytes());
+    bytes.extend_from_array(&expiry.to_be_bytes());
+    bytes
+}
+
+/// Verify a permit for `action` against the signer's ed25519 public key.
+/// Nonce bookkeeping is left to the caller, which owns the storage.
+pub fn verify(
+    env: &Env,
+    public_key: &BytesN<32>,
+    order_hash: &BytesN<32>,
+    action: PermitAction,
+    expected_nonce: u64,
+    permit: &Permit,
+) -> Result<(), Error> {
+    if permit.action != action {
+        return Err(Error::InvalidPermit);
+    }
+    if env.ledger().timestamp() >= permit.expiry {
+        return Err(Error::PermitExpired);
+    }
+    if permit.nonce != expected_nonce {
+        return Err(Error::InvalidPermit);
+    }
+    let message = payload(
+        env,
+        &env.current_contract_address(),
+        order_hash,
+        permit.action,
+        permit.nonce,
+        permit.expiry,
+    );
+    // Traps on a bad signature, aborting the whole invocation
+    env.crypto().ed25519_verify(public_key, &message, &permit.signature);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use soroban_sdk::{contracttype, symbol_short, BytesN, Env, Symbol};
+
+use crate::baseescrow::Error;
+use crate::events;
+
+/// Lifecycle stage of an escrow
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EscrowStage {
+    Created,
+    Withdrawn,
+    Cancelled,
+    Rescued,
+    Expired,
+    Stuck, // Flagged by the admin as unable to settle normally, only rescue is left
+    PartiallyWithdrawn, // Some parts of a withdrawal schedule paid out, the rest still held
+}
+
+/// First topic of every lifecycle event: ("escrow", order_hash, stage)
+pub const STAGE_EVENT_TOPIC: Symbol = symbol_short!("escrow");
+
+/// Payload of the lifecycle event, emitted by whichever contract handled the action
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StageEvent {
+    pub amount: i128,   // Swap amount the transition concerns
+    pub timestamp: u64, // Ledger timestamp of the transition
+}
+
+/// Allowed transitions matrix - every settlement path starts from Created or, once a first
+/// part was paid out, PartiallyWithdrawn, which becomes Withdrawn only with the full amount.
+/// A Stuck escrow can only be rescued and all other stages are terminal
+pub fn can_transition(from: &EscrowStage, to: &EscrowStage) -> bool {
+    matches!(
+        (from, to),
+        (EscrowStage::Created, EscrowStage::Withdrawn)
+            | (EscrowStage::Created, EscrowStage::PartiallyWithdrawn)
+            | (EscrowStage::Created, EscrowStage::Cancelled)
+            | (EscrowStage::Created, EscrowStage::Rescued)
+            | (EscrowStage::Created, EscrowStage::Expired)
+            | (EscrowStage::Created, EscrowStage::Stuck)
+            | (EscrowStage::PartiallyWithdrawn, EscrowStage::Withdrawn)
+            | (EscrowStage::PartiallyWithdrawn, EscrowStage::Cancelled)
+            | (EscrowStage::PartiallyWithdrawn, EscrowStage::Rescued)
+            | (EscrowStage::PartiallyWithdrawn, EscrowStage::Expired)
+            | (EscrowStage::PartiallyWithdrawn, EscrowStage::Stuck)
+            | (EscrowStage::Stuck, EscrowStage::Rescued)
+    )
+}
+
+/// Validate a transition without side effects (use before moving any funds)
+pub fn check_transition(from: &EscrowStage, to: &EscrowStage) -> Result<(), Error> {
+    if !can_transition(from, to) {
+        return Err(Error::InvalidStageTransition);
+    }
+    Ok(())
+}
+
+/// Emit the lifecycle event for an escrow entering a stage
+pub fn emit_stage(env: &Env, order_hash: &BytesN<32>, stage: EscrowStage, amount: i128) {
+    let payload = StageEvent {
+        amount,
+        timestamp: env.ledger().timestamp(),
+    };
+    events::publish(env, (STAGE_EVENT_TOPIC, order_hash.clone(), stage), payload);
+}
+
+/// Validate a transition and emit its lifecycle event
+pub fn transition(env: &Env, order_hash: &BytesN<32>, from: EscrowStage, to: EscrowStage, amount: i128) -> Result<(), Error> {
+    check_transition(&from, &to)?;
+    emit_stage(env, order_hash, to, amount);
+    Ok(())
+}
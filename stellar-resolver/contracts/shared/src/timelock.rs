@@ -1,4 +1,5 @@
 use soroban_sdk::{contracttype, Env, U256};
+use crate::layout::{self, stage_bit_offset, stage_byte_index, DEPLOYED_AT_BYTE_INDEX, DEPLOYED_AT_OFFSET, TIMELOCK_FIELD_BYTES, WORD_BYTES};
 use crate::types::TimeLockError;
 
 /// Timelock stages - must match Solidity exactly
@@ -14,8 +15,51 @@ pub enum Stage {
     DstCancellation = 6,
 }
 
+impl Stage {
+    /// Every stage in enum (and packing) order
+    pub const ALL: [Stage; 7] = [
+        Stage::SrcWithdrawal,
+        Stage::SrcPublicWithdrawal,
+        Stage::SrcCancellation,
+        Stage::SrcPublicCancellation,
+        Stage::DstWithdrawal,
+        Stage::DstPublicWithdrawal,
+        Stage::DstCancellation,
+    ];
+}
+
+/// A timelock schedule in any representation: a deployment time plus a uint32 offset per
+/// stage. Hashing and validation only go through these accessors, and the packed uint256
+/// is derived from them, so two representations of one schedule can't hash differently
+pub trait TimelocksRepr {
+    /// Deployment timestamp the stage offsets count from
+    fn deployed_at(&self, env: &Env) -> Result<u32, TimeLockError>;
+
+    /// Offset of a stage in seconds after deployment (not an absolute timestamp)
+    fn stage_offset(&self, env: &Env, stage: Stage) -> Result<u32, TimeLockError>;
+
+    /// Absolute timestamp of a stage (deployed_at + offset)
+    fn stage_timestamp(&self, env: &Env, stage: Stage) -> Result<u64, TimeLockError> {
+        (self.deployed_at(env)? as u64)
+            .checked_add(self.stage_offset(env, stage)? as u64)
+            .ok_or(TimeLockError::TimelockValueOverflow)
+    }
+
+    /// Canonical packing, the 32 big-endian bytes of Solidity's uint256 timelocks (see layout.rs)
+    fn to_packed_bytes(&self, env: &Env) -> Result<[u8; WORD_BYTES], TimeLockError> {
+        let mut word = [0u8; WORD_BYTES];
+        word[DEPLOYED_AT_BYTE_INDEX..DEPLOYED_AT_BYTE_INDEX + TIMELOCK_FIELD_BYTES]
+            .copy_from_slice(&self.deployed_at(env)?.to_be_bytes());
+        for stage in Stage::ALL {
+            let index = stage_byte_index(stage);
+            word[index..index + TIMELOCK_FIELD_BYTES].copy_from_slice(&self.stage_offset(env, stage)?.to_be_bytes());
+        }
+        Ok(word)
+    }
+}
+
 /// Timelocks - packed into single U256 value to match Solidity exactly
-/// This MUST match the Solidity TimelocksLib.sol bit packing exactly
+/// This MUST match the Solidity TimelocksLib.sol bit packing exactly, see layout.rs
 #[contracttype]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Timelocks {
@@ -25,10 +69,8 @@ pub struct Timelocks {
 }
 
 impl Timelocks {
-    /// Constants matching Solidity TimelocksLib.sol
-    const DEPLOYED_AT_OFFSET: u32 = 224;
-    
     /// Create new Timelocks with proper bit packing
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         env: &Env,
         deployed_at: u32,
@@ -43,119 +85,80 @@ impl Timelocks {
         let mut packed = U256::from_u32(env, 0);
         
         // Pack deployed_at (bits 224-255)
-        let deployed_at_shifted = U256::from_u32(env, deployed_at).shl(Self::DEPLOYED_AT_OFFSET);
+        let deployed_at_shifted = U256::from_u32(env, deployed_at).shl(DEPLOYED_AT_OFFSET);
         packed = packed.add(&deployed_at_shifted);
         
-        // Pack each timelock (32 bits each)
-        let src_withdrawal_shifted = U256::from_u32(env, src_withdrawal).shl(192);
-        packed = packed.add(&src_withdrawal_shifted);
-        
-        let src_public_withdrawal_shifted = U256::from_u32(env, src_public_withdrawal).shl(160);
-        packed = packed.add(&src_public_withdrawal_shifted);
-        
-        let src_cancellation_shifted = U256::from_u32(env, src_cancellation).shl(128);
-        packed = packed.add(&src_cancellation_shifted);
-        
-        let src_public_cancellation_shifted = U256::from_u32(env, src_public_cancellation).shl(96);
-        packed = packed.add(&src_public_cancellation_shifted);
-        
-        let dst_withdrawal_shifted = U256::from_u32(env, dst_withdrawal).shl(64);
-        packed = packed.add(&dst_withdrawal_shifted);
-        
-        let dst_public_withdrawal_shifted = U256::from_u32(env, dst_public_withdrawal).shl(32);
-        packed = packed.add(&dst_public_withdrawal_shifted);
-        
-        let dst_cancellation_shifted = U256::from_u32(env, dst_cancellation);
-        packed = packed.add(&dst_cancellation_shifted);
+        // Pack each timelock (32 bits each) at its stage's offset
+        let stages = [
+            (Stage::SrcWithdrawal, src_withdrawal),
+            (Stage::SrcPublicWithdrawal, src_public_withdrawal),
+            (Stage::SrcCancellation, src_cancellation),
+            (Stage::SrcPublicCancellation, src_public_cancellation),
+            (Stage::DstWithdrawal, dst_withdrawal),
+            (Stage::DstPublicWithdrawal, dst_public_withdrawal),
+            (Stage::DstCancellation, dst_cancellation),
+        ];
+        for (stage, offset) in stages {
+            packed = packed.add(&U256::from_u32(env, offset).shl(stage_bit_offset(stage)));
+        }
         
         Self { packed_value: packed }
     }
     
     /// Set deployed_at timestamp
-    pub fn set_deployed_at(&mut self, env: &Env, value: u32) {
-        // Create mask to clear deployed_at bits (invert the deployed_at mask)
-        let deployed_at_mask = U256::from_u32(env, 0xffffffff).shl(Self::DEPLOYED_AT_OFFSET);
-        let _clear_mask = deployed_at_mask; // In real implementation, this would be bitwise NOT
-        
-        // Clear the deployed_at bits and set new value
-        let _new_deployed_at = U256::from_u32(env, value).shl(Self::DEPLOYED_AT_OFFSET);
-        
-        // For simplicity, we'll reconstruct the entire value
-        // In production, you'd use proper bitwise operations
-        let current_bytes = self.packed_value.to_be_bytes();
-        let mut bytes_array = [0u8; 32];
-        
-        // Copy bytes from Soroban Bytes to array
-        for i in 0..32 {
-            bytes_array[i] = current_bytes.get(i as u32).unwrap_or(0);
-        }
+    pub fn set_deployed_at(&mut self, env: &Env, value: u32) -> Result<(), TimeLockError> {
+        let mut bytes_array = self.word()?;
         
         // Update deployed_at in the first 4 bytes (big-endian)
-        bytes_array[0..4].copy_from_slice(&value.to_be_bytes());
+        bytes_array[DEPLOYED_AT_BYTE_INDEX..DEPLOYED_AT_BYTE_INDEX + TIMELOCK_FIELD_BYTES].copy_from_slice(&value.to_be_bytes());
         
         // Convert back to U256
         let updated_bytes = soroban_sdk::Bytes::from_array(env, &bytes_array);
         self.packed_value = U256::from_be_bytes(env, &updated_bytes);
+        Ok(())
     }
     
     /// Get deployed_at timestamp (bits 224-255)
-    pub fn get_deployed_at(&self, _env: &Env) -> u32 {
-        let bytes = self.packed_value.to_be_bytes();
-        let mut deployed_at_bytes = [0u8; 4];
-        
-        // Extract first 4 bytes (deployed_at is in bits 224-255)
-        for i in 0..4 {
-            deployed_at_bytes[i] = bytes.get(i as u32).unwrap_or(0);
-        }
-        
-        u32::from_be_bytes(deployed_at_bytes)
+    pub fn get_deployed_at(&self, _env: &Env) -> Result<u32, TimeLockError> {
+        Ok(Self::field(&self.word()?, DEPLOYED_AT_BYTE_INDEX))
     }
     
     /// Get raw timelock offset for a stage (not absolute timestamp)
-    pub fn get_stage_offset(&self, _env: &Env, stage: Stage) -> u32 {
-        let bytes = self.packed_value.to_be_bytes();
-        let start_idx = match stage {
-            Stage::SrcWithdrawal => 4,
-            Stage::SrcPublicWithdrawal => 8,
-            Stage::SrcCancellation => 12,
-            Stage::SrcPublicCancellation => 16,
-            Stage::DstWithdrawal => 20,
-            Stage::DstPublicWithdrawal => 24,
-            Stage::DstCancellation => 28,
-        };
-        
-        let mut offset_bytes = [0u8; 4];
-        for i in 0..4 {
-            offset_bytes[i] = bytes.get((start_idx + i) as u32).unwrap_or(0);
-        }
-        
-        u32::from_be_bytes(offset_bytes)
+    pub fn get_stage_offset(&self, _env: &Env, stage: Stage) -> Result<u32, TimeLockError> {
+        Ok(Self::field(&self.word()?, layout::stage_byte_index(stage)))
     }
     
     /// Get absolute timestamp for a stage (deployed_at + offset)
     pub fn get_stage_timestamp(&self, env: &Env, stage: Stage) -> Result<u64, TimeLockError> {
-        let deployed_at = self.get_deployed_at(env) as u64;
-        let offset = self.get_stage_offset(env, stage) as u64;
-        
-        deployed_at
-            .checked_add(offset)
-            .ok_or(TimeLockError::TimelockValueOverflow)
+        self.stage_timestamp(env, stage)
     }
     
     /// Convert to 32-byte array for EVM-compatible hashing
-    pub fn to_bytes(&self, _env: &Env) -> [u8; 32] {
+    pub fn to_bytes(&self, env: &Env) -> Result<[u8; WORD_BYTES], TimeLockError> {
+        self.to_packed_bytes(env)
+    }
+    
+    /// The packed uint256 as its 32 big-endian bytes. Any other length means the value is
+    /// malformed: reading missing bytes as zeros would open every stage at deployed_at
+    fn word(&self) -> Result<[u8; WORD_BYTES], TimeLockError> {
         let bytes = self.packed_value.to_be_bytes();
-        let mut result = [0u8; 32];
-        
-        for i in 0..32 {
-            result[i] = bytes.get(i as u32).unwrap_or(0);
+        if bytes.len() as usize != WORD_BYTES {
+            return Err(TimeLockError::MalformedTimelocks);
         }
-        
-        result
+        let mut word = [0u8; WORD_BYTES];
+        bytes.copy_into_slice(&mut word);
+        Ok(word)
+    }
+    
+    /// The big-endian uint32 field starting at index
+    fn field(word: &[u8; WORD_BYTES], index: usize) -> u32 {
+        let mut field = [0u8; TIMELOCK_FIELD_BYTES];
+        field.copy_from_slice(&word[index..index + TIMELOCK_FIELD_BYTES]);
+        u32::from_be_bytes(field)
     }
     
     /// Create from 32-byte array (for cross-chain compatibility)
-    pub fn from_bytes(env: &Env, bytes: [u8; 32]) -> Self {
+    pub fn from_bytes(env: &Env, bytes: [u8; WORD_BYTES]) -> Self {
         let soroban_bytes = soroban_sdk::Bytes::from_array(env, &bytes);
         Self {
             packed_value: U256::from_be_bytes(env, &soroban_bytes),
@@ -163,6 +166,21 @@ impl Timelocks {
     }
 }
 
+impl TimelocksRepr for Timelocks {
+    fn deployed_at(&self, env: &Env) -> Result<u32, TimeLockError> {
+        self.get_deployed_at(env)
+    }
+
+    fn stage_offset(&self, env: &Env, stage: Stage) -> Result<u32, TimeLockError> {
+        self.get_stage_offset(env, stage)
+    }
+
+    // Every bit of the word is a field, so the packed value already is the canonical packing
+    fn to_packed_bytes(&self, _env: &Env) -> Result<[u8; WORD_BYTES], TimeLockError> {
+        self.word()
+    }
+}
+
 /// Timelocks module - functions for working with timelock data
 pub mod timelocks {
     use super::*;
@@ -173,16 +191,20 @@ pub mod timelocks {
         Timelocks,
     }
     
-    /// Set deployed_at timestamp and persist to storage
-    pub fn set_deployed_at(env: &Env, timelocks: &mut Timelocks, value: u64) {
-        // Note: deployed_at is u32 in the packed format (matching Solidity)
-        timelocks.set_deployed_at(env, value as u32);
+    /// Set deployed_at timestamp and persist to storage.
+    /// deployed_at is a uint32 in the packed format (matching Solidity), so timestamps
+    /// past 2106 are rejected instead of being truncated
+    #[cfg(feature = "storage")]
+    pub fn set_deployed_at(env: &Env, timelocks: &mut Timelocks, value: u64) -> Result<(), TimeLockError> {
+        let deployed_at = u32::try_from(value).map_err(|_| TimeLockError::DeployedAtOutOfRange)?;
+        timelocks.set_deployed_at(env, deployed_at)?;
         env.storage().persistent().set(&DataKey::Timelocks, timelocks);
+        Ok(())
     }
     
     /// Calculate rescue start time
     pub fn rescue_start(timelocks: &Timelocks, env: &Env, rescue_delay: u64) -> Result<u64, TimeLockError> {
-        let deployed_at = timelocks.get_deployed_at(env) as u64;
+        let deployed_at = timelocks.get_deployed_at(env)? as u64;
         deployed_at
             .checked_add(rescue_delay)
             .ok_or(TimeLockError::RescueStartOverflow)
@@ -193,18 +215,26 @@ pub mod timelocks {
         timelocks.get_stage_timestamp(env, stage)
     }
     
+    /// Get absolute timestamp for a stage pushed back by a finality lag, so the
+    /// counterpart chain can finalize before the stage opens
+    pub fn get_with_lag(timelocks: &Timelocks, env: &Env, stage: Stage, finality_lag: u64) -> Result<u64, TimeLockError> {
+        get(timelocks, env, stage)?
+            .checked_add(finality_lag)
+            .ok_or(TimeLockError::TimelockValueOverflow)
+    }
+    
     /// Validate timelock ordering and constraints
-    pub fn validate_timelocks(timelocks: &Timelocks, env: &Env) -> Result<(), TimeLockError> {
+    pub fn validate_timelocks<T: TimelocksRepr>(timelocks: &T, env: &Env) -> Result<(), TimeLockError> {
         // Ensure deployed_at is set
-        if timelocks.get_deployed_at(env) == 0 {
+        if timelocks.deployed_at(env)? == 0 {
             return Err(TimeLockError::DeploymentTimestampNotSet);
         }
         
         // Validate source chain ordering
-        let src_withdrawal = timelocks.get_stage_offset(env, Stage::SrcWithdrawal);
-        let src_public_withdrawal = timelocks.get_stage_offset(env, Stage::SrcPublicWithdrawal);
-        let src_cancellation = timelocks.get_stage_offset(env, Stage::SrcCancellation);
-        let src_public_cancellation = timelocks.get_stage_offset(env, Stage::SrcPublicCancellation);
+        let src_withdrawal = timelocks.stage_offset(env, Stage::SrcWithdrawal)?;
+        let src_public_withdrawal = timelocks.stage_offset(env, Stage::SrcPublicWithdrawal)?;
+        let src_cancellation = timelocks.stage_offset(env, Stage::SrcCancellation)?;
+        let src_public_cancellation = timelocks.stage_offset(env, Stage::SrcPublicCancellation)?;
         
         if src_withdrawal >= src_public_withdrawal
             || src_public_withdrawal >= src_cancellation
@@ -214,9 +244,9 @@ pub mod timelocks {
         }
         
         // Validate destination chain ordering
-        let dst_withdrawal = timelocks.get_stage_offset(env, Stage::DstWithdrawal);
-        let dst_public_withdrawal = timelocks.get_stage_offset(env, Stage::DstPublicWithdrawal);
-        let dst_cancellation = timelocks.get_stage_offset(env, Stage::DstCancellation);
+        let dst_withdrawal = timelocks.stage_offset(env, Stage::DstWithdrawal)?;
+        let dst_public_withdrawal = timelocks.stage_offset(env, Stage::DstPublicWithdrawal)?;
+        let dst_cancellation = timelocks.stage_offset(env, Stage::DstCancellation)?;
         
         if dst_withdrawal >= dst_public_withdrawal
             || dst_public_withdrawal >= dst_cancellation
@@ -227,12 +257,22 @@ pub mod timelocks {
         Ok(())
     }
     
+    /// Seconds from deployment until the last stage opens, i.e. how long the schedule can
+    /// hold funds before every cancellation path is available
+    pub fn horizon<T: TimelocksRepr>(timelocks: &T, env: &Env) -> Result<u64, TimeLockError> {
+        let last_src = timelocks.stage_offset(env, Stage::SrcPublicCancellation)?;
+        let last_dst = timelocks.stage_offset(env, Stage::DstCancellation)?;
+        Ok(last_src.max(last_dst) as u64)
+    }
+    
     /// Store timelocks in persistent storage
+    #[cfg(feature = "storage")]
     pub fn store_timelocks(env: &Env, timelocks: &Timelocks) {
         env.storage().persistent().set(&DataKey::Timelocks, timelocks);
     }
     
     /// Retrieve timelocks from persistent storage
+    #[cfg(feature = "storage")]
     pub fn get_timelocks(env: &Env) -> Option<Timelocks> {
         env.storage().persistent().get(&DataKey::Timelocks)
     }
@@ -0,0 +1,10 @@
+use soroban_sdk::{Env, IntoVal, Val};
+
+/// Keep a persistent entry alive for at least `ledgers` more ledgers. Missing entries are
+/// skipped, so the optional records of an escrow can be bumped alongside the required ones
+pub fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K, ledgers: u32) {
+    let storage = env.storage().persistent();
+    if storage.has(key) {
+        storage.extend_ttl(key, ledgers, ledgers);
+    }
+}
@@ -10,4 +10,6 @@ pub enum TimeLockError {
     InvalidSourceChainTimelockOrdering = 4,
     InvalidDestinationChainTimelockOrdering = 5,
     TimelockOffsetTooLarge = 6,
+    DeployedAtOutOfRange = 7,
+    MalformedTimelocks = 8, // Packed value isn't 32 bytes, its fields can't be read
 }
\ No newline at end of file
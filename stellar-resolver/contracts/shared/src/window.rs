@@ -0,0 +1,113 @@
+//! Time windows of an escrow's actions, each a pair of timelock stages.
+//!
+//! Every contract checks its windows through these helpers instead of pairing `only_after`
+//! and `only_before` on stages itself, so a window always opens at its start (inclusive),
+//! closes at its deadline (exclusive) and fails with the same error everywhere.
+
+use soroban_sdk::{contracttype, Env};
+
+use crate::baseescrow::{only_after, only_before_within, Error};
+use crate::timelock::{timelocks, Stage, Timelocks};
+
+/// Side of the swap an escrow holds, which decides the stages its windows use
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EscrowType {
+    Source,
+    Destination,
+}
+
+impl EscrowType {
+    fn withdrawal(&self) -> Stage {
+        match self {
+            EscrowType::Source => Stage::SrcWithdrawal,
+            EscrowType::Destination => Stage::DstWithdrawal,
+        }
+    }
+
+    fn public_withdrawal(&self) -> Stage {
+        match self {
+            EscrowType::Source => Stage::SrcPublicWithdrawal,
+            EscrowType::Destination => Stage::DstPublicWithdrawal,
+        }
+    }
+
+    fn cancellation(&self) -> Stage {
+        match self {
+            EscrowType::Source => Stage::SrcCancellation,
+            EscrowType::Destination => Stage::DstCancellation,
+        }
+    }
+}
+
+fn stage_time(env: &Env, timelocks: &Timelocks, stage: Stage) -> Result<u64, Error> {
+    timelocks::get(timelocks, env, stage).map_err(|_| Error::TimeLockError)
+}
+
+// The withdrawal stage pushed back by the finality lag, so the counterpart chain is final
+// before any withdrawal path opens
+fn withdraw_start(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType, finality_lag: u64) -> Result<u64, Error> {
+    timelocks::get_with_lag(timelocks, env, escrow_type.withdrawal(), finality_lag).map_err(|_| Error::TimeLockError)
+}
+
+// Withdrawals landing up to `tolerance` seconds after the deadline still pass
+fn in_withdraw_bounds(env: &Env, start: u64, deadline: u64, tolerance: u64) -> Result<(u64, u64), Error> {
+    only_after(env, start).map_err(|_| Error::TooEarlyForWithdrawal)?;
+    only_before_within(env, deadline, tolerance).map_err(|_| Error::WithdrawalWindowClosed)?;
+    Ok((start, deadline))
+}
+
+/// Taker-only withdrawal, from the withdrawal stage (plus the finality lag) until public
+/// withdrawal opens. Returns the window as (start, deadline)
+pub fn in_private_withdraw_window(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType, finality_lag: u64) -> Result<(u64, u64), Error> {
+    in_private_withdraw_window_within(env, timelocks, escrow_type, finality_lag, 0)
+}
+
+/// in_private_withdraw_window still open `tolerance` seconds past its deadline, for callers
+/// absorbing ledger close-time skew between the chains. The returned window is unchanged
+pub fn in_private_withdraw_window_within(
+    env: &Env,
+    timelocks: &Timelocks,
+    escrow_type: &EscrowType,
+    finality_lag: u64,
+    tolerance: u64,
+) -> Result<(u64, u64), Error> {
+    let start = withdraw_start(env, timelocks, escrow_type, finality_lag)?;
+    let deadline = stage_time(env, timelocks, escrow_type.public_withdrawal())?;
+    in_withdraw_bounds(env, start, deadline, tolerance)
+}
+
+/// Taker withdrawal that stays open through the public period, until cancellation opens
+/// (EscrowDst.withdraw on EVM). Returns the window as (start, deadline)
+pub fn in_withdraw_window(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType, finality_lag: u64) -> Result<(u64, u64), Error> {
+    let start = withdraw_start(env, timelocks, escrow_type, finality_lag)?;
+    let deadline = stage_time(env, timelocks, escrow_type.cancellation())?;
+    in_withdraw_bounds(env, start, deadline, 0)
+}
+
+/// Withdrawal by anyone, from public withdrawal (but never before the finality lag passed)
+/// until cancellation opens. Returns the window as (start, deadline)
+pub fn in_public_withdraw_window(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType, finality_lag: u64) -> Result<(u64, u64), Error> {
+    let public_start = stage_time(env, timelocks, escrow_type.public_withdrawal())?;
+    let start = public_start.max(withdraw_start(env, timelocks, escrow_type, finality_lag)?);
+    let deadline = stage_time(env, timelocks, escrow_type.cancellation())?;
+    in_withdraw_bounds(env, start, deadline, 0)
+}
+
+/// Cancellation by the funder, open from the cancellation stage on. Returns its start
+pub fn in_cancel_window(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType) -> Result<u64, Error> {
+    let start = stage_time(env, timelocks, escrow_type.cancellation())?;
+    only_after(env, start).map_err(|_| Error::CancellationNotReachable)?;
+    Ok(start)
+}
+
+/// Cancellation by anyone, open from the source chain's public cancellation stage on.
+/// Destination escrows have no public cancellation. Returns its start
+pub fn in_public_cancel_window(env: &Env, timelocks: &Timelocks, escrow_type: &EscrowType) -> Result<u64, Error> {
+    if *escrow_type != EscrowType::Source {
+        return Err(Error::CancellationNotReachable);
+    }
+    let start = stage_time(env, timelocks, Stage::SrcPublicCancellation)?;
+    only_after(env, start).map_err(|_| Error::CancellationNotReachable)?;
+    Ok(start)
+}
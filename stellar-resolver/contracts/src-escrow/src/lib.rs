@@ -6,7 +6,7 @@
 mod srcescrow;
 
 // Re-export the contract
-pub use srcescrow::SrcEscrow;
+pub use srcescrow::{SrcEscrow, SrcEscrowClient};
 
 #[cfg(test)]
 mod test;
@@ -3,9 +3,10 @@ use soroban_sdk::{
 symbol_short,
 };
 use shared::{
-    BaseEscrowTrait, EscrowError as Error, only_taker, only_valid_secret, only_before, only_after, uni_transfer,
+    BaseEscrowTrait, EscrowError as Error, PayoutSummary, only_taker, only_valid_secret, only_valid_cancel_secret, only_bound_immutables, uni_transfer, record_revealed_secret, get_revealed_secret,
     Immutables, other_immutables as immutables,
-    timelocks, Stage
+    timelocks, events, EscrowStage, EscrowType, Asset, in_private_withdraw_window, in_cancel_window,
+    get_stage, only_stage_allows, advance_stage
 };
 
 #[contract]
@@ -25,11 +26,11 @@ fn only_maker(env: &Env, immutables: &Immutables) -> Result<(), Error> {
 
 #[contractimpl]
 impl SrcEscrow {
-    /// Public initialization function called after deployment
-    pub fn init(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Symbol {
+    /// Public initialization function called after deployment, only once
+    pub fn init(env: Env, factory: Address, rescue_delay: u64, immutables: Immutables) -> Result<Symbol, Error> {
         // Use the BaseEscrow initialize method
-        <shared::baseescrow::BaseEscrow as BaseEscrowTrait>::initialize(env, factory, rescue_delay, immutables);
-        symbol_short!("init_ok")
+        <shared::baseescrow::BaseEscrow as BaseEscrowTrait>::initialize(env, factory, rescue_delay, immutables)?;
+        Ok(symbol_short!("init_ok"))
     }
 
     fn get_rescue_delay(env: Env) -> u64 {
@@ -46,50 +47,76 @@ impl SrcEscrow {
             .unwrap_or_else(|| panic!("Factory not set"))
     }
 
-    pub fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables) -> Result<(), Error> {
+    /// Taker withdraw, an optional target receives the swap amount instead of the taker (taker auth required)
+    pub fn withdraw(env: Env, secret: BytesN<32>, immutables: Immutables, target: Option<Address>) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Withdrawn)?;
         only_taker(&env, &immutables)?;
         only_valid_secret(&env, &secret, &immutables)?;
         src_validate_immutables(&env, &immutables)?;
-        let withdraw_deadline = timelocks::get(&immutables.timelocks, &env, Stage::SrcPublicWithdrawal).map_err(|_| Error::TimeLockError)?;
-        only_before(&env, withdraw_deadline)?;
+        // The withdrawal window opens only once the counterpart chain is final
+        in_private_withdraw_window(&env, &immutables.timelocks, &EscrowType::Source, immutables.finality_lag)?;
 
         // Get addresses
         let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
             .ok_or(Error::AddressMappingMissing)?;
+        let deposit_token = if immutables.native_deposit { Asset::native_address(&env) } else { stellar_token.clone() };
         let stellar_taker = immutables::get_stellar_addr(&env, &immutables.taker.evm)
             .ok_or(Error::AddressMappingMissing)?;
 
+        // Redirecting the payout is only allowed for the taker itself
+        let recipient = match target {
+            Some(target) => {
+                stellar_taker.require_auth();
+                target
+            }
+            None => stellar_taker.clone(),
+        };
+
         // ✅ FIX: Funds to taker, safety deposit to caller (incentive for revealing secret)
-        uni_transfer(&env, &stellar_token, &stellar_taker, immutables.amount)?;
+        uni_transfer(&env, &stellar_token, &recipient, immutables.amount)?;
         // Safety deposit to taker (simplified for now)
-        uni_transfer(&env, &stellar_token, &stellar_taker, immutables.safety_deposit)?;
-
-        env.events().publish((symbol_short!("Withdraw"), secret), immutables.amount);
-        Ok(())
+        uni_transfer(&env, &deposit_token, &stellar_taker, immutables.safety_deposit)?;
+
+        advance_stage(&env, &immutables.order_hash, EscrowStage::Withdrawn, immutables.amount)?;
+        record_revealed_secret(&env, &secret);
+        events::publish(&env, (symbol_short!("Withdraw"), secret), immutables.amount);
+        Ok(PayoutSummary {
+            recipient,
+            token: stellar_token,
+            amount: immutables.amount,
+            safety_deposit_recipient: stellar_taker,
+            safety_deposit_token: deposit_token,
+            safety_deposit_amount: immutables.safety_deposit,
+        })
     }
 
-    pub fn cancel(env: Env, immutables: Immutables) -> Result<(), Error> {
+    pub fn cancel(env: Env, immutables: Immutables) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Cancelled)?;
         only_maker(&env, &immutables)?;
         src_validate_immutables(&env, &immutables)?;
-        let cancel_time = timelocks::get(&immutables.timelocks, &env, Stage::SrcCancellation).map_err(|_| Error::TimeLockError)?;
-        only_after(&env, cancel_time)?;
+        in_cancel_window(&env, &immutables.timelocks, &EscrowType::Source)?;
 
-        // Get addresses
-        let stellar_token = immutables::get_stellar_addr(&env, &immutables.token.evm)
-            .ok_or(Error::AddressMappingMissing)?;
-        let stellar_maker = immutables::get_stellar_addr(&env, &immutables.maker.evm)
-            .ok_or(Error::AddressMappingMissing)?;
+        _src_cancel(&env, &immutables)
+    }
 
-        // ✅ FIX: Funds back to maker, safety deposit to caller (incentive for cleanup)
-        uni_transfer(&env, &stellar_token, &stellar_maker, immutables.amount)?;
-        // Safety deposit to maker (simplified for now)
-        uni_transfer(&env, &stellar_token, &stellar_maker, immutables.safety_deposit)?;
+    /// Early cancel with the pre-image of the order's cancel hashlock (maker or relayer submits)
+    pub fn cooperative_cancel(env: Env, cancel_secret: BytesN<32>, immutables: Immutables) -> Result<PayoutSummary, Error> {
+        only_stage_allows(&env, &EscrowStage::Cancelled)?;
+        only_valid_cancel_secret(&env, &cancel_secret, &immutables)?;
+        src_validate_immutables(&env, &immutables)?;
 
-        env.events().publish((symbol_short!("Cancelled"),), immutables.amount);
-        Ok(())
+        _src_cancel(&env, &immutables)
     }
 
 
+    pub fn src_revealed_secret(env: Env, hashlock: BytesN<32>) -> Option<BytesN<32>> {
+        get_revealed_secret(&env, &hashlock)
+    }
+
+    pub fn src_stage(env: Env) -> EscrowStage {
+        get_stage(&env)
+    }
+
     pub fn src_rescue_delay(env: Env) -> u64 {
         Self::get_rescue_delay(env)
     }
@@ -99,15 +126,40 @@ impl SrcEscrow {
     }
 }
 
+// Funds back to maker, shared by both cancel paths
+fn _src_cancel(env: &Env, immutables: &Immutables) -> Result<PayoutSummary, Error> {
+    // Get addresses
+    let stellar_token = immutables::get_stellar_addr(env, &immutables.token.evm)
+        .ok_or(Error::AddressMappingMissing)?;
+    let deposit_token = if immutables.native_deposit { Asset::native_address(env) } else { stellar_token.clone() };
+    let stellar_maker = immutables::get_stellar_addr(env, &immutables.maker.evm)
+        .ok_or(Error::AddressMappingMissing)?;
+
+    // ✅ FIX: Funds back to maker, safety deposit to caller (incentive for cleanup)
+    uni_transfer(env, &stellar_token, &stellar_maker, immutables.amount)?;
+    // Safety deposit back to the sponsor if one provided it, else to maker (simplified for now)
+    let deposit_recipient = immutables.sponsor.clone().unwrap_or(stellar_maker.clone());
+    uni_transfer(env, &deposit_token, &deposit_recipient, immutables.safety_deposit)?;
+
+    advance_stage(env, &immutables.order_hash, EscrowStage::Cancelled, immutables.amount)?;
+    events::publish(env, (symbol_short!("Cancelled"),), immutables.amount);
+    Ok(PayoutSummary {
+        recipient: stellar_maker.clone(),
+        token: stellar_token,
+        amount: immutables.amount,
+        safety_deposit_recipient: deposit_recipient,
+        safety_deposit_token: deposit_token,
+        safety_deposit_amount: immutables.safety_deposit,
+    })
+}
+
 // Specific validate_immutables for SrcEscrow
 fn src_validate_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
-    if immutables.amount <= 0 || immutables.safety_deposit <= 0 {
-        return Err(Error::InvalidImmutables);
-    }
+    immutables.checked_amount()?;
+    immutables.checked_safety_deposit()?;
     timelocks::validate_timelocks(&immutables.timelocks, env).map_err(|_| Error::TimeLockError)?;
-    if immutables::hash(env, immutables).map_err(|_| Error::TimeLockError)? != immutables.order_hash {
-        return Err(Error::InvalidImmutables);
-    }
+    // Must be the immutables the escrow was initialized with, not substituted parameters
+    only_bound_immutables(env, immutables)?;
     // Verify Stellar addresses exist
     if immutables::get_stellar_addr(env,
 &immutables.maker.evm).is_none() ||
@@ -0,0 +1,87 @@
+//! Test fixtures shared by the contracts' test modules.
+//!
+//! The `create_test_*` helpers build the small synthetic orders the unit tests
+//! use everywhere. `vectors` adds realistic ones: mainnet EVM token and account
+//! addresses with their real decimals, and a minutes-scale timelock schedule
+//! like production Fusion+ orders use, for tests that should look like traffic.
+//!
+//! Only `shared`'s own unit tests can't use this crate, they would see a second
+//! copy of its types.
+
+#![no_std]
+
+use shared::{DualAddress, Immutables, LopOrder, Timelocks};
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Address, Bytes, BytesN, Env, Vec};
+
+pub mod vectors;
+
+/// Party with a fresh Stellar address and an EVM address derived from it, so every
+/// party gets its own mapping
+pub fn create_test_dual_address(env: &Env) -> DualAddress {
+    let stellar_addr = Address::generate(env);
+    let digest: BytesN<32> = env.crypto().keccak256(&stellar_addr.clone().to_xdr(env)).into();
+    let mut evm_bytes = [0u8; 20];
+    evm_bytes.copy_from_slice(&digest.to_array()[..20]);
+    DualAddress {
+        evm: BytesN::from_array(env, &evm_bytes),
+        stellar: stellar_addr,
+    }
+}
+
+/// Party with a given Stellar address (typically a token) and EVM address 0x4242..42<suffix>
+pub fn create_test_dual_address_with_stellar(env: &Env, stellar_addr: Address, evm_suffix: u8) -> DualAddress {
+    let mut evm_bytes = [0x42; 20];
+    evm_bytes[19] = evm_suffix; // Make unique EVM addresses
+    DualAddress {
+        evm: BytesN::from_array(env, &evm_bytes),
+        stellar: stellar_addr,
+    }
+}
+
+/// (secret, keccak256 hashlock) pair
+pub fn create_test_secret(env: &Env) -> (BytesN<32>, BytesN<32>) {
+    let secret = BytesN::from_array(env, &[0x42; 32]);
+    let hashlock = env.crypto().keccak256(&Bytes::from_array(env, &secret.to_array())).into();
+    (secret, hashlock)
+}
+
+/// Order of 1000 with a safety deposit of 100, deployed at 1000 with stages 100-400 seconds
+/// later on the source chain and 150-350 on the destination chain, no Stellar-only extensions
+pub fn create_test_immutables_with_secret(env: &Env, _secret: BytesN<32>, hashlock: BytesN<32>) -> Immutables {
+    Immutables {
+        order_hash: BytesN::from_array(env, &[0x01; 32]),
+        hashlock,
+        hashlocks: Vec::new(env),
+        cancel_hashlock: None,
+        sponsor: None,
+        maker: create_test_dual_address(env),
+        taker: create_test_dual_address(env),
+        token: create_test_dual_address(env),
+        amount: 1000,
+        safety_deposit: 100,
+        timelocks: Timelocks::new(
+            env, 1000, // deployed_at
+            100,  // src_withdrawal
+            200,  // src_public_withdrawal
+            300,  // src_cancellation
+            400,  // src_public_cancellation
+            150,  // dst_withdrawal
+            250,  // dst_public_withdrawal
+            350,  // dst_cancellation
+        ),
+        expiration: 0,
+        rescue_delay: 0,
+        finality_lag: 0,
+        src_chain_id: 0,
+        dst_chain_id: 0,
+        parts: Vec::new(env),
+        native_deposit: false,
+        deposit_decay_bps: 0,
+        stellar_origin: false,
+        lop_order: LopOrder::Unrecorded,
+        attestation_required: false,
+        parent: None,
+    }
+}
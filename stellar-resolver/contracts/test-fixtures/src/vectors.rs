@@ -0,0 +1,122 @@
+//! Realistic fixture vectors: mainnet token contracts with their real decimals, the
+//! well-known Anvil/Hardhat dev accounts as maker and resolver, and a timelock schedule
+//! with minutes-scale stages and finality slack like production Fusion+ orders.
+
+use shared::{DualAddress, Immutables, LopOrder, Timelocks};
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{Address, BytesN, Env, Vec};
+
+/// Decimals of every classic Stellar asset
+pub const STELLAR_DECIMALS: u32 = 7;
+
+/// Ethereum mainnet chain id
+pub const ETHEREUM_CHAIN_ID: u64 = 1;
+
+/// An ERC-20 as deployed on Ethereum mainnet
+#[derive(Clone, Copy, Debug)]
+pub struct TokenVector {
+    pub symbol: &'static str,
+    pub evm: [u8; 20],
+    pub decimals: u32,
+}
+
+pub const USDC: TokenVector = TokenVector {
+    symbol: "USDC",
+    evm: hex20("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+    decimals: 6,
+};
+
+pub const USDT: TokenVector = TokenVector {
+    symbol: "USDT",
+    evm: hex20("dac17f958d2ee523a2206206994597c13d831ec7"),
+    decimals: 6,
+};
+
+pub const WETH: TokenVector = TokenVector {
+    symbol: "WETH",
+    evm: hex20("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+    decimals: 18,
+};
+
+pub const DAI: TokenVector = TokenVector {
+    symbol: "DAI",
+    evm: hex20("6b175474e89094c44da98b954eedeac495271d0f"),
+    decimals: 18,
+};
+
+/// Anvil/Hardhat dev account #0, the maker
+pub const MAKER: [u8; 20] = hex20("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+
+/// Anvil/Hardhat dev account #1, the resolver
+pub const RESOLVER: [u8; 20] = hex20("70997970c51812dc3a010c7d01b50e0d17dc79c8");
+
+/// Stage offsets in seconds, in Timelocks::new order: the source chain's withdrawal opens
+/// after 2 minutes of finality slack, public withdrawal after ~8, cancellation after 17
+/// and public cancellation after 19; the destination side closes before the source does
+pub const FUSION_PLUS_SCHEDULE: [u32; 7] = [120, 500, 1020, 1140, 120, 380, 900];
+
+/// `whole` tokens in base units of a token with `decimals`
+pub const fn units(whole: i128, decimals: u32) -> i128 {
+    whole * 10i128.pow(decimals)
+}
+
+/// FUSION_PLUS_SCHEDULE packed for an escrow deployed at `deployed_at`
+pub fn fusion_plus_timelocks(env: &Env, deployed_at: u32) -> Timelocks {
+    let [a, b, c, d, e, f, g] = FUSION_PLUS_SCHEDULE;
+    Timelocks::new(env, deployed_at, a, b, c, d, e, f, g)
+}
+
+/// Order selling `amount` of `token`, escrowed on Stellar as `stellar_token`, from MAKER
+/// to RESOLVER, with a 1% safety deposit and the Fusion+ schedule deployed at 1000
+pub fn realistic_immutables(env: &Env, token: &TokenVector, stellar_token: Address, hashlock: BytesN<32>, amount: i128) -> Immutables {
+    let party = |evm: &[u8; 20], stellar: Address| DualAddress {
+        evm: BytesN::from_array(env, evm),
+        stellar,
+    };
+    Immutables {
+        order_hash: BytesN::from_array(env, &[0x0f; 32]),
+        hashlock,
+        hashlocks: Vec::new(env),
+        cancel_hashlock: None,
+        sponsor: None,
+        maker: party(&MAKER, Address::generate(env)),
+        taker: party(&RESOLVER, Address::generate(env)),
+        token: party(&token.evm, stellar_token),
+        amount,
+        safety_deposit: (amount / 100).max(1),
+        timelocks: fusion_plus_timelocks(env, 1000),
+        expiration: 0,
+        rescue_delay: 0,
+        finality_lag: 0,
+        src_chain_id: 0,
+        dst_chain_id: 0,
+        parts: Vec::new(env),
+        native_deposit: false,
+        deposit_decay_bps: 0,
+        stellar_origin: false,
+        lop_order: LopOrder::Unrecorded,
+        attestation_required: false,
+        parent: None,
+    }
+}
+
+// Lowercase hex to bytes at compile time
+const fn hex20(hex: &str) -> [u8; 20] {
+    let hex = hex.as_bytes();
+    assert!(hex.len() == 40);
+    let mut bytes = [0u8; 20];
+    let mut i = 0;
+    while i < 20 {
+        bytes[i] = nibble(hex[2 * i]) << 4 | nibble(hex[2 * i + 1]);
+        i += 1;
+    }
+    bytes
+}
+
+const fn nibble(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => panic!("not a lowercase hex digit"),
+    }
+}
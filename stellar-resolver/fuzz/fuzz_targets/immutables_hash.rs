@@ -0,0 +1,87 @@
+#![no_main]
+
+//! immutables::hash never panics on arbitrary field values: it errors exactly on amounts
+//! the EVM encoding can't carry, and otherwise is keccak256 of encode, deterministic and
+//! with the Solidity-compatible head of eight words
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use shared::layout::IMMUTABLES_ENCODED_BYTES;
+use shared::{other_immutables as immutables, LopOrder, LopOrderFields, Timelocks, WithdrawalPart};
+use soroban_sdk::testutils::EnvTestConfig;
+use soroban_sdk::{Bytes, BytesN, Env, U256};
+use test_fixtures::create_test_immutables_with_secret;
+
+// Parts a schedule can have, see the factory's MAX_WITHDRAWAL_PARTS
+const MAX_PARTS: usize = 32;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    order_hash: [u8; 32],
+    hashlock: [u8; 32],
+    backup_hashlocks: Vec<[u8; 32]>,
+    cancel_hashlock: Option<[u8; 32]>,
+    maker: [u8; 20],
+    taker: [u8; 20],
+    token: [u8; 20],
+    amount: i128,
+    safety_deposit: i128,
+    timelocks: [u8; 32],
+    src_chain_id: u64,
+    dst_chain_id: u64,
+    parts: Vec<([u8; 32], i128)>,
+    native_deposit: bool,
+    deposit_decay_bps: u32,
+    stellar_origin: bool,
+    lop_order: Option<([u8; 32], [u8; 32], [u8; 20])>,
+}
+
+fuzz_target!(|input: Input| {
+    let env = Env::new_with_config(EnvTestConfig { capture_snapshot_at_drop: false });
+    let hashlock = BytesN::from_array(&env, &input.hashlock);
+    let mut immutables = create_test_immutables_with_secret(&env, hashlock.clone(), hashlock);
+    immutables.order_hash = BytesN::from_array(&env, &input.order_hash);
+    for backup in input.backup_hashlocks.iter() {
+        immutables.hashlocks.push_back(BytesN::from_array(&env, backup));
+    }
+    immutables.cancel_hashlock = input.cancel_hashlock.map(|cancel| BytesN::from_array(&env, &cancel));
+    immutables.maker.evm = BytesN::from_array(&env, &input.maker);
+    immutables.taker.evm = BytesN::from_array(&env, &input.taker);
+    immutables.token.evm = BytesN::from_array(&env, &input.token);
+    immutables.amount = input.amount;
+    immutables.safety_deposit = input.safety_deposit;
+    immutables.timelocks = Timelocks::from_bytes(&env, input.timelocks);
+    immutables.src_chain_id = input.src_chain_id;
+    immutables.dst_chain_id = input.dst_chain_id;
+    for (part_hashlock, amount) in input.parts.iter().take(MAX_PARTS) {
+        immutables.parts.push_back(WithdrawalPart { hashlock: BytesN::from_array(&env, part_hashlock), amount: *amount });
+    }
+    immutables.native_deposit = input.native_deposit;
+    immutables.deposit_decay_bps = input.deposit_decay_bps;
+    immutables.stellar_origin = input.stellar_origin;
+    if let Some((salt, maker_traits, receiver)) = input.lop_order {
+        immutables.lop_order = LopOrder::Recorded(LopOrderFields {
+            salt: U256::from_be_bytes(&env, &Bytes::from_array(&env, &salt)),
+            maker_traits: U256::from_be_bytes(&env, &Bytes::from_array(&env, &maker_traits)),
+            receiver: BytesN::from_array(&env, &receiver),
+        });
+    }
+
+    let encoded = immutables::encode(&env, &immutables);
+    let hash = immutables::hash(&env, &immutables);
+    let encodable = input.amount > 0
+        && input.safety_deposit >= 0
+        && immutables.parts.iter().all(|part| part.amount > 0);
+    assert_eq!(encoded.is_ok(), encodable);
+    assert_eq!(hash.is_ok(), encodable);
+
+    if let (Ok(encoded), Ok(hash)) = (encoded, hash) {
+        let digest: BytesN<32> = env.crypto().keccak256(&encoded).into();
+        assert_eq!(hash, digest);
+        assert_eq!(immutables::hash(&env, &immutables), Ok(hash));
+        assert!(encoded.len() as usize >= IMMUTABLES_ENCODED_BYTES);
+        // The head is the Solidity abi.encode, starting with the order hash and hashlock
+        let head = Bytes::from_array(&env, &input.order_hash);
+        assert_eq!(encoded.slice(0..32), head);
+    }
+});
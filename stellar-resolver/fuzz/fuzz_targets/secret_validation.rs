@@ -0,0 +1,61 @@
+#![no_main]
+
+//! only_valid_secret / only_valid_preimage accept exactly the pre-images of the order's
+//! committed hashlocks and fail with a fixed error otherwise, whatever the input
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use shared::baseescrow::MAX_PREIMAGE_BYTES;
+use shared::{only_valid_preimage, only_valid_secret, EscrowError};
+use soroban_sdk::testutils::EnvTestConfig;
+use soroban_sdk::{Bytes, BytesN, Env};
+use test_fixtures::create_test_immutables_with_secret;
+
+// Backup hashlocks an order carries at most in practice
+const MAX_BACKUP_HASHLOCKS: usize = 8;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    secret: [u8; 32],
+    hashlock: [u8; 32],
+    // Commit to the secret itself, so the accepting path is reached as well
+    commit_to_secret: bool,
+    backup_hashlocks: Vec<[u8; 32]>,
+    preimage: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let env = Env::new_with_config(EnvTestConfig { capture_snapshot_at_drop: false });
+    let keccak = |bytes: &Bytes| -> BytesN<32> { env.crypto().keccak256(bytes).into() };
+
+    let secret = BytesN::from_array(&env, &input.secret);
+    let secret_hash = keccak(&Bytes::from_array(&env, &input.secret));
+    let hashlock = match input.commit_to_secret {
+        true => secret_hash.clone(),
+        false => BytesN::from_array(&env, &input.hashlock),
+    };
+    let mut immutables = create_test_immutables_with_secret(&env, secret.clone(), hashlock.clone());
+    for backup in input.backup_hashlocks.iter().take(MAX_BACKUP_HASHLOCKS) {
+        immutables.hashlocks.push_back(BytesN::from_array(&env, backup));
+    }
+    let unlocks = |digest: &BytesN<32>| *digest == hashlock || immutables.hashlocks.contains(digest);
+
+    let result = only_valid_secret(&env, &secret, &immutables);
+    match unlocks(&secret_hash) {
+        true => assert_eq!(result, Ok(())),
+        false => assert_eq!(result, Err(EscrowError::InvalidSecret)),
+    }
+    // A 32-byte preimage goes through the same check as a secret
+    assert_eq!(only_valid_preimage(&env, &secret.into(), &immutables), result);
+
+    // Any other length: the length check first, then the same hashlock check
+    let preimage = Bytes::from_slice(&env, &input.preimage);
+    let result = only_valid_preimage(&env, &preimage, &immutables);
+    if preimage.is_empty() || preimage.len() > MAX_PREIMAGE_BYTES {
+        assert_eq!(result, Err(EscrowError::InvalidPreimageLength));
+    } else if unlocks(&keccak(&preimage)) {
+        assert_eq!(result, Ok(()));
+    } else {
+        assert_eq!(result, Err(EscrowError::InvalidSecret));
+    }
+});
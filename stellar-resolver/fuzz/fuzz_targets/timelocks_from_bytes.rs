@@ -0,0 +1,70 @@
+#![no_main]
+
+//! Timelocks::from_bytes takes any 32 bytes: unpacking them round-trips, every stage reads
+//! back as deployed_at plus its big-endian offset, and the validation and timestamp helpers
+//! return errors instead of panicking
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use shared::layout::{stage_byte_index, DEPLOYED_AT_BYTE_INDEX, TIMELOCK_FIELD_BYTES};
+use shared::{timelocks, Stage, TimeLockError, Timelocks};
+use soroban_sdk::testutils::EnvTestConfig;
+use soroban_sdk::Env;
+
+const STAGES: [Stage; 7] = [
+    Stage::SrcWithdrawal,
+    Stage::SrcPublicWithdrawal,
+    Stage::SrcCancellation,
+    Stage::SrcPublicCancellation,
+    Stage::DstWithdrawal,
+    Stage::DstPublicWithdrawal,
+    Stage::DstCancellation,
+];
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    packed: [u8; 32],
+    deployed_at: u32,
+    rescue_delay: u64,
+    finality_lag: u64,
+}
+
+fn field(bytes: &[u8; 32], index: usize) -> u32 {
+    let mut field = [0u8; TIMELOCK_FIELD_BYTES];
+    field.copy_from_slice(&bytes[index..index + TIMELOCK_FIELD_BYTES]);
+    u32::from_be_bytes(field)
+}
+
+fuzz_target!(|input: Input| {
+    let env = Env::new_with_config(EnvTestConfig { capture_snapshot_at_drop: false });
+    let mut timelocks = Timelocks::from_bytes(&env, input.packed);
+    assert_eq!(timelocks.to_bytes(&env).unwrap(), input.packed);
+
+    let deployed_at = field(&input.packed, DEPLOYED_AT_BYTE_INDEX);
+    assert_eq!(timelocks.get_deployed_at(&env).unwrap(), deployed_at);
+    for stage in STAGES {
+        let offset = field(&input.packed, stage_byte_index(stage));
+        assert_eq!(timelocks.get_stage_offset(&env, stage).unwrap(), offset);
+        // Two u32s always fit a u64
+        assert_eq!(timelocks::get(&timelocks, &env, stage), Ok(deployed_at as u64 + offset as u64));
+        let lagged = (deployed_at as u64 + offset as u64).checked_add(input.finality_lag);
+        assert_eq!(
+            timelocks::get_with_lag(&timelocks, &env, stage, input.finality_lag),
+            lagged.ok_or(TimeLockError::TimelockValueOverflow)
+        );
+    }
+    assert_eq!(
+        timelocks::rescue_start(&timelocks, &env, input.rescue_delay),
+        (deployed_at as u64).checked_add(input.rescue_delay).ok_or(TimeLockError::RescueStartOverflow)
+    );
+    // Any 32 bytes are well formed, validation only ever rejects the schedule itself
+    assert_ne!(timelocks::validate_timelocks(&timelocks, &env), Err(TimeLockError::MalformedTimelocks));
+    assert!(timelocks::horizon(&timelocks, &env).unwrap() <= u32::MAX as u64);
+
+    // Rewriting deployed_at leaves every stage offset untouched
+    timelocks.set_deployed_at(&env, input.deployed_at).unwrap();
+    assert_eq!(timelocks.get_deployed_at(&env).unwrap(), input.deployed_at);
+    for stage in STAGES {
+        assert_eq!(timelocks.get_stage_offset(&env, stage).unwrap(), field(&input.packed, stage_byte_index(stage)));
+    }
+});